@@ -1,7 +1,11 @@
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
+use crate::data::sqlite::backend::{ConnectionSource, DefaultConnectionSource};
+use crate::data::storage::attachment_blob::{AttachmentBlobStorage, LocalFsAttachmentBlobStorage};
+use crate::data::storage::vault_blob::{LocalFsVaultBlobStorage, VaultBlobStorage};
 use crate::data::storage_paths::StoragePaths;
+use crate::data::sync::VaultSyncTransport;
 use crate::error::{ErrorCodeString, Result};
 
 use zeroize::Zeroizing;
@@ -21,10 +25,39 @@ pub struct AppState {
     pub vault_persist_requested: AtomicBool,
     pub vault_persist_in_flight: AtomicBool,
     pub backup_guard: Mutex<()>,
+    pub connection_source: Arc<dyn ConnectionSource>,
+    /// Where the active profile's encrypted vault DB and envelope master
+    /// key blobs actually live. Defaults to the local filesystem; settings
+    /// can swap this for a remote (e.g. S3-compatible) backend so those
+    /// blobs sync across machines. See `data::storage::vault_blob`.
+    pub vault_blob_storage: Mutex<Arc<dyn VaultBlobStorage>>,
+    /// Where attachment ciphertext actually lives. Defaults to the local
+    /// filesystem, same as `vault_blob_storage`; see
+    /// `data::storage::attachment_blob`.
+    pub attachment_blob_storage: Mutex<Arc<dyn AttachmentBlobStorage>>,
+    /// `None` unless the user has opted into remote sync; see
+    /// `services::sync_service`.
+    pub sync_transport: Mutex<Option<Arc<dyn VaultSyncTransport>>>,
 }
 
 impl AppState {
     pub fn new(storage_paths: StoragePaths) -> Self {
+        Self::with_connection_source(storage_paths, Arc::new(DefaultConnectionSource))
+    }
+
+    /// Builds an `AppState` that routes `repo_impl`'s queries through a
+    /// caller-supplied `ConnectionSource` instead of the default pooled
+    /// connection lookup — e.g. to pin every query to one scratch database
+    /// during a bulk import.
+    pub fn with_connection_source(
+        storage_paths: StoragePaths,
+        connection_source: Arc<dyn ConnectionSource>,
+    ) -> Self {
+        let vault_blob_storage: Arc<dyn VaultBlobStorage> =
+            Arc::new(LocalFsVaultBlobStorage::new(storage_paths.clone()));
+        let attachment_blob_storage: Arc<dyn AttachmentBlobStorage> =
+            Arc::new(LocalFsAttachmentBlobStorage::new(storage_paths.clone()));
+
         Self {
             active_profile: Mutex::new(None),
             storage_paths: Mutex::new(storage_paths),
@@ -34,33 +67,119 @@ impl AppState {
             vault_persist_requested: AtomicBool::new(false),
             vault_persist_in_flight: AtomicBool::new(false),
             backup_guard: Mutex::new(()),
+            connection_source,
+            vault_blob_storage: Mutex::new(vault_blob_storage),
+            attachment_blob_storage: Mutex::new(attachment_blob_storage),
+            sync_transport: Mutex::new(None),
         }
     }
 
+    /// Installs (or replaces) the vault blob storage backend, e.g. to switch
+    /// from the local filesystem to a configured remote object store.
+    pub fn set_vault_blob_storage(&self, storage: Arc<dyn VaultBlobStorage>) -> Result<()> {
+        let mut slot = self
+            .vault_blob_storage
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        *slot = storage;
+        Ok(())
+    }
+
+    pub fn get_vault_blob_storage(&self) -> Result<Arc<dyn VaultBlobStorage>> {
+        self.vault_blob_storage
+            .lock()
+            .map(|guard| guard.clone())
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))
+    }
+
+    /// Installs (or replaces) the attachment blob storage backend, e.g. to
+    /// switch from the local filesystem to a configured remote object
+    /// store.
+    pub fn set_attachment_blob_storage(&self, storage: Arc<dyn AttachmentBlobStorage>) -> Result<()> {
+        let mut slot = self
+            .attachment_blob_storage
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        *slot = storage;
+        Ok(())
+    }
+
+    pub fn get_attachment_blob_storage(&self) -> Result<Arc<dyn AttachmentBlobStorage>> {
+        self.attachment_blob_storage
+            .lock()
+            .map(|guard| guard.clone())
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))
+    }
+
+    /// Installs (or replaces) the remote sync transport. Pass `None` to
+    /// disable sync.
+    pub fn set_sync_transport(&self, transport: Option<Arc<dyn VaultSyncTransport>>) -> Result<()> {
+        let mut slot = self
+            .sync_transport
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        *slot = transport;
+        Ok(())
+    }
+
     pub fn set_workspace_root(&self, workspace_root: std::path::PathBuf) -> Result<()> {
-        {
+        let storage_paths = {
             let mut storage_paths = self
                 .storage_paths
                 .lock()
                 .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
             storage_paths.configure_workspace(workspace_root)?;
-        }
+            storage_paths.clone()
+        };
+        self.reset_local_vault_blob_storage(storage_paths.clone())?;
+        self.reset_local_attachment_blob_storage(storage_paths)?;
         self.clear_security_state()?;
         Ok(())
     }
 
     pub fn clear_workspace_root(&self) -> Result<()> {
-        {
+        let storage_paths = {
             let mut storage_paths = self
                 .storage_paths
                 .lock()
                 .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
             storage_paths.clear_workspace();
-        }
+            storage_paths.clone()
+        };
+        self.reset_local_vault_blob_storage(storage_paths.clone())?;
+        self.reset_local_attachment_blob_storage(storage_paths)?;
         self.clear_security_state()?;
         Ok(())
     }
 
+    /// Re-points the default local vault blob backend at the new workspace
+    /// directory. No-op if a remote backend has since been installed via
+    /// `set_vault_blob_storage` — only the built-in local default tracks the
+    /// workspace path.
+    fn reset_local_vault_blob_storage(&self, storage_paths: StoragePaths) -> Result<()> {
+        let mut slot = self
+            .vault_blob_storage
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        if slot.is_local_default() {
+            *slot = Arc::new(LocalFsVaultBlobStorage::new(storage_paths));
+        }
+        Ok(())
+    }
+
+    /// Same re-pointing as `reset_local_vault_blob_storage`, for the
+    /// attachment blob backend.
+    fn reset_local_attachment_blob_storage(&self, storage_paths: StoragePaths) -> Result<()> {
+        let mut slot = self
+            .attachment_blob_storage
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        if slot.is_local_default() {
+            *slot = Arc::new(LocalFsAttachmentBlobStorage::new(storage_paths));
+        }
+        Ok(())
+    }
+
     pub fn get_storage_paths(&self) -> Result<StoragePaths> {
         let storage_paths = self
             .storage_paths
@@ -72,11 +191,37 @@ impl AppState {
         Ok(storage_paths.clone())
     }
 
+    /// The active vault session's derived key for `profile_id`. Most
+    /// callers that need a connection should go through
+    /// `sqlite::backend::open_vault_connection` instead, which already
+    /// picks the pooled file vs. the session connection for them; this is
+    /// for callers that need the raw key itself rather than a connection
+    /// (`oplog_service`'s payload encryption, `backup_service`'s chunk-store
+    /// sealing). `None` if `profile_id` is passwordless or isn't the
+    /// currently unlocked session.
+    pub fn vault_key_for(&self, profile_id: &str) -> Result<Option<[u8; 32]>> {
+        let session = self
+            .vault_session
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        Ok(session
+            .as_ref()
+            .filter(|s| s.profile_id == profile_id)
+            .map(|s| *s.key))
+    }
+
     pub fn logout_and_cleanup(self: &Arc<Self>) -> Result<()> {
         crate::services::security_service::lock_vault(self)?;
 
         crate::data::sqlite::pool::clear_all_pools();
 
+        // Revoke the browser extension's bridge access along with the rest
+        // of the session: once native-host.json is gone, `pm-native-host`
+        // has nothing to forward requests to.
+        if let Ok(storage_paths) = self.storage_paths.lock() {
+            crate::ipc::registry::remove_ipc_info(storage_paths.app_dir());
+        }
+
         self.clear_security_state()?;
 
         Ok(())
@@ -1,10 +1,73 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose;
+use base64::Engine;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::Sha256;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::path::{Path, PathBuf};
+use x25519_dalek::{PublicKey, StaticSecret};
 
 const MAX_FRAME_LEN: usize = 1024 * 1024; // 1MB
+const NONCE_LEN: usize = 12;
+const HKDF_INFO: &[u8] = b"pm-native-bridge-v1";
+const FRAME_AAD: &[u8] = b"pm-native-bridge-frame-v1";
+const EXPECTED_SCHEMA_VERSION: u8 = 2;
+
+/// Duplicated from `ipc::handshake`/`data::crypto::sharing` rather than
+/// imported: this binary has no access to the app's crate, so the
+/// handshake's wire format (HKDF info string, frame AAD) is kept identical
+/// by hand instead.
+fn derive_shared_key(our_secret: &StaticSecret, their_public: &PublicKey) -> [u8; 32] {
+    let shared_secret = our_secret.diffie_hellman(their_public);
+    let hk = Hkdf::<Sha256>::new(None, shared_secret.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(HKDF_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+fn seal_frame(key: &[u8; 32], plaintext: &[u8]) -> Option<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad: FRAME_AAD,
+            },
+        )
+        .ok()?;
+    let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+    Some(out)
+}
+
+fn open_frame(key: &[u8; 32], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce_bytes),
+            Payload {
+                msg: ciphertext,
+                aad: FRAME_AAD,
+            },
+        )
+        .ok()
+}
 
 #[derive(Debug, Deserialize)]
 struct NativeRequest {
@@ -33,6 +96,7 @@ struct IpcInfo {
     pub port: u16,
     pub token: String,
     pub created_at_ms: u128,
+    pub server_public_key: String,
 }
 
 fn primary_ipc_info_path(app_dir: &Path) -> PathBuf {
@@ -62,7 +126,7 @@ fn load_ipc_info(app_dir: &Path) -> Option<IpcInfo> {
     let path = ipc_info_path_for_load(app_dir);
     let content = std::fs::read_to_string(path).ok()?;
     let info: IpcInfo = serde_json::from_str(&content).ok()?;
-    if info.schema_version != 1 {
+    if info.schema_version != EXPECTED_SCHEMA_VERSION {
         return None;
     }
     Some(info)
@@ -126,11 +190,29 @@ fn forward_to_app(req: NativeRequest) -> NativeResponse {
         None => return error_response(req.id, "APP_NOT_RUNNING"),
     };
 
+    let Ok(server_public_bytes) = general_purpose::STANDARD.decode(&info.server_public_key) else {
+        return error_response(req.id, "APP_PROTOCOL_ERROR");
+    };
+    let Ok(server_public_bytes): Result<[u8; 32], _> = server_public_bytes.try_into() else {
+        return error_response(req.id, "APP_PROTOCOL_ERROR");
+    };
+    let server_public = PublicKey::from(server_public_bytes);
+
     let mut stream = match TcpStream::connect(("127.0.0.1", info.port)) {
         Ok(s) => s,
         Err(_) => return error_response(req.id, "APP_NOT_RUNNING"),
     };
 
+    // Handshake: generate a one-time keypair for this connection, send the
+    // public half in the clear, and derive the shared key that seals
+    // everything else. The app does the matching DH on its side.
+    let our_secret = StaticSecret::random_from_rng(OsRng);
+    let our_public = PublicKey::from(&our_secret);
+    if write_frame(&mut stream, our_public.as_bytes()).is_err() {
+        return error_response(req.id, "APP_NOT_RUNNING");
+    }
+    let shared_key = derive_shared_key(&our_secret, &server_public);
+
     let bridge_req = serde_json::json!({
         "id": req.id,
         "token": info.token,
@@ -142,14 +224,20 @@ fn forward_to_app(req: NativeRequest) -> NativeResponse {
         Ok(b) => b,
         Err(_) => return error_response("unknown".to_string(), "SERIALIZE_FAILED"),
     };
+    let Some(sealed) = seal_frame(&shared_key, &bytes) else {
+        return error_response("unknown".to_string(), "SERIALIZE_FAILED");
+    };
 
-    if write_frame(&mut stream, &bytes).is_err() {
+    if write_frame(&mut stream, &sealed).is_err() {
         return error_response("unknown".to_string(), "APP_NOT_RUNNING");
     }
-    let frame = match read_frame(&mut stream) {
+    let sealed_resp = match read_frame(&mut stream) {
         Ok(Some(b)) => b,
         _ => return error_response("unknown".to_string(), "APP_NOT_RUNNING"),
     };
+    let Some(frame) = open_frame(&shared_key, &sealed_resp) else {
+        return error_response("unknown".to_string(), "APP_PROTOCOL_ERROR");
+    };
 
     match serde_json::from_slice::<NativeResponse>(&frame) {
         Ok(resp) => resp,
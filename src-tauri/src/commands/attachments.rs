@@ -1,8 +1,11 @@
-use tauri::AppHandle;
+use std::sync::Arc;
 
+use tauri::{AppHandle, Manager};
+
+use crate::app_state::AppState;
 use crate::error::{ErrorCodeString, Result};
 use crate::services::attachments_service;
-use crate::types::AttachmentMeta;
+use crate::types::{AttachmentMeta, AttachmentPurgeReport, AttachmentRangePayload};
 
 #[tauri::command]
 pub async fn list_attachments(app: AppHandle, datacard_id: String) -> Result<Vec<AttachmentMeta>> {
@@ -44,6 +47,30 @@ pub async fn purge_attachment(app: AppHandle, attachment_id: String) -> Result<(
     .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
 
+#[tauri::command]
+pub async fn get_attachment_range(
+    app: AppHandle,
+    attachment_id: String,
+    offset: i64,
+    length: i64,
+) -> Result<AttachmentRangePayload> {
+    tauri::async_runtime::spawn_blocking(move || {
+        attachments_service::get_attachment_range(&app, attachment_id, offset, length)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn purge_expired_attachments(app: AppHandle) -> Result<AttachmentPurgeReport> {
+    let app_state = app.state::<Arc<AppState>>().inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        attachments_service::purge_expired_for_active_profile(&app_state)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
 #[tauri::command]
 pub async fn save_attachment_to_path(
     app: AppHandle,
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use tauri::Manager;
+
+use crate::app_state::AppState;
+use crate::error::{ErrorCodeString, Result};
+use crate::services::audit_log_service;
+use crate::types::AuditLogEntry;
+
+#[tauri::command]
+pub async fn get_audit_log(
+    app: tauri::AppHandle,
+    row_id: String,
+) -> Result<Vec<AuditLogEntry>> {
+    let app_state = app.state::<Arc<AppState>>().inner().clone();
+    tauri::async_runtime::spawn_blocking(move || audit_log_service::list_audit_log(&app_state, &row_id))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
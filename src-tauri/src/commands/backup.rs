@@ -6,7 +6,9 @@ use crate::app_state::AppState;
 use crate::error::{ErrorCodeString, Result};
 use crate::services::backup_service::{
     backup_create as backup_create_service, backup_create_if_due_auto as backup_create_if_due_auto_service,
-    backup_list as backup_list_service, backup_restore as backup_restore_service, BackupListItem,
+    backup_list as backup_list_service, backup_repair as backup_repair_service,
+    backup_restore as backup_restore_service, backup_verify as backup_verify_service, BackupListItem,
+    BackupRepairReport, BackupVerifyReport,
 };
 
 #[tauri::command]
@@ -46,3 +48,25 @@ pub async fn backup_create_if_due_auto(state: State<'_, Arc<AppState>>) -> Resul
         .await
         .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
+
+#[tauri::command]
+pub async fn backup_verify(
+    backup_path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<BackupVerifyReport> {
+    let app = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || backup_verify_service(&app, backup_path))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn backup_repair(
+    backup_path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<BackupRepairReport> {
+    let app = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || backup_repair_service(&app, backup_path))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
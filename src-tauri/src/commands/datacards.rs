@@ -5,8 +5,10 @@ use tauri::State;
 use crate::app_state::AppState;
 use crate::error::{ErrorCodeString, Result};
 use crate::services::datacards_service;
+use crate::services::password_exposure_service;
 use crate::types::{
-    CreateDataCardInput, DataCard, DataCardSummary, MoveDataCardInput, UpdateDataCardInput,
+    CreateDataCardInput, DataCard, DataCardSummary, MoveDataCardInput, PasswordExposureReport,
+    TotpCodeResult, UpdateDataCardInput,
 };
 
 #[tauri::command]
@@ -113,3 +115,31 @@ pub async fn list_deleted_datacards_summary_command(
     .await
     .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
+
+#[tauri::command]
+pub async fn get_custom_field_totp_code(
+    datacard_id: String,
+    field_key: String,
+    state: State<Arc<AppState>>,
+) -> Result<TotpCodeResult> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        datacards_service::get_custom_field_totp_code(datacard_id, field_key, &state)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn check_password_exposure(
+    datacard_id: String,
+    check_breach: bool,
+    state: State<Arc<AppState>>,
+) -> Result<PasswordExposureReport> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        password_exposure_service::check_password_exposure(&state, &datacard_id, check_breach)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
@@ -5,7 +5,10 @@ use tauri::State;
 use crate::app_state::AppState;
 use crate::error::{ErrorCodeString, Result};
 use crate::services::folders_service;
-use crate::types::{CreateFolderInput, Folder, MoveFolderInput, RenameFolderInput};
+use crate::types::{
+    CreateFolderInput, Folder, FolderBatchItemResult, MoveFolderInput, MoveFoldersInput,
+    RenameFolderInput,
+};
 
 #[tauri::command]
 pub async fn list_folders(state: State<'_, Arc<AppState>>) -> Result<Vec<Folder>> {
@@ -73,3 +76,47 @@ pub async fn purge_folder(id: String, state: State<'_, Arc<AppState>>) -> Result
         .await
         .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
+
+#[tauri::command]
+pub async fn move_folders(
+    input: MoveFoldersInput,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<FolderBatchItemResult>> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || folders_service::move_folders(input, &state))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn delete_folders(
+    ids: Vec<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<FolderBatchItemResult>> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || folders_service::delete_folders(ids, &state))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn restore_folders(
+    ids: Vec<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<FolderBatchItemResult>> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || folders_service::restore_folders(ids, &state))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn purge_folders(
+    ids: Vec<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<FolderBatchItemResult>> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || folders_service::purge_folders(ids, &state))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::error::{ErrorCodeString, Result};
+use crate::services::import_service::{self, ImportDirectoryReport};
+
+#[tauri::command]
+pub async fn import_directory(
+    source_root: String,
+    target_folder_id: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ImportDirectoryReport> {
+    let state = state.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        import_service::import_directory(source_root, target_folder_id, &state)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
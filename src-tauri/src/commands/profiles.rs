@@ -9,7 +9,7 @@ use crate::types::{ProfileMeta, ProfilesList};
 
 #[tauri::command]
 pub async fn profiles_list(state: State<'_, Arc<AppState>>) -> Result<ProfilesList> {
-    let storage_paths = state.inner().storage_paths.clone();
+    let storage_paths = state.inner().get_storage_paths()?;
 
     tauri::async_runtime::spawn_blocking(move || profiles_service::list_profiles(&storage_paths))
         .await
@@ -22,10 +22,12 @@ pub async fn profile_create(
     password: Option<String>,
     state: State<'_, Arc<AppState>>,
 ) -> Result<ProfileMeta> {
-    let storage_paths = state.inner().storage_paths.clone();
+    let app_state = state.inner().clone();
+    let storage_paths = app_state.get_storage_paths()?;
 
     tauri::async_runtime::spawn_blocking(move || {
-        profiles_service::create_profile(&storage_paths, &name, password)
+        let blob_storage = app_state.get_vault_blob_storage()?;
+        profiles_service::create_profile(&storage_paths, blob_storage.as_ref(), &name, password)
     })
     .await
     .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
@@ -34,11 +36,11 @@ pub async fn profile_create(
 #[tauri::command]
 pub async fn profile_delete(id: String, state: State<'_, Arc<AppState>>) -> Result<bool> {
     let app_state = state.inner().clone();
-    let storage_paths = app_state.storage_paths.clone();
+    let storage_paths = app_state.get_storage_paths()?;
 
     tauri::async_runtime::spawn_blocking(move || {
         let should_lock = app_state
-            .logged_in_profile
+            .active_profile
             .lock()
             .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
             .as_deref()
@@ -63,7 +65,7 @@ pub async fn profile_delete(id: String, state: State<'_, Arc<AppState>>) -> Resu
 #[tauri::command]
 pub async fn get_active_profile(state: State<'_, Arc<AppState>>) -> Result<Option<ProfileMeta>> {
     let app_state = state.inner().clone();
-    let storage_paths = app_state.storage_paths.clone();
+    let storage_paths = app_state.get_storage_paths()?;
 
     tauri::async_runtime::spawn_blocking(move || {
         if let Ok(active) = app_state.active_profile.lock() {
@@ -81,7 +83,7 @@ pub async fn get_active_profile(state: State<'_, Arc<AppState>>) -> Result<Optio
 #[tauri::command]
 pub async fn set_active_profile(id: String, state: State<'_, Arc<AppState>>) -> Result<bool> {
     let app_state = state.inner().clone();
-    let storage_paths = app_state.storage_paths.clone();
+    let storage_paths = app_state.get_storage_paths()?;
 
     tauri::async_runtime::spawn_blocking(move || {
         if !profiles_service::list_profiles(&storage_paths)?
@@ -112,3 +114,98 @@ pub async fn set_active_profile(id: String, state: State<'_, Arc<AppState>>) ->
     .await
     .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
+
+#[tauri::command]
+pub async fn enable_keychain_unlock(
+    id: String,
+    password: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool> {
+    let storage_paths = state.inner().get_storage_paths()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        profiles_service::store_profile_secret(&storage_paths, &id, &password)?;
+        Ok(true)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn disable_keychain_unlock(id: String, state: State<'_, Arc<AppState>>) -> Result<bool> {
+    let storage_paths = state.inner().get_storage_paths()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        profiles_service::clear_profile_secret(&storage_paths, &id)?;
+        Ok(true)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn enroll_totp(
+    id: String,
+    password: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String> {
+    let storage_paths = state.inner().get_storage_paths()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        profiles_service::enroll_totp(&storage_paths, &id, &password)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn disable_totp(id: String, state: State<'_, Arc<AppState>>) -> Result<bool> {
+    let storage_paths = state.inner().get_storage_paths()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        profiles_service::disable_totp(&storage_paths, &id)?;
+        Ok(true)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
+#[tauri::command]
+pub async fn rotate_master_password(
+    id: String,
+    old_password: String,
+    new_password: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool> {
+    let app_state = state.inner().clone();
+    let storage_paths = app_state.get_storage_paths()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        // The in-memory vault session (if this profile is the one
+        // currently unlocked) still holds the *old* vault key — an ordinary
+        // persist after rotation would silently re-encrypt the vault under
+        // it again. Lock first, exactly like `profile_delete` does for the
+        // active profile, so rotation is the last thing to touch the vault
+        // key before the caller has to log back in.
+        let is_active = app_state
+            .active_profile
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
+            .as_deref()
+            == Some(&id);
+        if is_active {
+            security_service::lock_vault(&app_state)?;
+        }
+
+        let blob_storage = app_state.get_vault_blob_storage()?;
+        profiles_service::rotate_master_password(
+            &storage_paths,
+            blob_storage.as_ref(),
+            &id,
+            &old_password,
+            &new_password,
+        )
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
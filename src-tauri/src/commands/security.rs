@@ -10,12 +10,13 @@ use crate::services::security_service;
 pub async fn login_vault(
     id: String,
     password: Option<String>,
+    totp_token: Option<String>,
     state: State<'_, Arc<AppState>>,
 ) -> Result<bool> {
     let app_state = state.inner().clone();
 
     tauri::async_runtime::spawn_blocking(move || {
-        security_service::login_vault(&id, password, &app_state)
+        security_service::login_vault(&id, password, totp_token, &app_state)
     })
     .await
     .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
@@ -30,6 +31,21 @@ pub async fn lock_vault(state: State<'_, Arc<AppState>>) -> Result<bool> {
         .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
 
+#[tauri::command]
+pub async fn change_master_password(
+    current_password: String,
+    new_password: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool> {
+    let app_state = state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        security_service::change_master_password(&current_password, &new_password, &app_state)
+    })
+    .await
+    .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
+
 #[tauri::command]
 pub async fn is_logged_in(state: State<'_, Arc<AppState>>) -> Result<bool> {
     let app_state = state.inner().clone();
@@ -54,3 +70,12 @@ pub async fn health_check() -> Result<bool> {
         .await
         .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
 }
+
+#[tauri::command]
+pub async fn db_schema_version(state: State<'_, Arc<AppState>>) -> Result<i32> {
+    let app_state = state.inner().clone();
+
+    tauri::async_runtime::spawn_blocking(move || security_service::db_schema_version(&app_state))
+        .await
+        .map_err(|_| ErrorCodeString::new("TASK_JOIN_FAILED"))?
+}
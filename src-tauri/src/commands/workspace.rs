@@ -9,6 +9,7 @@ use uuid::Uuid;
 
 use crate::app_state::AppState;
 use crate::data::fs::atomic_write::write_atomic;
+use crate::data::storage_paths::is_network_filesystem;
 use crate::data::workspaces::registry::{
     display_name_from_path, encode_workspace_path, load_registry, resolve_workspace_path,
     save_registry, WorkspaceRecord,
@@ -168,6 +169,11 @@ pub async fn workspace_list(state: State<'_, Arc<AppState>>) -> Result<Vec<Works
                     exists,
                     valid,
                     is_active: active_id.as_deref() == Some(&record.id),
+                    // Surfaced so the UI can warn before the user commits to a
+                    // workspace where SQLite's locking assumptions are shakier
+                    // (see `storage_paths::is_network_filesystem`), rather than
+                    // only finding out via the pool's own warn-log later.
+                    is_network: exists && is_network_filesystem(&resolved),
                 })
             })
             .collect()
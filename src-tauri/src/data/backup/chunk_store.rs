@@ -0,0 +1,72 @@
+//! Content-addressed store for backup chunks produced by `chunking::chunk`.
+//!
+//! Each chunk is written once under the hex SHA-256 of its *plaintext*
+//! bytes; a later backup whose chunking produces the same hash reuses the
+//! file already on disk instead of writing it again. This is what makes
+//! incremental backups of a large, mostly-unchanged vault cheap: only the
+//! chunks that actually differ from the previous backup hit the disk.
+//!
+//! Each chunk is sealed under the owning profile's vault key via
+//! `cipher::encrypt_backup_chunk`, the same `Option<key>` convention used
+//! everywhere else in the crate — `cipher::encrypt_placeholder` only still
+//! runs for a passwordless profile, which has no key to seal chunks under.
+
+use std::fs;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use crate::data::crypto::cipher;
+use crate::data::fs::atomic_write::write_atomic;
+use crate::error::{ErrorCodeString, Result};
+
+pub fn chunk_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub struct ChunkStore {
+    dir: PathBuf,
+}
+
+impl ChunkStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn chunk_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{hash}.chunk"))
+    }
+
+    /// Whether a chunk file exists under `hash`, without reading or
+    /// decrypting it. Used by backup verification to tell a missing chunk
+    /// apart from one that exists but fails to decrypt or re-hash.
+    pub fn exists(&self, hash: &str) -> bool {
+        self.chunk_path(hash).is_file()
+    }
+
+    /// Writes `plaintext` under its content hash unless a chunk with that
+    /// hash already exists. Returns the hash and whether this call actually
+    /// wrote a new file (`false` is a dedup hit against a prior backup).
+    pub fn put(&self, profile_id: &str, vault_key: Option<&[u8; 32]>, plaintext: &[u8]) -> Result<(String, bool)> {
+        fs::create_dir_all(&self.dir).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+        let hash = chunk_hash(plaintext);
+        let path = self.chunk_path(&hash);
+        if path.exists() {
+            return Ok((hash, false));
+        }
+        let encrypted = match vault_key {
+            Some(key) => cipher::encrypt_backup_chunk(profile_id, &hash, key, plaintext)?,
+            None => cipher::encrypt_placeholder(plaintext),
+        };
+        write_atomic(&path, &encrypted).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+        Ok((hash, true))
+    }
+
+    pub fn get(&self, profile_id: &str, vault_key: Option<&[u8; 32]>, hash: &str) -> Result<Vec<u8>> {
+        let bytes = fs::read(self.chunk_path(hash)).map_err(|_| ErrorCodeString::new("BACKUP_CORRUPTED"))?;
+        match vault_key {
+            Some(key) => cipher::decrypt_backup_chunk(profile_id, hash, key, &bytes),
+            None => Ok(cipher::decrypt_placeholder(&bytes)),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Content-defined chunking (CDC) for backup payloads.
+//!
+//! Splits a byte stream into variable-length chunks whose boundaries are
+//! determined by the *content* (a rolling hash crossing a threshold) rather
+//! than by fixed offsets. Two backups that differ by a small edit in the
+//! middle of the vault database still share almost all of their chunks,
+//! since only the chunk(s) actually touched shift — everything before and
+//! after re-aligns to the same boundaries. This is what lets
+//! `backup_service` dedupe chunks across incremental backups instead of
+//! storing the whole database again every time.
+//!
+//! The rolling hash is a simple 64-bit "gear hash": `h = (h << 1) + table[byte]`,
+//! windowed implicitly by the shift (old bytes fall off the top after 64
+//! shifts). A boundary is declared when the low bits of `h` are all zero,
+//! which splits the input into chunks of ~`AVG_CHUNK_SIZE` bytes on average.
+
+const MIN_CHUNK_SIZE: usize = 4 * 1024;
+const MAX_CHUNK_SIZE: usize = 128 * 1024;
+const AVG_CHUNK_SIZE_MASK: u64 = (32 * 1024 - 1) as u64;
+
+/// A 256-entry table of pseudo-random 64-bit constants, one per byte value,
+/// used by the gear hash below. Generated with a small fixed seed (SplitMix64)
+/// so chunking is deterministic across runs and platforms.
+fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    for entry in table.iter_mut() {
+        seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        *entry = z ^ (z >> 31);
+    }
+    table
+}
+
+/// Splits `data` into content-defined chunks. Every chunk but the last is
+/// between `MIN_CHUNK_SIZE` and `MAX_CHUNK_SIZE` bytes; the last chunk is
+/// whatever remains.
+pub fn chunk(data: &[u8]) -> Vec<&[u8]> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let table = gear_table();
+    let mut chunks = Vec::new();
+    let mut start = 0usize;
+    let mut hash: u64 = 0;
+
+    for (i, &byte) in data.iter().enumerate() {
+        hash = hash.wrapping_shl(1).wrapping_add(table[byte as usize]);
+        let len = i - start + 1;
+        if len < MIN_CHUNK_SIZE {
+            continue;
+        }
+        if len >= MAX_CHUNK_SIZE || hash & AVG_CHUNK_SIZE_MASK == 0 {
+            chunks.push(&data[start..=i]);
+            start = i + 1;
+            hash = 0;
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(&data[start..]);
+    }
+
+    chunks
+}
@@ -1,6 +1,263 @@
-//! Placeholder for AEAD XChaCha20-Poly1305 implementation.
-//! Step 1 stores only metadata and key checks; encryption will be expanded later.
+//! The AEAD primitive every persisted blob in this crate is sealed under:
+//! zstd-compress the plaintext, then seal it with XChaCha20-Poly1305 under a
+//! fresh random 24-byte nonce prepended to the ciphertext. Every wrapper
+//! below binds its own caller-specific associated data (a profile id, a
+//! chunk hash, an attachment's content hash, ...) so a sealed blob read back
+//! under the wrong identity fails closed instead of decrypting into garbage
+//! or silently succeeding against the wrong record.
+//!
+//! `encrypt_placeholder`/`decrypt_placeholder` are untouched identity
+//! functions, kept for two reasons: `backup_service::decode_payload_verified`
+//! still relies on `decrypt_placeholder` being a no-op to read old
+//! format-version-1/2 archives written before this module existed, and a
+//! *passwordless* profile has no key material to encrypt its oplog/backup
+//! chunks under in the first place — both `services::oplog_service` and
+//! `data::backup::chunk_store` fall back to these only in that case, the
+//! same `Option<key>` convention `data::crypto::encrypted_value` and
+//! `data::sqlite::repo_impl` already use elsewhere in the crate.
 
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::error::{ErrorCodeString, Result};
+
+/// Byte length of every key this module accepts.
+pub const KEY_LEN: usize = 32;
+
+/// Marks a blob as sealed by this module, so callers that can receive
+/// either an encrypted or a legacy/plaintext file (see
+/// `master_key::read_master_key_unwrapped`) can tell which one they hold
+/// before attempting to decrypt it.
+pub const PM_ENC_MAGIC: [u8; 4] = *b"PMC1";
+
+const NONCE_LEN: usize = 24;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compresses `plaintext` with zstd, seals it with XChaCha20-Poly1305 under
+/// a fresh random nonce, and binds `aad`. Output is
+/// `PM_ENC_MAGIC || nonce || ciphertext(+tag)`.
+pub fn encrypt_bytes(key: &[u8; KEY_LEN], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let compressed =
+        zstd::encode_all(plaintext, ZSTD_LEVEL).map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))?;
+
+    let cipher = XChaCha20Poly1305::new_from_slice(key).map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))?;
+    let nonce_bytes = random_nonce();
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce_bytes), Payload { msg: &compressed, aad })
+        .map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))?;
+
+    let mut out = Vec::with_capacity(PM_ENC_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&PM_ENC_MAGIC);
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Inverse of `encrypt_bytes`. A bad `aad`/key, a truncated header, or a
+/// tampered ciphertext all surface as the same `DECRYPT_FAILED` rather than
+/// distinguishing which step failed, so a caller can't learn anything about
+/// *why* a blob didn't open.
+pub fn decrypt_bytes(key: &[u8; KEY_LEN], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < PM_ENC_MAGIC.len() + NONCE_LEN || !sealed.starts_with(&PM_ENC_MAGIC) {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    let rest = &sealed[PM_ENC_MAGIC.len()..];
+    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = XChaCha20Poly1305::new_from_slice(key).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    let compressed = cipher
+        .decrypt(XNonce::from_slice(nonce_bytes), Payload { msg: ciphertext, aad })
+        .map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+
+    zstd::decode_all(compressed.as_slice()).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))
+}
+
+fn random_nonce() -> [u8; NONCE_LEN] {
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    nonce
+}
+
+/// Seals the vault's addressable blob (a chunk manifest, or — for older,
+/// unchunked profiles — the whole serialized database) under `key`, binding
+/// `profile_id` so it can't be relocated to another profile's storage.
+pub fn encrypt_vault_blob(profile_id: &str, key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_bytes(key, vault_blob_aad(profile_id).as_slice(), plaintext)
+}
+
+pub fn decrypt_vault_blob(profile_id: &str, key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    decrypt_bytes(key, vault_blob_aad(profile_id).as_slice(), sealed)
+}
+
+fn vault_blob_aad(profile_id: &str) -> Vec<u8> {
+    format!("vault_blob:{profile_id}").into_bytes()
+}
+
+/// Seals one attachment's content, binding both `profile_id` and its
+/// content hash so the blob can't be relocated between profiles, or have a
+/// different attachment's ciphertext substituted in under the same name.
+pub fn encrypt_attachment_blob(
+    profile_id: &str,
+    content_hash: &str,
+    key: &[u8; KEY_LEN],
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    encrypt_bytes(key, attachment_aad(profile_id, content_hash).as_slice(), plaintext)
+}
+
+pub fn decrypt_attachment_blob(
+    profile_id: &str,
+    content_hash: &str,
+    key: &[u8; KEY_LEN],
+    sealed: &[u8],
+) -> Result<Vec<u8>> {
+    decrypt_bytes(key, attachment_aad(profile_id, content_hash).as_slice(), sealed)
+}
+
+fn attachment_aad(profile_id: &str, content_hash: &str) -> Vec<u8> {
+    format!("attachment:{profile_id}:{content_hash}").into_bytes()
+}
+
+/// Streaming counterpart to `encrypt_attachment_blob`/`decrypt_attachment_blob`
+/// for attachments too large to hold in memory whole — see
+/// `data::crypto::stream_cipher` for the framed format itself. Binds the
+/// same `profile_id`/`content_hash` associated data either way, so a blob
+/// sealed whole and one sealed as a stream authenticate under identical
+/// rules.
+pub fn encrypt_attachment_stream(
+    profile_id: &str,
+    content_hash: &str,
+    key: &[u8; KEY_LEN],
+    reader: &mut impl std::io::Read,
+    writer: &mut impl std::io::Write,
+) -> Result<()> {
+    super::stream_cipher::encrypt_stream(key, attachment_aad(profile_id, content_hash).as_slice(), reader, writer)
+}
+
+pub fn decrypt_attachment_stream(
+    profile_id: &str,
+    content_hash: &str,
+    key: &[u8; KEY_LEN],
+    reader: &mut impl std::io::Read,
+    writer: &mut impl std::io::Write,
+) -> Result<()> {
+    super::stream_cipher::decrypt_stream(key, attachment_aad(profile_id, content_hash).as_slice(), reader, writer)
+}
+
+/// Like `decrypt_attachment_stream`, but only decrypts the frames
+/// overlapping the plaintext byte range `[start, end)` — see
+/// `stream_cipher::decrypt_stream_range`.
+pub fn decrypt_attachment_stream_range(
+    profile_id: &str,
+    content_hash: &str,
+    key: &[u8; KEY_LEN],
+    reader: &mut impl std::io::Read,
+    writer: &mut impl std::io::Write,
+    start: u64,
+    end: u64,
+) -> Result<()> {
+    super::stream_cipher::decrypt_stream_range(
+        key,
+        attachment_aad(profile_id, content_hash).as_slice(),
+        reader,
+        writer,
+        start,
+        end,
+    )
+}
+
+/// Seals a key-check payload (see `key_check::create_key_check_file`),
+/// binding `profile_id` so a key-check file can't be copied between
+/// profiles and mistaken for a match.
+pub fn encrypt_key_check(profile_id: &str, key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_bytes(key, key_check_aad(profile_id).as_slice(), plaintext)
+}
+
+pub fn decrypt_key_check(profile_id: &str, key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    decrypt_bytes(key, key_check_aad(profile_id).as_slice(), sealed)
+}
+
+fn key_check_aad(profile_id: &str) -> Vec<u8> {
+    format!("key_check:{profile_id}").into_bytes()
+}
+
+/// Seals one record of the CRDT-style sync log — an operation or a
+/// checkpoint, see `data::sync::vault_log` — binding `profile_id` the same
+/// way `encrypt_vault_blob` does, so a record can't be relocated to another
+/// profile's log and replayed there.
+pub fn encrypt_sync_log_entry(profile_id: &str, key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_bytes(key, sync_log_aad(profile_id).as_slice(), plaintext)
+}
+
+pub fn decrypt_sync_log_entry(profile_id: &str, key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    decrypt_bytes(key, sync_log_aad(profile_id).as_slice(), sealed)
+}
+
+fn sync_log_aad(profile_id: &str) -> Vec<u8> {
+    format!("sync_log:{profile_id}").into_bytes()
+}
+
+/// Seals one `services::oplog_service` entry, binding `profile_id`,
+/// `op_type` and `created_at` (all stored alongside the ciphertext anyway)
+/// so a replayed entry can't be relocated to another profile's log or have
+/// its recorded operation kind/timestamp swapped without the AEAD tag
+/// failing to verify.
+pub fn encrypt_oplog_entry(
+    profile_id: &str,
+    op_type: &str,
+    created_at: &str,
+    key: &[u8; KEY_LEN],
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    encrypt_bytes(key, oplog_aad(profile_id, op_type, created_at).as_slice(), plaintext)
+}
+
+pub fn decrypt_oplog_entry(
+    profile_id: &str,
+    op_type: &str,
+    created_at: &str,
+    key: &[u8; KEY_LEN],
+    sealed: &[u8],
+) -> Result<Vec<u8>> {
+    decrypt_bytes(key, oplog_aad(profile_id, op_type, created_at).as_slice(), sealed)
+}
+
+fn oplog_aad(profile_id: &str, op_type: &str, created_at: &str) -> Vec<u8> {
+    format!("oplog:{profile_id}:{op_type}:{created_at}").into_bytes()
+}
+
+/// Seals one `data::backup::chunk_store` chunk, binding `profile_id` and its
+/// own content hash (same shape as `data::storage::vault_chunk_store`'s
+/// `chunk_aad`) so a backup chunk can't be relocated to another profile's
+/// chunk store or replayed under a different hash.
+pub fn encrypt_backup_chunk(profile_id: &str, hash: &str, key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_bytes(key, backup_chunk_aad(profile_id, hash).as_slice(), plaintext)
+}
+
+pub fn decrypt_backup_chunk(profile_id: &str, hash: &str, key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    decrypt_bytes(key, backup_chunk_aad(profile_id, hash).as_slice(), sealed)
+}
+
+fn backup_chunk_aad(profile_id: &str, hash: &str) -> Vec<u8> {
+    format!("backup_chunk:{profile_id}:{hash}").into_bytes()
+}
+
+/// Writes an already-sealed blob to disk atomically. Named for what it
+/// holds, not for what it does to it — callers encrypt first, then hand the
+/// result here.
+pub fn write_encrypted_file(path: &std::path::Path, sealed: &[u8]) -> Result<()> {
+    crate::data::fs::atomic_write::write_atomic(path, sealed)
+        .map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))
+}
+
+pub fn read_encrypted_file(path: &std::path::Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))
+}
+
+/// Identity passthrough — see the module doc comment for why this hasn't
+/// been upgraded to a real seal yet.
 pub fn encrypt_placeholder(data: &[u8]) -> Vec<u8> {
     data.to_vec()
 }
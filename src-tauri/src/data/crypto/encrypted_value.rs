@@ -0,0 +1,134 @@
+//! Transparent at-rest encryption for individual SQLite column values via
+//! AES-256-GCM, keyed by the active profile's vault key (the same key
+//! `security_service::persist_active_vault` already uses to encrypt the
+//! vault DB as a whole once it's serialized to disk). Column-level
+//! encryption here is defense-in-depth on top of that: `password_history`
+//! rows sit in an in-memory `rusqlite::Connection` for the life of a vault
+//! session (see `app_state::VaultSession`), and this keeps that in-memory
+//! copy — and anything that might capture it, e.g. a swap file or crash
+//! dump — unreadable without the vault key too.
+//!
+//! `EncryptedValue` implements `rusqlite::ToSql`/`FromSql` so a column can
+//! be declared as this type instead of `String` and the wire format below
+//! is produced/parsed for you. `FromSql`'s signature has no way to receive
+//! the per-profile key, though, so reading one back only ever recovers
+//! *ciphertext* — actual decryption is the separate `decrypt` method,
+//! called explicitly with the key in hand, same as every other
+//! per-profile secret in this crate (`secret_store::protect`/`unprotect`)
+//! is threaded through rather than hidden behind ambient state. That's
+//! also where tag verification happens and a tampered/corrupted row is
+//! rejected with `DB_DECRYPT_FAILED`.
+//!
+//! On-disk layout, stored as one SQLite BLOB:
+//! `[u64 tag_len][tag][u64 nonce_len][nonce][u64 ciphertext_len][ciphertext]`,
+//! all lengths big-endian.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+
+use crate::error::{ErrorCodeString, Result};
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct EncryptedValue {
+    tag: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedValue {
+    /// Seals `plaintext` under `key`, binding it to `aad` (e.g. the owning
+    /// datacard's id) so an encrypted value can't be copied onto a
+    /// different row and still decrypt.
+    pub fn encrypt(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| ErrorCodeString::new("DB_ENCRYPT_KEY_INVALID"))?;
+
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+
+        let mut sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: plaintext, aad })
+            .map_err(|_| ErrorCodeString::new("DB_ENCRYPT_FAILED"))?;
+        // `Aead::encrypt` appends the tag to the end of the ciphertext;
+        // split it off so it's stored as its own segment per this module's
+        // layout rather than re-deriving the split point on every read.
+        let tag = sealed.split_off(sealed.len() - TAG_LEN);
+
+        Ok(Self {
+            tag,
+            nonce: nonce_bytes.to_vec(),
+            ciphertext: sealed,
+        })
+    }
+
+    /// Verifies and decrypts. `aad` must match what `encrypt` was called
+    /// with, or (same as a tampered tag) this fails closed with
+    /// `DB_DECRYPT_FAILED`.
+    pub fn decrypt(&self, key: &[u8; 32], aad: &[u8]) -> Result<Vec<u8>> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| ErrorCodeString::new("DB_ENCRYPT_KEY_INVALID"))?;
+
+        let mut combined = Vec::with_capacity(self.ciphertext.len() + self.tag.len());
+        combined.extend_from_slice(&self.ciphertext);
+        combined.extend_from_slice(&self.tag);
+
+        cipher
+            .decrypt(Nonce::from_slice(&self.nonce), Payload { msg: &combined, aad })
+            .map_err(|_| ErrorCodeString::new("DB_DECRYPT_FAILED"))
+    }
+
+    fn to_blob(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            24 + self.tag.len() + self.nonce.len() + self.ciphertext.len(),
+        );
+        for segment in [&self.tag, &self.nonce, &self.ciphertext] {
+            out.extend_from_slice(&(segment.len() as u64).to_be_bytes());
+            out.extend_from_slice(segment);
+        }
+        out
+    }
+
+    fn from_blob(blob: &[u8]) -> std::result::Result<Self, &'static str> {
+        let mut cursor = blob;
+        let tag = take_segment(&mut cursor)?;
+        let nonce = take_segment(&mut cursor)?;
+        let ciphertext = take_segment(&mut cursor)?;
+        if !cursor.is_empty() {
+            return Err("trailing bytes after ciphertext segment");
+        }
+        Ok(Self { tag, nonce, ciphertext })
+    }
+}
+
+fn take_segment(cursor: &mut &[u8]) -> std::result::Result<Vec<u8>, &'static str> {
+    if cursor.len() < 8 {
+        return Err("truncated segment length");
+    }
+    let (len_bytes, rest) = cursor.split_at(8);
+    let len = u64::from_be_bytes(len_bytes.try_into().expect("exactly 8 bytes")) as usize;
+    if rest.len() < len {
+        return Err("truncated segment body");
+    }
+    let (segment, rest) = rest.split_at(len);
+    *cursor = rest;
+    Ok(segment.to_vec())
+}
+
+impl ToSql for EncryptedValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Blob(self.to_blob())))
+    }
+}
+
+impl FromSql for EncryptedValue {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let blob = value.as_blob()?;
+        Self::from_blob(blob).map_err(|_| FromSqlError::InvalidType)
+    }
+}
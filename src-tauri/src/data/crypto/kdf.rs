@@ -1,26 +1,116 @@
+use std::time::Instant;
+
 use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use argon2::{Algorithm, Argon2, Params, Version};
 use rand::rngs::OsRng;
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroizing;
 
 use crate::error::{ErrorCodeString, Result};
 
 pub const DERIVED_KEY_LEN: usize = 32;
 const SALT_LEN: usize = 16;
-const ARGON2_MEMORY_KIB: u32 = 19456;
-const ARGON2_TIME_COST: u32 = 2;
-const ARGON2_LANES: u32 = 1;
-
-fn argon2_instance() -> Result<Argon2<'static>> {
-    let params = Params::new(
-        ARGON2_MEMORY_KIB,
-        ARGON2_TIME_COST,
-        ARGON2_LANES,
+
+/// Bumped whenever the calibration target or bounds below change enough that
+/// an old `KdfParams` should be considered stale. `open_protected_vault_session`
+/// re-derives (and re-persists) the key for any profile whose stored params
+/// are below this version, so calibration improvements roll out to existing
+/// profiles the next time they unlock rather than requiring a reset.
+pub const CURRENT_KDF_VERSION: u32 = 2;
+
+const CALIBRATION_TARGET_MS: u128 = 300;
+const MIN_MEMORY_KIB: u32 = 19_456; // 19 MiB, the floor OWASP recommends for Argon2id
+const MAX_MEMORY_KIB: u32 = 262_144; // 256 MiB
+const MIN_TIME_COST: u32 = 2;
+const MAX_TIME_COST: u32 = 10;
+
+/// Versioned Argon2id parameters. Stored alongside (not instead of) the
+/// random salt so a profile created on a slower or faster machine keeps
+/// params calibrated for *that* machine instead of today's. There's no
+/// separate algorithm id field: every version derives with Argon2id, and
+/// `version` already forces a rehash if that ever changes, so a field that
+/// would only ever hold one value would just be dead weight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub version: u32,
+    pub memory_kib: u32,
+    pub time_cost: u32,
+    pub lanes: u32,
+    #[serde(with = "salt_b64")]
+    pub salt: Vec<u8>,
+}
+
+mod salt_b64 {
+    use base64::engine::general_purpose;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+        general_purpose::STANDARD.encode(bytes).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(d)?;
+        general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn argon2_instance(params: &KdfParams) -> Result<Argon2<'static>> {
+    let argon2_params = Params::new(
+        params.memory_kib,
+        params.time_cost,
+        params.lanes,
         Some(DERIVED_KEY_LEN),
     )
     .map_err(|_| ErrorCodeString::new("PASSWORD_HASH"))?;
-    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params))
+}
+
+/// Benchmarks Argon2id on this machine at a fixed memory/lane cost and
+/// scales `time_cost` until a single hash takes roughly `CALIBRATION_TARGET_MS`,
+/// so slower hardware doesn't silently fall below a safe work factor and
+/// faster hardware isn't stuck paying a needlessly low one.
+pub fn calibrate_params() -> KdfParams {
+    let lanes = std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(1)
+        .clamp(1, 4);
+    let memory_kib = MIN_MEMORY_KIB;
+
+    let mut time_cost = MIN_TIME_COST;
+    while time_cost < MAX_TIME_COST {
+        let probe = KdfParams {
+            version: CURRENT_KDF_VERSION,
+            memory_kib,
+            time_cost,
+            lanes,
+            salt: vec![0u8; SALT_LEN],
+        };
+        let Ok(argon2) = argon2_instance(&probe) else { break };
+        let mut scratch = [0u8; DERIVED_KEY_LEN];
+        let started = Instant::now();
+        if argon2
+            .hash_password_into(b"pm-kdf-calibration-probe", &probe.salt, &mut scratch)
+            .is_err()
+        {
+            break;
+        }
+        if started.elapsed().as_millis() >= CALIBRATION_TARGET_MS {
+            break;
+        }
+        time_cost += 1;
+    }
+
+    KdfParams {
+        version: CURRENT_KDF_VERSION,
+        memory_kib,
+        time_cost,
+        lanes,
+        salt: generate_kdf_salt().to_vec(),
+    }
 }
 
 pub fn generate_kdf_salt() -> [u8; SALT_LEN] {
@@ -30,17 +120,62 @@ pub fn generate_kdf_salt() -> [u8; SALT_LEN] {
 }
 
 pub fn derive_master_key(password: &str, salt: &[u8]) -> Result<[u8; DERIVED_KEY_LEN]> {
-    let argon2 = argon2_instance()?;
+    // Back-compat entry point for callers that haven't migrated to
+    // `derive_master_key_versioned` yet; uses version 1's fixed cost.
+    let params = KdfParams {
+        version: 1,
+        memory_kib: MIN_MEMORY_KIB,
+        time_cost: MIN_TIME_COST,
+        lanes: 1,
+        salt: salt.to_vec(),
+    };
+    derive_master_key_versioned(password, &params)
+}
+
+pub fn derive_master_key_versioned(password: &str, params: &KdfParams) -> Result<[u8; DERIVED_KEY_LEN]> {
+    let argon2 = argon2_instance(params)?;
     let mut output = Zeroizing::new([0u8; DERIVED_KEY_LEN]);
     argon2
-        .hash_password_into(password.as_bytes(), salt, &mut output[..])
+        .hash_password_into(password.as_bytes(), &params.salt, &mut output[..])
         .map_err(|_| ErrorCodeString::new("PASSWORD_HASH"))?;
     Ok(*output)
 }
 
+pub fn needs_rehash(params: &KdfParams) -> bool {
+    params.version < CURRENT_KDF_VERSION
+}
+
+pub fn write_params_file(path: &std::path::Path, params: &KdfParams) -> Result<()> {
+    let serialized =
+        serde_json::to_vec_pretty(params).map_err(|_| ErrorCodeString::new("KDF_PARAMS_WRITE_FAILED"))?;
+    crate::data::fs::atomic_write::write_atomic(path, &serialized)
+        .map_err(|_| ErrorCodeString::new("KDF_PARAMS_WRITE_FAILED"))
+}
+
+/// Reads a params file, transparently accepting the legacy format (a bare
+/// 16-byte salt with no version/cost metadata) as version 1 so profiles
+/// created before this format existed can still unlock.
+pub fn read_params_file(path: &std::path::Path) -> Result<KdfParams> {
+    let bytes = std::fs::read(path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
+    if let Ok(params) = serde_json::from_slice::<KdfParams>(&bytes) {
+        return Ok(params);
+    }
+    if bytes.len() == SALT_LEN {
+        return Ok(KdfParams {
+            version: 1,
+            memory_kib: MIN_MEMORY_KIB,
+            time_cost: MIN_TIME_COST,
+            lanes: 1,
+            salt: bytes,
+        });
+    }
+    Err(ErrorCodeString::new("KDF_PARAMS_CORRUPTED"))
+}
+
 pub fn hash_password(password: &str) -> Result<String> {
     let salt = SaltString::generate(&mut OsRng);
-    let argon2 = argon2_instance()?;
+    let params = calibrate_params();
+    let argon2 = argon2_instance(&params)?;
     let password_hash = argon2
         .hash_password(password.as_bytes(), &salt)
         .map_err(|_| ErrorCodeString::new("PASSWORD_HASH"))?
@@ -50,6 +185,6 @@ pub fn hash_password(password: &str) -> Result<String> {
 
 pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
     let parsed = PasswordHash::new(hash).map_err(|_| ErrorCodeString::new("PASSWORD_VERIFY"))?;
-    let argon2 = argon2_instance()?;
+    let argon2 = Argon2::default();
     Ok(argon2.verify_password(password.as_bytes(), &parsed).is_ok())
 }
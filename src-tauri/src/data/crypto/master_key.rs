@@ -4,9 +4,10 @@ use rand::rngs::OsRng;
 use rand::RngCore;
 use zeroize::Zeroizing;
 
-use crate::data::crypto::{cipher, dpapi};
+use crate::data::crypto::{cipher, secret_store};
 use crate::data::fs::atomic_write::write_atomic;
 use crate::data::profiles::paths::{dpapi_key_path, vault_key_path};
+use crate::data::storage::vault_blob::{VaultBlobKind, VaultBlobStorage};
 use crate::data::storage_paths::StoragePaths;
 use crate::error::{ErrorCodeString, Result};
 
@@ -58,28 +59,38 @@ fn aad(profile_id: &str) -> Vec<u8> {
     format!("master_key:{}", profile_id).into_bytes()
 }
 
+/// Writes the envelope master key through a `VaultBlobStorage` backend
+/// (local disk by default, optionally a synced remote one) rather than a
+/// fixed local path, so a profile's encrypted key blob can live wherever
+/// `AppState::vault_blob_storage` is configured to put it.
 pub fn write_master_key_wrapped_with_password(
-    sp: &StoragePaths,
+    storage: &dyn VaultBlobStorage,
     profile_id: &str,
     wrapping_key: &[u8; MASTER_KEY_LEN],
     master_key: &[u8; MASTER_KEY_LEN],
 ) -> Result<()> {
     let plaintext = build_plaintext(profile_id, master_key);
     let blob = cipher::encrypt_bytes(wrapping_key, &aad(profile_id), plaintext.as_slice())?;
-    cipher::write_encrypted_file(&vault_key_path(sp, profile_id)?, &blob)
+    storage.write_blob(profile_id, VaultBlobKind::MasterKey, &blob)
 }
 
 pub fn read_master_key_wrapped_with_password(
-    sp: &StoragePaths,
+    storage: &dyn VaultBlobStorage,
     profile_id: &str,
     wrapping_key: &[u8; MASTER_KEY_LEN],
 ) -> Result<[u8; MASTER_KEY_LEN]> {
-    let blob = cipher::read_encrypted_file(&vault_key_path(sp, profile_id)?)?;
+    let blob = storage.read_blob(profile_id, VaultBlobKind::MasterKey)?;
     let plaintext = cipher::decrypt_bytes(wrapping_key, &aad(profile_id), &blob)?;
     parse_plaintext(profile_id, &plaintext)
 }
 
-pub fn read_master_key_wrapped_with_dpapi(
+/// Reads the legacy `dpapi_key.bin` blob through `secret_store::unprotect`.
+/// Despite the file's name (left as-is so existing installs keep working —
+/// see `dpapi_key_path`), this has protected the key via whatever OS-bound
+/// store `secret_store` picks for the current platform (DPAPI, Keychain,
+/// Secret Service, or its file fallback) ever since `secret_store` stopped
+/// being Windows-only, not just on Windows.
+pub fn read_master_key_wrapped_with_keyring(
     sp: &StoragePaths,
     profile_id: &str,
 ) -> Result<[u8; MASTER_KEY_LEN]> {
@@ -88,7 +99,7 @@ pub fn read_master_key_wrapped_with_dpapi(
         return Err(ErrorCodeString::new("DPAPI_KEY_MISSING"));
     }
     let protected = fs::read(&path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
-    let plaintext = dpapi::unprotect(&protected, Some(profile_id.as_bytes()))?;
+    let plaintext = secret_store::unprotect(&protected, Some(profile_id.as_bytes()))?;
     parse_plaintext(profile_id, &plaintext)
 }
 
@@ -129,7 +140,8 @@ pub fn read_master_key_unwrapped(
 /// Read passwordless master key in the current (portable) format.
 ///
 /// Backwards-compatibility:
-/// If vault_key.bin doesn't exist yet, we try legacy dpapi_key.bin (Windows only),
+/// If vault_key.bin doesn't exist yet, we try the legacy dpapi_key.bin —
+/// keyring-wrapped via `secret_store` on every platform, not just Windows —
 /// and migrate it to portable vault_key.bin.
 pub fn read_master_key_passwordless_portable(
     sp: &StoragePaths,
@@ -138,12 +150,12 @@ pub fn read_master_key_passwordless_portable(
     match read_master_key_unwrapped(sp, profile_id) {
         Ok(key) => Ok(key),
         Err(e) => {
-            // Only attempt DPAPI migration when the portable file is missing.
+            // Only attempt the keyring migration when the portable file is missing.
             if e.code != "VAULT_KEY_MISSING" {
                 return Err(e);
             }
 
-            let key = read_master_key_wrapped_with_dpapi(sp, profile_id)?;
+            let key = read_master_key_wrapped_with_keyring(sp, profile_id)?;
 
             // Best-effort migration to portable format.
             let _ = write_master_key_unwrapped(sp, profile_id, &key);
@@ -0,0 +1,347 @@
+//! Cross-platform secret storage for small at-rest secrets (e.g. a cached
+//! key, the native-host IPC token) that should be bound to this OS
+//! user/machine rather than to our own application key material.
+//!
+//! This used to be Windows-only DPAPI. OS keychains don't transform bytes
+//! the way DPAPI does, though — they're a labeled key-value store, not a
+//! `protect(bytes) -> bytes` function. So every platform here keeps a
+//! random *wrapping key* in its OS store, under a label derived from
+//! `entropy`, and uses that wrapping key with our own AEAD
+//! (`data::crypto::cipher`) to encrypt/decrypt the caller's actual bytes.
+//! That keeps `protect`/`unprotect`'s signature and on-disk ciphertext
+//! shape identical across platforms, while still tying the secret to
+//! OS-bound storage the way DPAPI did. `entropy` doubles as the AEAD's
+//! associated data, same as before, so a ciphertext written under one
+//! label still fails closed if read back under another.
+//!
+//! - Windows: the wrapping key is protected with DPAPI
+//!   (`CryptProtectData`/`CryptUnprotectData`), same primitive this module
+//!   used directly before it covered more than one platform.
+//! - macOS: the wrapping key lives in the login Keychain, via
+//!   `security-framework`'s safe wrapper over `SecItemAdd`/`SecItemCopyMatching`
+//!   (a generic password item, accessible without a prompt or iCloud sync).
+//! - Linux: the wrapping key lives in the desktop Secret Service
+//!   (`secret-service` crate, i.e. libsecret/D-Bus) when a keyring daemon
+//!   answers; if none is running (headless box, minimal window manager) we
+//!   fall back to a 0600 file next to the app binary. That's the same
+//!   reduced-security tradeoff `master_key::write_master_key_unwrapped`
+//!   already documents for its own passwordless mode, just applied here.
+
+use crate::data::crypto::cipher;
+use crate::error::{ErrorCodeString, Result};
+
+const WRAPPING_KEY_LEN: usize = 32;
+
+/// Keeps (or creates) one random wrapping key per label, using whatever
+/// OS-bound storage this platform has available.
+trait SecretStore {
+    fn wrapping_key(&self, label: &str) -> Result<[u8; WRAPPING_KEY_LEN]>;
+}
+
+fn label_for(entropy: Option<&[u8]>) -> String {
+    match entropy {
+        Some(bytes) if !bytes.is_empty() => format!("pm-secret-{}", hex_encode(bytes)),
+        _ => "pm-secret-default".to_string(),
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn protect(plaintext: &[u8], entropy: Option<&[u8]>) -> Result<Vec<u8>> {
+    let label = label_for(entropy);
+    let wrapping_key = platform_store().wrapping_key(&label)?;
+    cipher::encrypt_bytes(&wrapping_key, entropy.unwrap_or(&[]), plaintext)
+}
+
+pub fn unprotect(ciphertext: &[u8], entropy: Option<&[u8]>) -> Result<Vec<u8>> {
+    let label = label_for(entropy);
+    let wrapping_key = platform_store().wrapping_key(&label)?;
+    cipher::decrypt_bytes(&wrapping_key, entropy.unwrap_or(&[]), ciphertext)
+}
+
+/// Directory used by impls that need somewhere on disk to keep OS-protected
+/// material (the DPAPI blob on Windows, the keyring-less fallback on
+/// Linux). Sits next to the executable, same as `StoragePaths` does for the
+/// app dir before a workspace is chosen — `protect`/`unprotect` have no
+/// `StoragePaths` to thread through, since their signature predates it and
+/// callers shouldn't have to change to keep using them.
+fn fallback_dir() -> Result<std::path::PathBuf> {
+    let exe_path =
+        std::env::current_exe().map_err(|_| ErrorCodeString::new("APP_DIR_UNAVAILABLE"))?;
+    let dir = exe_path
+        .parent()
+        .ok_or_else(|| ErrorCodeString::new("APP_DIR_UNAVAILABLE"))?
+        .join("pm-secrets");
+    std::fs::create_dir_all(&dir).map_err(|_| ErrorCodeString::new("SECRET_STORE_UNAVAILABLE"))?;
+    Ok(dir)
+}
+
+fn generate_wrapping_key() -> [u8; WRAPPING_KEY_LEN] {
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+    let mut key = [0u8; WRAPPING_KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+#[cfg(windows)]
+fn platform_store() -> impl SecretStore {
+    windows_impl::DpapiStore
+}
+
+#[cfg(target_os = "macos")]
+fn platform_store() -> impl SecretStore {
+    macos_impl::KeychainStore
+}
+
+#[cfg(target_os = "linux")]
+fn platform_store() -> impl SecretStore {
+    linux_impl::SecretServiceStore
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn platform_store() -> impl SecretStore {
+    unsupported_impl::UnsupportedStore
+}
+
+#[cfg(windows)]
+mod windows_impl {
+    use super::{fallback_dir, generate_wrapping_key, SecretStore, WRAPPING_KEY_LEN};
+    use crate::error::{ErrorCodeString, Result};
+    use std::ptr;
+    use core::ffi::c_void;
+
+    // windows-sys does not always expose the `DATA_BLOB` alias name.
+    // In Win32 headers, `DATA_BLOB` is just an alias of `_CRYPTOAPI_BLOB` (aka
+    // `CRYPT_INTEGER_BLOB`). We use `CRYPT_INTEGER_BLOB` to keep this
+    // compatible across windows-sys versions.
+    use windows_sys::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+    use windows_sys::Win32::Foundation::LocalFree;
+
+    type DataBlob = CRYPT_INTEGER_BLOB;
+
+    fn blob_from_slice(bytes: &[u8]) -> DataBlob {
+        DataBlob {
+            cbData: bytes.len() as u32,
+            pbData: bytes.as_ptr() as *mut u8,
+        }
+    }
+
+    fn dpapi_protect(plaintext: &[u8], entropy: &[u8]) -> Result<Vec<u8>> {
+        unsafe {
+            let mut in_blob = blob_from_slice(plaintext);
+            let mut out_blob = DataBlob { cbData: 0, pbData: ptr::null_mut() };
+            let mut ent_blob = blob_from_slice(entropy);
+
+            let ok = CryptProtectData(
+                &mut in_blob as *mut DataBlob,
+                ptr::null(),
+                &mut ent_blob as *mut DataBlob,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut out_blob as *mut DataBlob,
+            );
+            if ok == 0 {
+                return Err(ErrorCodeString::new("DPAPI_PROTECT_FAILED"));
+            }
+            let out =
+                std::slice::from_raw_parts(out_blob.pbData as *const u8, out_blob.cbData as usize)
+                    .to_vec();
+            let _ = LocalFree(out_blob.pbData as *mut c_void);
+            Ok(out)
+        }
+    }
+
+    fn dpapi_unprotect(ciphertext: &[u8], entropy: &[u8]) -> Result<Vec<u8>> {
+        unsafe {
+            let mut in_blob = blob_from_slice(ciphertext);
+            let mut out_blob = DataBlob { cbData: 0, pbData: ptr::null_mut() };
+            let mut ent_blob = blob_from_slice(entropy);
+
+            let ok = CryptUnprotectData(
+                &mut in_blob as *mut DataBlob,
+                ptr::null_mut(),
+                &mut ent_blob as *mut DataBlob,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut out_blob as *mut DataBlob,
+            );
+            if ok == 0 {
+                return Err(ErrorCodeString::new("DPAPI_UNPROTECT_FAILED"));
+            }
+            let out =
+                std::slice::from_raw_parts(out_blob.pbData as *const u8, out_blob.cbData as usize)
+                    .to_vec();
+            let _ = LocalFree(out_blob.pbData as *mut c_void);
+            Ok(out)
+        }
+    }
+
+    pub(super) struct DpapiStore;
+
+    impl SecretStore for DpapiStore {
+        fn wrapping_key(&self, label: &str) -> Result<[u8; WRAPPING_KEY_LEN]> {
+            let path = fallback_dir()?.join(format!("{label}.dpapi"));
+            if path.exists() {
+                let protected = std::fs::read(&path)
+                    .map_err(|_| ErrorCodeString::new("SECRET_STORE_READ_FAILED"))?;
+                let plaintext = dpapi_unprotect(&protected, label.as_bytes())?;
+                if plaintext.len() != WRAPPING_KEY_LEN {
+                    return Err(ErrorCodeString::new("SECRET_STORE_CORRUPTED"));
+                }
+                let mut key = [0u8; WRAPPING_KEY_LEN];
+                key.copy_from_slice(&plaintext);
+                return Ok(key);
+            }
+
+            let key = generate_wrapping_key();
+            let protected = dpapi_protect(&key, label.as_bytes())?;
+            std::fs::write(&path, &protected)
+                .map_err(|_| ErrorCodeString::new("SECRET_STORE_WRITE_FAILED"))?;
+            Ok(key)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos_impl {
+    use super::{generate_wrapping_key, SecretStore, WRAPPING_KEY_LEN};
+    use crate::error::{ErrorCodeString, Result};
+    use security_framework::passwords::{get_generic_password, set_generic_password};
+
+    // Scopes every item this app puts in the Keychain so we never collide
+    // with another app's generic passwords under the same account name.
+    const SERVICE: &str = "password-manager.secret-store";
+
+    pub(super) struct KeychainStore;
+
+    impl SecretStore for KeychainStore {
+        fn wrapping_key(&self, label: &str) -> Result<[u8; WRAPPING_KEY_LEN]> {
+            match get_generic_password(SERVICE, label) {
+                Ok(bytes) if bytes.len() == WRAPPING_KEY_LEN => {
+                    let mut key = [0u8; WRAPPING_KEY_LEN];
+                    key.copy_from_slice(&bytes);
+                    Ok(key)
+                }
+                Ok(_) => Err(ErrorCodeString::new("SECRET_STORE_CORRUPTED")),
+                Err(_) => {
+                    let key = generate_wrapping_key();
+                    set_generic_password(SERVICE, label, &key)
+                        .map_err(|_| ErrorCodeString::new("SECRET_STORE_WRITE_FAILED"))?;
+                    Ok(key)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux_impl {
+    use super::{fallback_dir, generate_wrapping_key, SecretStore, WRAPPING_KEY_LEN};
+    use crate::error::{ErrorCodeString, Result};
+    use secret_service::{EncryptionType, SecretService};
+    use std::collections::HashMap;
+
+    const COLLECTION: &str = "default";
+    const ATTR_KEY: &str = "password-manager-secret-store-label";
+
+    pub(super) struct SecretServiceStore;
+
+    impl SecretStore for SecretServiceStore {
+        fn wrapping_key(&self, label: &str) -> Result<[u8; WRAPPING_KEY_LEN]> {
+            match secret_service_wrapping_key(label) {
+                Ok(key) => Ok(key),
+                Err(_) => fallback_file_wrapping_key(label),
+            }
+        }
+    }
+
+    /// Tries the real desktop Secret Service (libsecret/D-Bus). Fails
+    /// whenever no keyring daemon answers, e.g. a headless box or a
+    /// minimal window manager with no `gnome-keyring`/`kwallet` running.
+    fn secret_service_wrapping_key(label: &str) -> Result<[u8; WRAPPING_KEY_LEN]> {
+        let service = SecretService::connect(EncryptionType::Dh)
+            .map_err(|_| ErrorCodeString::new("SECRET_SERVICE_UNAVAILABLE"))?;
+        let collection = service
+            .get_default_collection()
+            .map_err(|_| ErrorCodeString::new("SECRET_SERVICE_UNAVAILABLE"))?;
+
+        let mut attrs = HashMap::new();
+        attrs.insert(ATTR_KEY, label);
+
+        let existing = collection
+            .search_items(attrs.clone())
+            .map_err(|_| ErrorCodeString::new("SECRET_SERVICE_UNAVAILABLE"))?;
+        if let Some(item) = existing.into_iter().next() {
+            let bytes = item
+                .get_secret()
+                .map_err(|_| ErrorCodeString::new("SECRET_STORE_READ_FAILED"))?;
+            if bytes.len() != WRAPPING_KEY_LEN {
+                return Err(ErrorCodeString::new("SECRET_STORE_CORRUPTED"));
+            }
+            let mut key = [0u8; WRAPPING_KEY_LEN];
+            key.copy_from_slice(&bytes);
+            return Ok(key);
+        }
+
+        let key = generate_wrapping_key();
+        collection
+            .create_item(
+                COLLECTION,
+                attrs,
+                &key,
+                true,
+                "application/octet-stream",
+            )
+            .map_err(|_| ErrorCodeString::new("SECRET_STORE_WRITE_FAILED"))?;
+        Ok(key)
+    }
+
+    /// No-keyring-daemon fallback: the wrapping key sits unencrypted in a
+    /// 0600 file next to the binary. Weaker than DPAPI/Keychain/Secret
+    /// Service, but still better than inlining it directly in the caller's
+    /// ciphertext — and it's the same tradeoff this crate already accepts
+    /// for its own passwordless portable mode.
+    fn fallback_file_wrapping_key(label: &str) -> Result<[u8; WRAPPING_KEY_LEN]> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = fallback_dir()?.join(format!("{label}.key"));
+        if path.exists() {
+            let bytes = std::fs::read(&path)
+                .map_err(|_| ErrorCodeString::new("SECRET_STORE_READ_FAILED"))?;
+            if bytes.len() != WRAPPING_KEY_LEN {
+                return Err(ErrorCodeString::new("SECRET_STORE_CORRUPTED"));
+            }
+            let mut key = [0u8; WRAPPING_KEY_LEN];
+            key.copy_from_slice(&bytes);
+            return Ok(key);
+        }
+
+        let key = generate_wrapping_key();
+        std::fs::write(&path, key)
+            .map_err(|_| ErrorCodeString::new("SECRET_STORE_WRITE_FAILED"))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|_| ErrorCodeString::new("SECRET_STORE_WRITE_FAILED"))?;
+        Ok(key)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod unsupported_impl {
+    use super::{SecretStore, WRAPPING_KEY_LEN};
+    use crate::error::{ErrorCodeString, Result};
+
+    pub(super) struct UnsupportedStore;
+
+    impl SecretStore for UnsupportedStore {
+        fn wrapping_key(&self, _label: &str) -> Result<[u8; WRAPPING_KEY_LEN]> {
+            Err(ErrorCodeString::new("SECRET_STORE_UNSUPPORTED_PLATFORM"))
+        }
+    }
+}
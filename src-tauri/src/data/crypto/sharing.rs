@@ -0,0 +1,80 @@
+//! x25519 key exchange + AES-GCM envelopes for sharing a single item between
+//! two profiles (or two devices/users) without either side learning the
+//! other's master password or vault key.
+//!
+//! Each profile has a long-lived x25519 identity keypair (see
+//! `services::sharing_service`). To share an item, the sender does a
+//! Diffie-Hellman exchange with the recipient's public key, stretches the
+//! shared secret through HKDF-SHA256, and seals the item with the result
+//! using AES-256-GCM. Only someone holding the matching private key can
+//! open the envelope.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::{ErrorCodeString, Result};
+
+pub const NONCE_LEN: usize = 12;
+const HKDF_INFO: &[u8] = b"pm-share-envelope-v1";
+
+pub fn generate_identity() -> StaticSecret {
+    StaticSecret::random_from_rng(OsRng)
+}
+
+pub fn public_key_of(secret: &StaticSecret) -> PublicKey {
+    PublicKey::from(secret)
+}
+
+/// Derives a 32-byte AES-256 key from an x25519 Diffie-Hellman exchange.
+/// Both sides compute the same key: `dh(our_secret, their_public)` is
+/// symmetric regardless of which side calls it.
+pub fn derive_shared_key(our_secret: &StaticSecret, their_public: &PublicKey) -> [u8; 32] {
+    let shared_secret = our_secret.diffie_hellman(their_public);
+    let hk = Hkdf::<Sha256>::new(None, shared_secret.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(HKDF_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Seals `plaintext` with AES-256-GCM under `shared_key`, using `aad` (e.g.
+/// the item id) to bind the envelope to its context. Returns
+/// `nonce || ciphertext`.
+pub fn encrypt_envelope(shared_key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(shared_key)
+        .map_err(|_| ErrorCodeString::new("SHARE_ENVELOPE_KEY_INVALID"))?;
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| ErrorCodeString::new("SHARE_ENVELOPE_ENCRYPT_FAILED"))?;
+
+    let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn decrypt_envelope(shared_key: &[u8; 32], aad: &[u8], envelope: &[u8]) -> Result<Vec<u8>> {
+    if envelope.len() < NONCE_LEN {
+        return Err(ErrorCodeString::new("SHARE_ENVELOPE_CORRUPTED"));
+    }
+    let (nonce_bytes, ciphertext) = envelope.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new_from_slice(shared_key)
+        .map_err(|_| ErrorCodeString::new("SHARE_ENVELOPE_KEY_INVALID"))?;
+
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce_bytes),
+            Payload { msg: ciphertext, aad },
+        )
+        .map_err(|_| ErrorCodeString::new("SHARE_ENVELOPE_DECRYPT_FAILED"))
+}
@@ -0,0 +1,319 @@
+//! Frame-oriented streaming counterpart to `cipher::encrypt_bytes`/
+//! `decrypt_bytes`, for blobs too large to hold in memory all at once (see
+//! `attachments_service::add_attachment_from_path`/`save_attachment_to_path`).
+//! The plaintext is split into fixed-size frames; each is sealed
+//! independently with XChaCha20-Poly1305 so encryption/decryption only ever
+//! needs one frame resident at a time, streaming from a `Read` to a
+//! `Write`.
+//!
+//! Every frame's nonce shares a random 16-byte prefix (written once, in the
+//! header) with its low 8 bytes set to the frame's index, so no per-frame
+//! nonce needs to be stored — it's reconstructed purely from position. The
+//! last frame authenticates a `final` marker as part of its AAD; every
+//! other frame authenticates `not final`. Because only one of those two
+//! markers can have been the one actually sealed under, truncating the
+//! stream (a later frame silently became "last") or appending data after
+//! the true last frame (the true last frame is no longer last) both change
+//! which marker the reader expects and make the affected frame fail to
+//! authenticate — so these tamper attempts surface as `DECRYPT_FAILED`
+//! rather than silently truncated/extended output.
+//!
+//! There's no separate "total frame count" field in the header: a true
+//! streaming writer doesn't know that count until the source is exhausted,
+//! and re-seeking to patch it in afterward would require the destination
+//! to be seekable, which `Write` alone doesn't guarantee. The final-frame
+//! marker serves the same purpose (detecting where the real stream ends)
+//! without that requirement.
+
+use std::io::{self, Read, Write};
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::error::{ErrorCodeString, Result};
+
+const MAGIC: &[u8; 4] = b"PMS1";
+const NONCE_PREFIX_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+
+/// Plaintext frame size. 1 MiB keeps per-frame AEAD overhead negligible
+/// while still bounding peak memory far below a multi-hundred-MB file.
+pub const FRAME_SIZE: usize = 1024 * 1024;
+
+fn cipher_for(key: &[u8; 32]) -> Result<XChaCha20Poly1305> {
+    XChaCha20Poly1305::new_from_slice(key).map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))
+}
+
+fn frame_nonce(prefix: &[u8; NONCE_PREFIX_LEN], index: u64) -> XNonce {
+    let mut bytes = [0u8; NONCE_PREFIX_LEN + 8];
+    bytes[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    bytes[NONCE_PREFIX_LEN..].copy_from_slice(&index.to_le_bytes());
+    *XNonce::from_slice(&bytes)
+}
+
+fn frame_aad(stream_aad: &[u8], index: u64, is_final: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(stream_aad.len() + 9);
+    out.extend_from_slice(stream_aad);
+    out.extend_from_slice(&index.to_le_bytes());
+    out.push(is_final as u8);
+    out
+}
+
+/// Fills `buf` from `reader`, looping on short reads, and only returns
+/// fewer than `buf.len()` bytes once the source is genuinely exhausted.
+fn fill_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+fn write_frame(writer: &mut impl Write, ciphertext: &[u8]) -> Result<()> {
+    writer
+        .write_all(&(ciphertext.len() as u32).to_le_bytes())
+        .and_then(|_| writer.write_all(ciphertext))
+        .map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))
+}
+
+/// Reads one length-prefixed frame, or `Ok(None)` at a clean EOF before any
+/// bytes of the next frame's length prefix were read.
+fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len_bytes = [0u8; 4];
+    let n = fill_or_eof(reader, &mut len_bytes).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n != 4 {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    let mut ciphertext = vec![0u8; len];
+    let read_len =
+        fill_or_eof(reader, &mut ciphertext).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    if read_len != len {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    Ok(Some(ciphertext))
+}
+
+/// Streams `reader` through fixed `FRAME_SIZE` plaintext frames, sealing
+/// each under `key` and `aad`, and writes the framed ciphertext to
+/// `writer`. Never holds more than one frame of plaintext or ciphertext in
+/// memory regardless of the source's total size.
+pub fn encrypt_stream(key: &[u8; 32], aad: &[u8], reader: &mut impl Read, writer: &mut impl Write) -> Result<()> {
+    let cipher = cipher_for(key)?;
+
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    {
+        use rand::rngs::OsRng;
+        use rand::RngCore;
+        OsRng.fill_bytes(&mut prefix);
+    }
+
+    writer
+        .write_all(MAGIC)
+        .and_then(|_| writer.write_all(&prefix))
+        .map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))?;
+
+    let mut buf = vec![0u8; FRAME_SIZE];
+    let mut index: u64 = 0;
+    // One frame of lookahead: a frame isn't written until we know whether
+    // the *next* read comes back empty, which is what tells us it was the
+    // last one.
+    let mut pending: Option<Vec<u8>> = None;
+
+    loop {
+        let n = fill_or_eof(reader, &mut buf).map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))?;
+        if n == 0 {
+            if let Some(prev) = pending.take() {
+                seal_and_write(&cipher, &prefix, aad, index, &prev, true, writer)?;
+            }
+            break;
+        }
+
+        if let Some(prev) = pending.take() {
+            seal_and_write(&cipher, &prefix, aad, index, &prev, false, writer)?;
+            index += 1;
+        }
+        pending = Some(buf[..n].to_vec());
+    }
+
+    Ok(())
+}
+
+fn seal_and_write(
+    cipher: &XChaCha20Poly1305,
+    prefix: &[u8; NONCE_PREFIX_LEN],
+    aad: &[u8],
+    index: u64,
+    plaintext: &[u8],
+    is_final: bool,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let nonce = frame_nonce(prefix, index);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: frame_aad(aad, index, is_final).as_slice() })
+        .map_err(|_| ErrorCodeString::new("ENCRYPT_FAILED"))?;
+    write_frame(writer, &ciphertext)
+}
+
+/// Inverse of `encrypt_stream`: streams framed ciphertext from `reader`,
+/// verifying and decrypting each frame under `key`/`aad`, and writes the
+/// reassembled plaintext to `writer`.
+pub fn decrypt_stream(key: &[u8; 32], aad: &[u8], reader: &mut impl Read, writer: &mut impl Write) -> Result<()> {
+    let cipher = cipher_for(key)?;
+
+    let mut header = [0u8; 4 + NONCE_PREFIX_LEN];
+    let header_len =
+        fill_or_eof(reader, &mut header).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    if header_len != header.len() || &header[..4] != MAGIC {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    prefix.copy_from_slice(&header[4..]);
+
+    let mut index: u64 = 0;
+    let mut pending: Option<Vec<u8>> = None;
+
+    loop {
+        let frame = read_frame(reader)?;
+        match frame {
+            None => {
+                if let Some(prev) = pending.take() {
+                    open_and_write(&cipher, &prefix, aad, index, &prev, true, writer)?;
+                }
+                break;
+            }
+            Some(ciphertext) => {
+                if let Some(prev) = pending.take() {
+                    open_and_write(&cipher, &prefix, aad, index, &prev, false, writer)?;
+                    index += 1;
+                }
+                pending = Some(ciphertext);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Like `decrypt_stream`, but only authenticates and writes the plaintext
+/// bytes overlapping `[start, end)` (a plaintext byte range) instead of the
+/// whole stream — used by `attachments_service::get_attachment_range` so
+/// paging through a large attachment doesn't pay to decrypt frames outside
+/// the page asked for. Frames before the range still have to be read off
+/// `reader` (length-prefixed framing gives no other way to find where a
+/// later frame starts) but are never decrypted; reading stops as soon as
+/// the range's last frame has been written.
+pub fn decrypt_stream_range(
+    key: &[u8; 32],
+    aad: &[u8],
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    start: u64,
+    end: u64,
+) -> Result<()> {
+    if end <= start {
+        return Ok(());
+    }
+    let cipher = cipher_for(key)?;
+
+    let mut header = [0u8; 4 + NONCE_PREFIX_LEN];
+    let header_len =
+        fill_or_eof(reader, &mut header).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    if header_len != header.len() || &header[..4] != MAGIC {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    prefix.copy_from_slice(&header[4..]);
+
+    let start_frame = start / FRAME_SIZE as u64;
+    let end_frame = (end - 1) / FRAME_SIZE as u64;
+
+    let mut index: u64 = 0;
+    let mut pending: Option<Vec<u8>> = None;
+
+    loop {
+        let frame = read_frame(reader)?;
+        match frame {
+            None => {
+                if let Some(prev) = pending.take() {
+                    if index >= start_frame && index <= end_frame {
+                        write_clipped_frame(&cipher, &prefix, aad, index, &prev, true, writer, start, end)?;
+                    }
+                }
+                break;
+            }
+            Some(ciphertext) => {
+                if let Some(prev) = pending.take() {
+                    if index >= start_frame && index <= end_frame {
+                        write_clipped_frame(&cipher, &prefix, aad, index, &prev, false, writer, start, end)?;
+                    }
+                    let done = index >= end_frame;
+                    index += 1;
+                    if done {
+                        break;
+                    }
+                }
+                pending = Some(ciphertext);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_clipped_frame(
+    cipher: &XChaCha20Poly1305,
+    prefix: &[u8; NONCE_PREFIX_LEN],
+    aad: &[u8],
+    index: u64,
+    ciphertext: &[u8],
+    is_final: bool,
+    writer: &mut impl Write,
+    start: u64,
+    end: u64,
+) -> Result<()> {
+    if ciphertext.len() < TAG_LEN {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    let nonce = frame_nonce(prefix, index);
+    let plaintext = cipher
+        .decrypt(&nonce, Payload { msg: ciphertext, aad: frame_aad(aad, index, is_final).as_slice() })
+        .map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+
+    let frame_start = index * FRAME_SIZE as u64;
+    let frame_end = frame_start + plaintext.len() as u64;
+    let clip_start = (start.max(frame_start) - frame_start) as usize;
+    let clip_end = (end.min(frame_end) - frame_start) as usize;
+    if clip_start < clip_end {
+        writer
+            .write_all(&plaintext[clip_start..clip_end])
+            .map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    }
+    Ok(())
+}
+
+fn open_and_write(
+    cipher: &XChaCha20Poly1305,
+    prefix: &[u8; NONCE_PREFIX_LEN],
+    aad: &[u8],
+    index: u64,
+    ciphertext: &[u8],
+    is_final: bool,
+    writer: &mut impl Write,
+) -> Result<()> {
+    if ciphertext.len() < TAG_LEN {
+        return Err(ErrorCodeString::new("DECRYPT_FAILED"));
+    }
+    let nonce = frame_nonce(prefix, index);
+    let plaintext = cipher
+        .decrypt(&nonce, Payload { msg: ciphertext, aad: frame_aad(aad, index, is_final).as_slice() })
+        .map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))?;
+    writer.write_all(&plaintext).map_err(|_| ErrorCodeString::new("DECRYPT_FAILED"))
+}
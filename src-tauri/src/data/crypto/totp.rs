@@ -0,0 +1,303 @@
+//! RFC 6238 time-based one-time passwords, computed from an `otpauth://`
+//! secret stored on a datacard (`DataCard::totp_uri`). Used by the native
+//! bridge (`ipc::server`'s `get_totp` request and the TOTP field on
+//! `get_credential_for_fill`) to hand the browser extension a fresh code
+//! without ever storing the code itself.
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::error::{ErrorCodeString, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    fn parse(name: &str) -> TotpAlgorithm {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA256" => TotpAlgorithm::Sha256,
+            "SHA512" => TotpAlgorithm::Sha512,
+            _ => TotpAlgorithm::Sha1,
+        }
+    }
+}
+
+/// The pieces of an `otpauth://totp/...` URI we actually need. Everything
+/// else (issuer, account name in the label) only matters for display and
+/// is left to the caller to parse out of the URI itself if it wants it.
+pub struct OtpAuthParams {
+    pub secret: String,
+    pub digits: u32,
+    pub period: u64,
+    pub algorithm: TotpAlgorithm,
+}
+
+/// A freshly computed code plus how long it's still valid for, so the
+/// caller can decide whether to show a countdown or just refetch.
+pub struct TotpCode {
+    pub code: String,
+    pub seconds_remaining: u64,
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                    }
+                    None => {
+                        out.push(bytes[i]);
+                        i += 1;
+                    }
+                }
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parses the `secret`/`digits`/`period`/`algorithm` query parameters out
+/// of an `otpauth://totp/...` URI. Unspecified parameters fall back to the
+/// Google Authenticator defaults (SHA1, 6 digits, 30-second period), same
+/// as every other TOTP app.
+pub fn parse_otpauth_uri(uri: &str) -> Result<OtpAuthParams> {
+    let trimmed = uri.trim();
+    let rest = trimmed
+        .strip_prefix("otpauth://totp/")
+        .ok_or_else(|| ErrorCodeString::new("TOTP_URI_INVALID"))?;
+    let query = rest
+        .split_once('?')
+        .map(|(_, q)| q)
+        .ok_or_else(|| ErrorCodeString::new("TOTP_URI_INVALID"))?;
+
+    let mut secret = None;
+    let mut digits = 6u32;
+    let mut period = 30u64;
+    let mut algorithm = TotpAlgorithm::Sha1;
+
+    for pair in query.split('&') {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+        let value = percent_decode(value);
+        match key {
+            "secret" => secret = Some(value),
+            "digits" => digits = value.parse().unwrap_or(6),
+            "period" => period = value.parse().unwrap_or(30),
+            "algorithm" => algorithm = TotpAlgorithm::parse(&value),
+            _ => {}
+        }
+    }
+
+    let secret = secret
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ErrorCodeString::new("TOTP_URI_INVALID"))?;
+    // Capped at 9, not 10: `hotp` computes its modulus as `10u32.pow(digits)`,
+    // and `10u32.pow(10)` overflows `u32::MAX` (panics in debug, wraps in
+    // release) — 9 digits is already far beyond what any real authenticator
+    // app displays.
+    if digits == 0 || digits > 9 || period == 0 {
+        return Err(ErrorCodeString::new("TOTP_URI_INVALID"));
+    }
+
+    Ok(OtpAuthParams {
+        secret,
+        digits,
+        period,
+        algorithm,
+    })
+}
+
+/// Parses a `CustomField` value of type `Totp`, which a user may have
+/// entered either as a full `otpauth://totp/...` provisioning URI or as a
+/// bare Base32 secret copied straight out of a setup page. Falls back to
+/// the Google Authenticator defaults (SHA1, 6 digits, 30-second period)
+/// for the latter, same as an unspecified query parameter in the former.
+pub fn parse_secret_or_uri(value: &str) -> Result<OtpAuthParams> {
+    let trimmed = value.trim();
+    if trimmed.starts_with("otpauth://") {
+        return parse_otpauth_uri(trimmed);
+    }
+    if trimmed.is_empty() {
+        return Err(ErrorCodeString::new("TOTP_SECRET_INVALID"));
+    }
+    Ok(OtpAuthParams {
+        secret: trimmed.to_string(),
+        digits: 6,
+        period: 30,
+        algorithm: TotpAlgorithm::Sha1,
+    })
+}
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32 decode (the alphabet every `otpauth://` secret is
+/// published in), accepting input with or without `=` padding.
+fn base32_decode(input: &str) -> Result<Vec<u8>> {
+    let mut bits: u64 = 0;
+    let mut bit_count: u32 = 0;
+    let mut out = Vec::new();
+
+    for ch in input.chars().filter(|c| *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&b| b == ch.to_ascii_uppercase() as u8)
+            .ok_or_else(|| ErrorCodeString::new("TOTP_SECRET_INVALID"))? as u64;
+        bits = (bits << 5) | value;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+        }
+    }
+
+    if out.is_empty() {
+        return Err(ErrorCodeString::new("TOTP_SECRET_INVALID"));
+    }
+    Ok(out)
+}
+
+/// RFC 4648 base32 encode, no padding — the format every authenticator app
+/// expects a freshly generated secret to be displayed/typed in.
+fn base32_encode(input: &[u8]) -> String {
+    let mut bits: u64 = 0;
+    let mut bit_count: u32 = 0;
+    let mut out = String::new();
+
+    for &byte in input {
+        bits = (bits << 8) | byte as u64;
+        bit_count += 8;
+        while bit_count >= 5 {
+            bit_count -= 5;
+            out.push(BASE32_ALPHABET[((bits >> bit_count) & 0x1f) as usize] as char);
+        }
+    }
+    if bit_count > 0 {
+        out.push(BASE32_ALPHABET[((bits << (5 - bit_count)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Generates a fresh random 160-bit TOTP secret (the size every RFC 6238
+/// reference implementation and authenticator app defaults to for
+/// SHA1-based codes), Base32-encoded for display/QR provisioning.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+/// Builds the `otpauth://totp/...` provisioning URI for a freshly generated
+/// secret, using the Google Authenticator defaults (SHA1, 6 digits,
+/// 30-second period) — the same defaults `parse_secret_or_uri` falls back
+/// to for a bare secret, so a URI built here round-trips through either
+/// parser unchanged.
+pub fn build_otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&digits=6&period=30&algorithm=SHA1",
+        issuer = urlencoding_component(issuer),
+        account = urlencoding_component(account),
+        secret = secret,
+    )
+}
+
+/// Minimal percent-encoding for the label/issuer segments of an `otpauth://`
+/// URI — mirrors `percent_decode`'s alphabet rather than pulling in a URL
+/// crate for two call sites.
+fn urlencoding_component(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(*byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// Checks `token` against the code valid at `unix_seconds` and, to tolerate
+/// clock skew between the device and the authenticator app, the codes one
+/// step before and after it.
+pub fn verify_with_skew(params: &OtpAuthParams, token: &str, unix_seconds: u64) -> Result<bool> {
+    let key = base32_decode(&params.secret)?;
+    let counter = unix_seconds / params.period;
+    for step in [counter.saturating_sub(1), counter, counter + 1] {
+        let code = hotp(params.algorithm, &key, step, params.digits)?;
+        if code == token {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn hmac_digest(algorithm: TotpAlgorithm, key: &[u8], counter: &[u8; 8]) -> Result<Vec<u8>> {
+    match algorithm {
+        TotpAlgorithm::Sha1 => {
+            let mut mac = Hmac::<Sha1>::new_from_slice(key)
+                .map_err(|_| ErrorCodeString::new("TOTP_SECRET_INVALID"))?;
+            mac.update(counter);
+            Ok(mac.finalize().into_bytes().to_vec())
+        }
+        TotpAlgorithm::Sha256 => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key)
+                .map_err(|_| ErrorCodeString::new("TOTP_SECRET_INVALID"))?;
+            mac.update(counter);
+            Ok(mac.finalize().into_bytes().to_vec())
+        }
+        TotpAlgorithm::Sha512 => {
+            let mut mac = Hmac::<Sha512>::new_from_slice(key)
+                .map_err(|_| ErrorCodeString::new("TOTP_SECRET_INVALID"))?;
+            mac.update(counter);
+            Ok(mac.finalize().into_bytes().to_vec())
+        }
+    }
+}
+
+fn hotp(algorithm: TotpAlgorithm, key: &[u8], counter: u64, digits: u32) -> Result<String> {
+    let digest = hmac_digest(algorithm, key, &counter.to_be_bytes())?;
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let truncated = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | (digest[offset + 3] as u32);
+
+    let modulus = 10u32.pow(digits);
+    Ok(format!("{:0width$}", truncated % modulus, width = digits as usize))
+}
+
+/// Generates the TOTP code valid at `unix_seconds`, plus how many seconds
+/// remain before it rolls over to the next one.
+pub fn generate(params: &OtpAuthParams, unix_seconds: u64) -> Result<TotpCode> {
+    let key = base32_decode(&params.secret)?;
+    let counter = unix_seconds / params.period;
+    let code = hotp(params.algorithm, &key, counter, params.digits)?;
+    let seconds_remaining = params.period - (unix_seconds % params.period);
+    Ok(TotpCode {
+        code,
+        seconds_remaining,
+    })
+}
@@ -27,18 +27,73 @@ pub fn kdf_salt_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
     Ok(profile_dir(sp, profile_id)?.join("kdf_salt.bin"))
 }
 
+/// The profile's CRDT-style sync log — see `data::sync::vault_log`. A
+/// separate file from `vault.db` so it can be pushed/pulled independently
+/// of the vault blob through the same `VaultBlobStorage` backend.
+pub fn vault_sync_log_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("vault_sync_log.bin"))
+}
+
+/// The profile's CRR-upgraded replica used for cr-sqlite peer-to-peer sync
+/// — see `data::sqlite::crdt`. Deliberately not `vault.db` itself: a
+/// cr-sqlite connection runs with the `crsqlite` extension loaded and its
+/// syncable tables converted to CRRs (extra `__crsql_clock` shadow tables,
+/// triggers, and bookkeeping columns), which nothing else in this crate
+/// expects to find when it opens the plain vault file.
+pub fn vault_crdt_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("vault_crdt.db"))
+}
+
 pub fn key_check_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
     Ok(profile_dir(sp, profile_id)?.join("key_check.bin"))
 }
 
+/// Where the profile's envelope master key lives: wrapped under the
+/// password-derived KDF key for a protected profile, or stored unwrapped
+/// for the passwordless-portable mode. See `data::crypto::master_key`.
+pub fn vault_key_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("vault_key.bin"))
+}
+
+/// Legacy location of a DPAPI/OS-keystore-wrapped master key, from before
+/// passwordless profiles moved to the portable unwrapped format. Kept only
+/// so `read_master_key_passwordless_portable` can migrate old profiles.
+pub fn dpapi_key_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("dpapi_key.bin"))
+}
+
+/// Where a profile's unlock secret lives once opted into OS-keychain-backed
+/// unlock: the master password, protected under `data::crypto::secret_store`
+/// rather than our own KDF key (it has to be readable with no password
+/// typed in, which is the whole point). See
+/// `profiles_service::store_profile_secret`.
+pub fn keychain_secret_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("keychain_secret.bin"))
+}
+
+/// Where a profile's TOTP second-factor secret lives once enrolled, OS-bound
+/// the same way `keychain_secret_path` is (see
+/// `data::crypto::secret_store`), since it has to be readable to check a
+/// login attempt's token before the master password has even been typed in
+/// some flows. See `profiles_service::enroll_totp`.
+pub fn totp_secret_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("totp_secret.bin"))
+}
+
+pub fn attachments_dir(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("attachments"))
+}
+
+/// Content-addressed: `content_hash` is the SHA-256 digest of the
+/// attachment's *plaintext*, not the attachment's row id, so two datacards
+/// attaching the same file share one on-disk blob. See
+/// `attachments_service::add_attachment_from_path`.
 pub fn attachment_file_path(
     sp: &StoragePaths,
     profile_id: &str,
-    attachment_id: &str,
+    content_hash: &str,
 ) -> Result<PathBuf> {
-    Ok(profile_dir(sp, profile_id)?
-        .join("attachments")
-        .join(format!("{attachment_id}.bin")))
+    Ok(attachments_dir(sp, profile_id)?.join(format!("{content_hash}.bin")))
 }
 
 pub fn attachments_preview_root(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
@@ -60,6 +115,14 @@ pub fn user_settings_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf
     Ok(profile_dir(sp, profile_id)?.join("user_settings.json"))
 }
 
+pub fn share_identity_path(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("share_identity.json"))
+}
+
+pub fn backups_dir(sp: &StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(profile_dir(sp, profile_id)?.join("backups"))
+}
+
 pub fn registry_path(sp: &StoragePaths) -> Result<PathBuf> {
     Ok(profiles_root(sp)?.join("registry.json"))
 }
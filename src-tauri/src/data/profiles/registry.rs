@@ -3,7 +3,7 @@ use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use crate::data::crypto::kdf::derive_master_key;
+use crate::data::crypto::kdf::{derive_master_key_versioned, read_params_file};
 use crate::data::crypto::key_check;
 use crate::data::profiles::paths::{
     ensure_profiles_dir, kdf_salt_path, profile_config_path, registry_path,
@@ -18,6 +18,15 @@ pub struct ProfileRecord {
     pub id: String,
     pub name: String,
     pub has_password: bool,
+    /// Whether this profile's master password is also held in the OS
+    /// keychain, so `unlock_from_keychain` can unlock it without a prompt.
+    /// See `profiles_service::store_profile_secret`.
+    #[serde(default)]
+    pub keychain_backed: bool,
+    /// Whether a TOTP secret is enrolled for this profile. See
+    /// `profiles_service::enroll_totp`.
+    #[serde(default)]
+    pub has_totp: bool,
 }
 
 impl From<ProfileRecord> for ProfileMeta {
@@ -26,6 +35,8 @@ impl From<ProfileRecord> for ProfileMeta {
             id: value.id,
             name: value.name,
             has_password: value.has_password,
+            keychain_backed: value.keychain_backed,
+            has_totp: value.has_totp,
         }
     }
 }
@@ -41,16 +52,16 @@ fn load_registry(sp: &StoragePaths) -> Result<ProfileRegistry> {
     if !path.exists() {
         return Ok(ProfileRegistry::default());
     }
-    let content =
-        fs::read_to_string(path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
-    serde_json::from_str(&content).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_PARSE"))
+    let content = fs::read_to_string(&path)
+        .map_err(|e| ErrorCodeString::wrap_io("PROFILE_STORAGE_READ", &e))?;
+    serde_json::from_str(&content).map_err(|e| ErrorCodeString::wrap_json("PROFILE_STORAGE_PARSE", &e))
 }
 
 fn save_registry(sp: &StoragePaths, registry: &ProfileRegistry) -> Result<()> {
     ensure_profiles_dir(sp)?;
     let path = registry_path(sp)?;
     let serialized = serde_json::to_string_pretty(registry)
-        .map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+        .map_err(|e| ErrorCodeString::wrap_json("PROFILE_STORAGE_WRITE", &e))?;
     write_atomic(&path, &serialized)
 }
 
@@ -64,15 +75,15 @@ fn write_atomic(path: &PathBuf, contents: &str) -> Result<()> {
         .ok_or_else(|| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
     let temp_path = parent.join(format!("{file_name}.{}.tmp", Uuid::new_v4()));
 
-    fs::write(&temp_path, contents).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+    fs::write(&temp_path, contents).map_err(|e| ErrorCodeString::wrap_io("PROFILE_STORAGE_WRITE", &e))?;
 
     if fs::rename(&temp_path, path).is_err() {
         if path.exists() {
             let _ = fs::remove_file(path);
         }
-        if let Err(_) = fs::rename(&temp_path, path) {
+        if let Err(retry_err) = fs::rename(&temp_path, path) {
             let _ = fs::remove_file(&temp_path);
-            return Err(ErrorCodeString::new("PROFILE_STORAGE_WRITE"));
+            return Err(ErrorCodeString::wrap_io("PROFILE_STORAGE_WRITE", &retry_err));
         }
     }
 
@@ -101,6 +112,8 @@ pub fn create_profile(
         id: id.clone(),
         name: name.to_string(),
         has_password,
+        keychain_backed: false,
+        has_totp: false,
     };
 
     let profile_dir = crate::data::profiles::paths::profile_dir(sp, &id)?;
@@ -153,6 +166,51 @@ pub fn get_profile(sp: &StoragePaths, id: &str) -> Result<Option<ProfileRecord>>
     Ok(registry.profiles.into_iter().find(|p| p.id == id))
 }
 
+/// Flips `keychain_backed` on a profile's registry record. Called after a
+/// successful `store_profile_secret`/`clear_profile_secret` so the flag
+/// never drifts from whether a secret is actually on disk.
+pub fn set_keychain_backed(sp: &StoragePaths, id: &str, keychain_backed: bool) -> Result<()> {
+    let mut registry = load_registry(sp)?;
+    let record = registry
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    record.keychain_backed = keychain_backed;
+    save_registry(sp, &registry)
+}
+
+/// Sets `has_password` on a profile's registry record. Called by
+/// `profiles_service::rotate_master_password` once rotation commits, so the
+/// record stays in sync even though rotation's own precondition (the
+/// profile must already have a password) means the value never actually
+/// flips in practice.
+pub fn set_has_password(sp: &StoragePaths, id: &str, has_password: bool) -> Result<()> {
+    let mut registry = load_registry(sp)?;
+    let record = registry
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    record.has_password = has_password;
+    save_registry(sp, &registry)
+}
+
+/// Flips `has_totp` on a profile's registry record. Called by
+/// `profiles_service::enroll_totp`/`disable_totp` once the secret file on
+/// disk is already written or removed, so the record never claims
+/// enrollment that doesn't actually exist.
+pub fn set_has_totp(sp: &StoragePaths, id: &str, has_totp: bool) -> Result<()> {
+    let mut registry = load_registry(sp)?;
+    let record = registry
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    record.has_totp = has_totp;
+    save_registry(sp, &registry)
+}
+
 pub fn verify_profile_password(sp: &StoragePaths, id: &str, password: &str) -> Result<bool> {
     let record = get_profile(sp, id)?.ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
     if !record.has_password {
@@ -163,7 +221,7 @@ pub fn verify_profile_password(sp: &StoragePaths, id: &str, password: &str) -> R
     if !salt_path.exists() {
         return Err(ErrorCodeString::new("KDF_SALT_MISSING"));
     }
-    let salt = fs::read(&salt_path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
-    let key = Zeroizing::new(derive_master_key(password, &salt)?);
+    let params = read_params_file(&salt_path)?;
+    let key = Zeroizing::new(derive_master_key_versioned(password, &params)?);
     key_check::verify_key_check_file(sp, id, &key)
 }
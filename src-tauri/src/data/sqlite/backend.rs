@@ -0,0 +1,145 @@
+//! Where `repo_impl` (and `oplog_service`, which needs the exact same
+//! decision for the oplog table living in the same vault file) get their
+//! SQLite connection for a profile.
+//!
+//! A passwordless profile's vault file is a real on-disk SQLite file (see
+//! `sqlite::init::init_database_passwordless`), so it's served from the
+//! r2d2 pool like any other `DbTarget::File`. A password-protected
+//! profile's vault file is not that at all — it's an AEAD-sealed envelope
+//! (or chunk-store manifest, see `data::storage::vault_chunk_store`) that
+//! only `security_service::open_protected_vault_session` knows how to turn
+//! back into a database, deserialized once at login into the in-memory
+//! connection parked on `AppState::vault_session`. Pooling `vault_db_path`
+//! as a SQLCipher file for such a profile would just hand SQLCipher bytes
+//! it can't parse as a database at all. So for a protected profile, "open a
+//! connection" means borrowing that session connection rather than
+//! pooling a file nothing can actually decrypt that way.
+//!
+//! `open_vault_connection` is the one place that tells these two cases
+//! apart. `repo_impl`'s query functions are written directly against
+//! `rusqlite::Connection` because the schema and SQL are the interesting
+//! part; `ConnectionSource` sits on top of `open_vault_connection` as the
+//! one thing `repo_impl` abstracts over — *which* connection for a given
+//! profile. `DefaultConnectionSource` (the only production implementation)
+//! is just that; an alternate `ConnectionSource` can redirect every query
+//! to a different database (e.g. a scratch file for a one-off import)
+//! without touching a single query in `repo_impl`.
+
+use std::sync::{Arc, MutexGuard};
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::app_state::{AppState, VaultSession};
+use crate::data::profiles::paths::vault_db_path;
+use crate::data::profiles::registry;
+use crate::data::sqlite::pool::{self, DbTarget};
+use crate::error::{ErrorCodeString, Result};
+
+/// A connection handed back by `open_vault_connection`/`ConnectionSource`:
+/// either a pooled SQLCipher-backed file connection (passwordless
+/// profiles) or a lock on the active session's already-decrypted
+/// in-memory connection (protected profiles). Every caller only ever
+/// needs `Deref<Target = rusqlite::Connection>`, so which variant it got
+/// is invisible past this module.
+pub enum VaultConnection<'a> {
+    Pooled(PooledConnection<SqliteConnectionManager>),
+    Session(MutexGuard<'a, Option<VaultSession>>),
+}
+
+impl std::ops::Deref for VaultConnection<'_> {
+    type Target = rusqlite::Connection;
+
+    fn deref(&self) -> &rusqlite::Connection {
+        match self {
+            VaultConnection::Pooled(conn) => conn,
+            VaultConnection::Session(guard) => {
+                &guard
+                    .as_ref()
+                    .expect("checked Some before constructing VaultConnection::Session")
+                    .conn
+            }
+        }
+    }
+}
+
+impl VaultConnection<'_> {
+    /// The session's vault key, if this connection came from one. A caller
+    /// that needs both the connection and the key for the same profile (e.g.
+    /// `oplog_service` sealing/opening an entry) should read it from here
+    /// rather than calling `AppState::vault_key_for` separately — two
+    /// independent `vault_session` locks can straddle a lock/unlock and
+    /// disagree about which profile is live; one lock can't. Always `None`
+    /// for `Pooled`, since that variant only ever serves a passwordless
+    /// profile (see `open_vault_connection`).
+    pub fn vault_key(&self) -> Option<[u8; 32]> {
+        match self {
+            VaultConnection::Pooled(_) => None,
+            VaultConnection::Session(guard) => guard.as_ref().map(|session| *session.key),
+        }
+    }
+}
+
+/// The shared decision `DefaultConnectionSource` and `oplog_service` both
+/// need: a passwordless profile opens its real on-disk file from the
+/// pool, unencrypted, same as always; a protected profile borrows the
+/// connection its unlocked session already decrypted,
+/// since nothing else in the tree can turn that profile's on-disk
+/// envelope back into a database. A protected profile with no live
+/// session for it (locked, or a different profile than the one actually
+/// unlocked) fails `VAULT_LOCKED` rather than silently pooling its
+/// envelope bytes as if they were a SQLite file.
+///
+/// A `Session` connection holds `vault_session`'s lock for as long as the
+/// caller keeps it, so queries against a protected profile serialize with
+/// each other and with `lock_vault`/`vault_key_for` — same as the one
+/// profile's in-memory `rusqlite::Connection` isn't `Sync` and couldn't be
+/// shared across threads any other way. A passwordless profile doesn't pay
+/// this cost; it still gets the pool.
+///
+/// A passwordless profile (or a protected one with no live session for
+/// `profile_id`) falls through to a `registry::get_profile` read on every
+/// call, same as `security_service::require_unlocked_active_profile` already
+/// does per command. That's the tiny `profiles.json` registry, not the vault
+/// itself — nowhere near the per-row vault-serialization cost
+/// `import_service` is careful to avoid — so a hot loop of `repo_impl` calls
+/// for a passwordless profile still re-reads it once per call, but that cost
+/// is negligible next to getting a locked protected profile wrong.
+pub fn open_vault_connection<'a>(state: &'a Arc<AppState>, profile_id: &str) -> Result<VaultConnection<'a>> {
+    let guard = state
+        .vault_session
+        .lock()
+        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+    if matches!(guard.as_ref(), Some(session) if session.profile_id == profile_id) {
+        return Ok(VaultConnection::Session(guard));
+    }
+    drop(guard);
+
+    // No matching session: either this profile is passwordless (which
+    // never populates `vault_session` — see `login_vault`), or it's
+    // protected and simply not unlocked (never was, or `lock_vault` just
+    // cleared the session without touching `active_profile`). The registry
+    // is what actually knows which, so there's no cheaper in-memory
+    // shortcut that's still correct after a lock.
+    let storage_paths = state.get_storage_paths()?;
+    let profile = registry::get_profile(&storage_paths, profile_id)?
+        .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    if profile.has_password {
+        return Err(ErrorCodeString::new("VAULT_LOCKED"));
+    }
+
+    let target = DbTarget::File(vault_db_path(&storage_paths, profile_id)?);
+    Ok(VaultConnection::Pooled(pool::get_conn(profile_id, target, None)?))
+}
+
+pub trait ConnectionSource: Send + Sync {
+    fn open_connection<'a>(&self, state: &'a Arc<AppState>, profile_id: &str) -> Result<VaultConnection<'a>>;
+}
+
+pub struct DefaultConnectionSource;
+
+impl ConnectionSource for DefaultConnectionSource {
+    fn open_connection<'a>(&self, state: &'a Arc<AppState>, profile_id: &str) -> Result<VaultConnection<'a>> {
+        open_vault_connection(state, profile_id)
+    }
+}
@@ -0,0 +1,182 @@
+//! Loads the [cr-sqlite](https://github.com/vlcn-io/cr-sqlite) `crsqlite`
+//! loadable extension into `DbTarget::CrdtFile` connections (see
+//! `data::sqlite::pool`) and upgrades the syncable vault tables to CRRs
+//! (conflict-free replicated row tables) so two devices' independently
+//! edited replicas can merge without a central server or a last-writer-
+//! wins-per-row policy losing concurrent edits to different columns.
+//!
+//! This is deliberately a second, opt-in replica (`vault_crdt.db`, see
+//! `paths::vault_crdt_path`) rather than a CRR upgrade of `vault.db` in
+//! place — nothing else in this crate expects `crsql_as_crr`'s shadow
+//! tables/triggers/columns to be sitting on the tables it already queries.
+//! Keeping the two vault files synchronized (writing through to both, or
+//! periodically re-deriving one from the other) is left to a follow-up;
+//! this module only provides the CRR-upgraded connection and the raw
+//! change-set primitives (`changes_since`/`apply_changes`) cr-sqlite needs
+//! for two sites to converge.
+//!
+//! Shipping the actual platform `crsqlite.{so,dylib,dll}` binary is outside
+//! what belongs in this source tree — `extension_path` resolves where it's
+//! expected to live alongside the installed app, the same way a Tauri
+//! sidecar binary would be, but placing it there is a packaging step, not
+//! a code change.
+//!
+//! Nothing in the crate calls into this module anymore — `services::sync_service`
+//! dropped its `crdt_*` wrappers (and `commands::sync` the Tauri commands
+//! built on them) once `data::sync::vault_log` was picked as the one
+//! cross-device merge log this tree will actually finish wiring. This file
+//! stays only as the reference column-level-merge prototype its functions
+//! already were; it isn't dead code left over by accident, so resist the
+//! urge to delete it on sight — re-wiring `crdt` instead of `vault_log`
+//! is a real option, just not the one that's been decided.
+
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::{ErrorCodeString, Result};
+use crate::types::ChangeRow;
+
+/// Tables this crate currently knows how to upgrade to CRRs. Mirrors
+/// `migrations::validate_core_schema`'s required-table list plus
+/// `attachments`, which is also safe to merge column-wise (unlike
+/// `datacard_password_history`, which is append-only and never updated in
+/// place — see `migrate_v7_to_v8_audit_log` — so last-writer-wins-per-row
+/// insert semantics already suit it fine without a CRR).
+pub const SYNCABLE_TABLES: &[&str] = &["folders", "datacards", "bank_cards", "attachments"];
+
+/// Where the platform's `crsqlite` extension is expected to live. Not
+/// shipped by this crate — see this module's doc comment — so every
+/// `load_and_upgrade` call against an environment without it installed
+/// fails with `CRDT_EXTENSION_MISSING` rather than silently running
+/// without CRR support.
+pub fn extension_path() -> Result<PathBuf> {
+    let dir = std::env::current_exe()
+        .map_err(|_| ErrorCodeString::new("CRDT_EXTENSION_MISSING"))?
+        .parent()
+        .ok_or_else(|| ErrorCodeString::new("CRDT_EXTENSION_MISSING"))?
+        .join("extensions");
+
+    let file_name = if cfg!(target_os = "windows") {
+        "crsqlite.dll"
+    } else if cfg!(target_os = "macos") {
+        "crsqlite.dylib"
+    } else {
+        "crsqlite.so"
+    };
+
+    Ok(dir.join(file_name))
+}
+
+fn has_table(conn: &Connection, name: &str) -> std::result::Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type='table' AND name=?1 LIMIT 1",
+        params![name],
+        |row| row.get::<_, i32>(0),
+    )
+    .optional()
+    .map(|found| found.is_some())
+}
+
+/// Loads `crsqlite` (if not already loaded on this connection) and runs
+/// `crsql_as_crr` over every table in `SYNCABLE_TABLES` that both exists
+/// and isn't already a CRR. Safe to call repeatedly on the same connection
+/// or across connections against the same file — a table that's missing
+/// (schema not migrated onto this file yet) or already upgraded (its
+/// `{table}__crsql_clock` shadow table is present) is left alone.
+pub fn load_and_upgrade(conn: &Connection) -> std::result::Result<(), rusqlite::Error> {
+    let path = extension_path().map_err(|_| rusqlite::Error::InvalidPath(PathBuf::new()))?;
+
+    unsafe {
+        conn.load_extension_enable()?;
+        let result = conn.load_extension(&path, None::<&str>);
+        conn.load_extension_disable()?;
+        result?;
+    }
+
+    for table in SYNCABLE_TABLES {
+        if !has_table(conn, table)? {
+            continue;
+        }
+        if has_table(conn, &format!("{table}__crsql_clock"))? {
+            continue;
+        }
+        conn.execute("SELECT crsql_as_crr(?1)", params![table])?;
+    }
+
+    Ok(())
+}
+
+/// This site's stable cr-sqlite identity, used to tell its own changes
+/// apart from a peer's when merging — see `apply_changes`.
+pub fn site_id(conn: &Connection) -> Result<Vec<u8>> {
+    conn.query_row("SELECT crsql_site_id()", [], |row| row.get(0))
+        .map_err(|_| ErrorCodeString::new("CRDT_QUERY_FAILED"))
+}
+
+/// The monotonic counter cr-sqlite bumps on every local write to a CRR
+/// table, used as `changes_since`'s watermark.
+pub fn db_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT crsql_db_version()", [], |row| row.get(0))
+        .map_err(|_| ErrorCodeString::new("CRDT_QUERY_FAILED"))
+}
+
+/// Every column-level change recorded after `since_db_version`, in the
+/// order cr-sqlite stored them.
+pub fn changes_since(conn: &Connection, since_db_version: i64) -> Result<Vec<ChangeRow>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT \"table\", pk, cid, val, col_version, db_version, site_id, cl, seq
+             FROM crsql_changes
+             WHERE db_version > ?1
+             ORDER BY db_version ASC, seq ASC",
+        )
+        .map_err(|_| ErrorCodeString::new("CRDT_QUERY_FAILED"))?;
+
+    let rows = stmt
+        .query_map(params![since_db_version], |row| {
+            Ok(ChangeRow {
+                table: row.get(0)?,
+                pk: row.get(1)?,
+                cid: row.get(2)?,
+                val: row.get(3)?,
+                col_version: row.get(4)?,
+                db_version: row.get(5)?,
+                site_id: row.get(6)?,
+                cl: row.get(7)?,
+                seq: row.get(8)?,
+            })
+        })
+        .map_err(|_| ErrorCodeString::new("CRDT_QUERY_FAILED"))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|_| ErrorCodeString::new("CRDT_QUERY_FAILED"))?;
+
+    Ok(rows)
+}
+
+/// Feeds a peer's changes back into `crsql_changes`, which cr-sqlite
+/// resolves against this site's own changes to the same row/column using
+/// its causal-length/column-version merge rule rather than either side
+/// simply overwriting the other.
+pub fn apply_changes(conn: &Connection, changes: &[ChangeRow]) -> Result<()> {
+    for change in changes {
+        conn.execute(
+            "INSERT INTO crsql_changes
+                (\"table\", pk, cid, val, col_version, db_version, site_id, cl, seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                change.table,
+                change.pk,
+                change.cid,
+                change.val,
+                change.col_version,
+                change.db_version,
+                change.site_id,
+                change.cl,
+                change.seq,
+            ],
+        )
+        .map_err(|_| ErrorCodeString::new("CRDT_APPLY_FAILED"))?;
+    }
+    Ok(())
+}
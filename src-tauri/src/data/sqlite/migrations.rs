@@ -1,48 +1,319 @@
-use rusqlite::Connection;
-use rusqlite::OptionalExtension;
+//! Ordered, additive migrations for the per-profile vault database.
+//! `migrate_to_latest` never drops or recreates an existing table to get a
+//! profile onto a newer schema — each entry in `MIGRATIONS` runs inside its
+//! own transaction against the `user_version` it expects to find, and a
+//! failure partway through rolls that step back and surfaces
+//! `DB_MIGRATION_FAILED` rather than leaving the file half-migrated or
+//! wiping the data that was already there.
+
+use rusqlite::{Connection, OptionalExtension};
 
 use crate::error::{ErrorCodeString, Result};
 
-const CURRENT_SCHEMA_VERSION: i32 = 6;
+/// Bumped by exactly one for every entry appended to `MIGRATIONS`.
+pub const CURRENT_DB_VERSION: i32 = 9;
+
+type MigrationFn = fn(&Connection) -> Result<()>;
+
+struct Migration {
+    from_version: i32,
+    to_version: i32,
+    run: MigrationFn,
+}
+
+/// Ordered, one-version-at-a-time migrations. `migrate_to_latest` looks up
+/// the entry whose `from_version` matches the DB's current `PRAGMA
+/// user_version` and keeps applying the next one until it reaches
+/// `CURRENT_DB_VERSION`, so a profile several versions behind replays every
+/// step in between rather than jumping straight to the latest schema.
+///
+/// The one exception is `migrate_to_v6_baseline`: every profile created by
+/// this app prior to this migration subsystem existing was stamped
+/// `user_version = 6` directly by the old one-shot "drop everything and
+/// recreate from `schema.sql`" step, with no record of what versions 1-5
+/// actually changed. There's no real history to replay there, so version 0
+/// (an untouched, freshly-created database file) goes straight to the
+/// baseline schema at 6. Every migration from 6 onward is a genuine,
+/// individually-reviewable incremental step.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from_version: 0,
+        to_version: 6,
+        run: migrate_to_v6_baseline,
+    },
+    Migration {
+        from_version: 6,
+        to_version: 7,
+        run: migrate_v6_to_v7_history_and_attachment_indexes,
+    },
+    Migration {
+        from_version: 7,
+        to_version: 8,
+        run: migrate_v7_to_v8_audit_log,
+    },
+    Migration {
+        from_version: 8,
+        to_version: 9,
+        run: migrate_v8_to_v9_datacards_audit_blob_fix,
+    },
+];
+
+fn migrate_to_v6_baseline(conn: &Connection) -> Result<()> {
+    conn.execute_batch(include_str!("schema.sql"))
+        .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))
+}
+
+/// `list_password_history`/`list_attachments_by_datacard` both filter on
+/// `datacard_id`, so every profile has been doing a full table scan of
+/// those two tables on every lookup. Indexing the column they already
+/// query by is the "new indexes" half of this migration subsystem's first
+/// real job.
+fn migrate_v6_to_v7_history_and_attachment_indexes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_datacard_password_history_datacard_id
+            ON datacard_password_history (datacard_id);
+        CREATE INDEX IF NOT EXISTS idx_attachments_datacard_id
+            ON attachments (datacard_id);",
+    )
+    .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))
+}
+
+/// A tamper-evident audit trail of edits/deletes, captured by triggers
+/// rather than by the Rust code paths that perform the writes — so it stays
+/// accurate even for a mutation this crate's own call sites forget to log
+/// through, since SQLite fires the trigger as part of the same statement
+/// regardless of which code path issued it. `old_value_json` is built with
+/// `json_object()` over every column of the row being changed, so
+/// recovering a prior value is just parsing that column back out; no
+/// separate per-table "undo" format to keep in sync with the schema.
+///
+/// `datacard_password_history` rows are append-only (see
+/// `insert_password_history`'s retention pruning and `clear_password_history`)
+/// and never updated in place, so it only gets a DELETE trigger — there's no
+/// UPDATE path on that table to capture. Its `password_value` column is
+/// sometimes an `EncryptedValue` BLOB (see `encrypted_value.rs`) rather than
+/// TEXT, and `json_object()` rejects BLOB arguments outright, so that column
+/// goes through `hex()` first when it isn't plain text.
+fn migrate_v7_to_v8_audit_log(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id TEXT PRIMARY KEY,
+            table_name TEXT NOT NULL,
+            row_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            old_value_json TEXT NOT NULL,
+            changed_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_audit_log_row_id ON audit_log (row_id);
+
+        CREATE TRIGGER IF NOT EXISTS trg_datacards_audit_update
+        AFTER UPDATE ON datacards
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'datacards', OLD.id, 'update',
+                json_object(
+                    'id', OLD.id, 'folder_id', OLD.folder_id, 'title', OLD.title, 'url', OLD.url,
+                    'email', OLD.email, 'username', OLD.username, 'mobile_phone', OLD.mobile_phone,
+                    'note', OLD.note, 'is_favorite', OLD.is_favorite, 'tags_json', OLD.tags_json,
+                    'uri_match', OLD.uri_match, 'password_value', OLD.password_value,
+                    'bank_card_json', OLD.bank_card_json, 'custom_fields_json', OLD.custom_fields_json,
+                    'totp_uri', OLD.totp_uri, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at,
+                    'deleted_at', OLD.deleted_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_datacards_audit_delete
+        AFTER DELETE ON datacards
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'datacards', OLD.id, 'delete',
+                json_object(
+                    'id', OLD.id, 'folder_id', OLD.folder_id, 'title', OLD.title, 'url', OLD.url,
+                    'email', OLD.email, 'username', OLD.username, 'mobile_phone', OLD.mobile_phone,
+                    'note', OLD.note, 'is_favorite', OLD.is_favorite, 'tags_json', OLD.tags_json,
+                    'uri_match', OLD.uri_match, 'password_value', OLD.password_value,
+                    'bank_card_json', OLD.bank_card_json, 'custom_fields_json', OLD.custom_fields_json,
+                    'totp_uri', OLD.totp_uri, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at,
+                    'deleted_at', OLD.deleted_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_attachments_audit_update
+        AFTER UPDATE ON attachments
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'attachments', OLD.id, 'update',
+                json_object(
+                    'id', OLD.id, 'datacard_id', OLD.datacard_id, 'file_name', OLD.file_name,
+                    'mime_type', OLD.mime_type, 'byte_size', OLD.byte_size,
+                    'content_hash', OLD.content_hash, 'source_mtime', OLD.source_mtime,
+                    'created_at', OLD.created_at, 'updated_at', OLD.updated_at, 'deleted_at', OLD.deleted_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_attachments_audit_delete
+        AFTER DELETE ON attachments
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'attachments', OLD.id, 'delete',
+                json_object(
+                    'id', OLD.id, 'datacard_id', OLD.datacard_id, 'file_name', OLD.file_name,
+                    'mime_type', OLD.mime_type, 'byte_size', OLD.byte_size,
+                    'content_hash', OLD.content_hash, 'source_mtime', OLD.source_mtime,
+                    'created_at', OLD.created_at, 'updated_at', OLD.updated_at, 'deleted_at', OLD.deleted_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_datacard_password_history_audit_delete
+        AFTER DELETE ON datacard_password_history
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'datacard_password_history', OLD.id, 'delete',
+                json_object(
+                    'id', OLD.id, 'datacard_id', OLD.datacard_id,
+                    'password_value', CASE WHEN typeof(OLD.password_value) = 'blob'
+                        THEN hex(OLD.password_value) ELSE OLD.password_value END,
+                    'created_at', OLD.created_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;",
+    )
+    .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))
+}
+
+/// `trg_datacards_audit_update`/`trg_datacards_audit_delete` (added in
+/// `migrate_v7_to_v8_audit_log`) embedded `OLD.password_value` straight into
+/// `json_object()` with no BLOB handling, unlike
+/// `trg_datacard_password_history_audit_delete` a few lines down, which
+/// already hex-encodes it. `datacards.password_value` is a real BLOB
+/// whenever a vault key is present, and SQLite's `json_object()` throws at
+/// runtime on a BLOB argument, so any protected profile with an
+/// encrypted-password datacard hit a runtime error out of this trigger on
+/// every update/delete. `CREATE TRIGGER IF NOT EXISTS` wouldn't touch an
+/// already-shipped copy of these triggers, so fixing this needs a real
+/// migration step — drop the two and recreate them with the same
+/// `typeof(...) = 'blob'` hex-encoding the history trigger already uses.
+fn migrate_v8_to_v9_datacards_audit_blob_fix(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS trg_datacards_audit_update;
+        CREATE TRIGGER trg_datacards_audit_update
+        AFTER UPDATE ON datacards
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'datacards', OLD.id, 'update',
+                json_object(
+                    'id', OLD.id, 'folder_id', OLD.folder_id, 'title', OLD.title, 'url', OLD.url,
+                    'email', OLD.email, 'username', OLD.username, 'mobile_phone', OLD.mobile_phone,
+                    'note', OLD.note, 'is_favorite', OLD.is_favorite, 'tags_json', OLD.tags_json,
+                    'uri_match', OLD.uri_match,
+                    'password_value', CASE WHEN typeof(OLD.password_value) = 'blob'
+                        THEN hex(OLD.password_value) ELSE OLD.password_value END,
+                    'bank_card_json', OLD.bank_card_json, 'custom_fields_json', OLD.custom_fields_json,
+                    'totp_uri', OLD.totp_uri, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at,
+                    'deleted_at', OLD.deleted_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;
+
+        DROP TRIGGER IF EXISTS trg_datacards_audit_delete;
+        CREATE TRIGGER trg_datacards_audit_delete
+        AFTER DELETE ON datacards
+        BEGIN
+            INSERT INTO audit_log (id, table_name, row_id, action, old_value_json, changed_at)
+            VALUES (
+                lower(hex(randomblob(16))), 'datacards', OLD.id, 'delete',
+                json_object(
+                    'id', OLD.id, 'folder_id', OLD.folder_id, 'title', OLD.title, 'url', OLD.url,
+                    'email', OLD.email, 'username', OLD.username, 'mobile_phone', OLD.mobile_phone,
+                    'note', OLD.note, 'is_favorite', OLD.is_favorite, 'tags_json', OLD.tags_json,
+                    'uri_match', OLD.uri_match,
+                    'password_value', CASE WHEN typeof(OLD.password_value) = 'blob'
+                        THEN hex(OLD.password_value) ELSE OLD.password_value END,
+                    'bank_card_json', OLD.bank_card_json, 'custom_fields_json', OLD.custom_fields_json,
+                    'totp_uri', OLD.totp_uri, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at,
+                    'deleted_at', OLD.deleted_at
+                ),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );
+        END;",
+    )
+    .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))
+}
 
 pub fn migrate_to_latest(conn: &Connection) -> Result<()> {
     conn.execute_batch("PRAGMA foreign_keys = ON;")
         .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
 
-    let version: i32 = conn
+    let mut version: i32 = conn
         .query_row("PRAGMA user_version;", [], |row| row.get(0))
         .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
 
-    log::info!(
-        "[DB][migrate] user_version={version}, current={CURRENT_SCHEMA_VERSION}"
-    );
-
-    if version < CURRENT_SCHEMA_VERSION {
-        conn.execute_batch(
-            "PRAGMA foreign_keys = OFF;
-DROP TABLE IF EXISTS attachments;
-DROP TABLE IF EXISTS datacard_password_history;
-DROP TABLE IF EXISTS password_history;
-DROP TABLE IF EXISTS datacards;
-DROP TABLE IF EXISTS folders;
-DROP TABLE IF EXISTS user_settings;
-DROP TABLE IF EXISTS bank_cards;",
-        )
-        .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))?;
+    log::info!("[DB][migrate] user_version={version}, current={CURRENT_DB_VERSION}");
 
-        conn.execute_batch(include_str!("schema.sql"))
+    while version < CURRENT_DB_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from_version == version)
+            .ok_or_else(|| {
+                log::error!("[DB][migrate] no registered migration from version {version}");
+                ErrorCodeString::new("DB_MIGRATION_FAILED")
+            })?;
+
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")
+            .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))?;
+
+        // `migrate_to_latest` only gets `&Connection`, not `&mut
+        // Connection`, so `Connection::transaction` (which needs a
+        // mutable borrow) isn't available here — `unchecked_transaction`
+        // is rusqlite's sanctioned way to start one through a shared
+        // reference instead. Still rolls back automatically if dropped
+        // without an explicit `commit()`, same as the checked version.
+        let tx = conn
+            .unchecked_transaction()
             .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))?;
-        conn.execute_batch(&format!("PRAGMA user_version = {CURRENT_SCHEMA_VERSION};"))
+
+        if let Err(err) = (migration.run)(&tx) {
+            log::error!(
+                "[DB][migrate] migration {} -> {} failed: {}",
+                migration.from_version,
+                migration.to_version,
+                err
+            );
+            return Err(ErrorCodeString::new("DB_MIGRATION_FAILED"));
+        }
+
+        tx.execute_batch(&format!(
+            "PRAGMA user_version = {};",
+            migration.to_version
+        ))
+        .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))?;
+
+        tx.commit()
             .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))?;
+
         conn.execute_batch("PRAGMA foreign_keys = ON;")
             .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))?;
-        return Ok(());
-    }
 
-    match version {
-        CURRENT_SCHEMA_VERSION => Ok(()),
-        _ => Err(ErrorCodeString::new("DB_MIGRATION_FAILED")),
+        version = migration.to_version;
     }
+
+    Ok(())
 }
 
 fn has_table(conn: &Connection, name: &str) -> Result<bool> {
@@ -0,0 +1,179 @@
+//! Append-only log of encrypted vault mutations, used to replay changes to
+//! other devices sharing the same profile. Entries are strictly ordered by
+//! `seq` (an autoincrementing rowid); a device that last saw `seq = N` can
+//! ask for everything with `seq > N` and replay it locally instead of
+//! re-syncing the whole vault.
+//!
+//! The log grows without bound unless checkpointed — see
+//! `services::oplog_service` for when/how checkpoints are taken.
+
+use rusqlite::{params, DatabaseName, OptionalExtension};
+
+use crate::error::{ErrorCodeString, Result};
+
+pub struct OpLogEntry {
+    pub seq: i64,
+    pub op_type: String,
+    pub payload: Vec<u8>,
+    pub created_at: String,
+}
+
+pub fn ensure_oplog_table(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS oplog (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            op_type TEXT NOT NULL,
+            payload BLOB NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS oplog_checkpoints (
+            seq INTEGER PRIMARY KEY,
+            created_at TEXT NOT NULL,
+            snapshot BLOB NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS oplog_device_cursors (
+            device_id TEXT PRIMARY KEY,
+            seq INTEGER NOT NULL,
+            updated_at TEXT NOT NULL
+        );",
+    )
+    .map_err(|_| ErrorCodeString::new("DB_MIGRATION_FAILED"))
+}
+
+pub fn append(conn: &rusqlite::Connection, op_type: &str, payload: &[u8], created_at: &str) -> Result<i64> {
+    ensure_oplog_table(conn)?;
+    conn.execute(
+        "INSERT INTO oplog (op_type, payload, created_at) VALUES (?1, ?2, ?3)",
+        params![op_type, payload, created_at],
+    )
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn list_since(conn: &rusqlite::Connection, since_seq: i64) -> Result<Vec<OpLogEntry>> {
+    ensure_oplog_table(conn)?;
+    let mut stmt = conn
+        .prepare("SELECT seq, op_type, payload, created_at FROM oplog WHERE seq > ?1 ORDER BY seq ASC")
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    let rows = stmt
+        .query_map(params![since_seq], |row| {
+            Ok(OpLogEntry {
+                seq: row.get(0)?,
+                op_type: row.get(1)?,
+                payload: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    Ok(rows)
+}
+
+pub fn latest_seq(conn: &rusqlite::Connection) -> Result<i64> {
+    ensure_oplog_table(conn)?;
+    conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM oplog", [], |row| row.get(0))
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
+
+pub fn last_checkpoint_seq(conn: &rusqlite::Connection) -> Result<i64> {
+    ensure_oplog_table(conn)?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(seq), 0) FROM oplog_checkpoints",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
+
+/// Records that a checkpoint (a full snapshot) was taken at `seq`, and
+/// drops every log entry at or before it — devices that missed those
+/// operations must recover from the snapshot instead of replaying them.
+///
+/// The snapshot is `conn`'s own main database image (`serialize`), taken
+/// before any oplog rows are deleted, so it always reflects the vault
+/// exactly as of `seq`: a device that only has this checkpoint can
+/// `deserialize` it wholesale instead of needing the compacted-away
+/// entries to reconstruct that state.
+pub fn checkpoint(conn: &rusqlite::Connection, seq: i64, created_at: &str) -> Result<()> {
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT seq FROM oplog_checkpoints WHERE seq = ?1",
+            params![seq],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    if exists.is_none() {
+        let snapshot = conn
+            .serialize(DatabaseName::Main)
+            .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+        conn.execute(
+            "INSERT INTO oplog_checkpoints (seq, created_at, snapshot) VALUES (?1, ?2, ?3)",
+            params![seq, created_at, &snapshot[..]],
+        )
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    }
+    conn.execute("DELETE FROM oplog WHERE seq <= ?1", params![seq])
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    Ok(())
+}
+
+/// The most recent checkpoint's snapshot, if any — a device recovering
+/// from scratch loads this instead of replaying the (now-compacted) log
+/// from the beginning. Returns the checkpoint's `seq` alongside the raw
+/// database image so the caller knows where to resume `list_since` from.
+pub fn latest_checkpoint_snapshot(conn: &rusqlite::Connection) -> Result<Option<(i64, Vec<u8>)>> {
+    ensure_oplog_table(conn)?;
+    conn.query_row(
+        "SELECT seq, snapshot FROM oplog_checkpoints ORDER BY seq DESC LIMIT 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
+
+/// Last sequence number a given device is known to have fully replayed.
+/// `None` means the device has never synced this vault and should pull
+/// from `seq = 0` (i.e. everything still in the log, or a checkpoint).
+pub fn device_cursor(conn: &rusqlite::Connection, device_id: &str) -> Result<Option<i64>> {
+    ensure_oplog_table(conn)?;
+    conn.query_row(
+        "SELECT seq FROM oplog_device_cursors WHERE device_id = ?1",
+        params![device_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
+
+pub fn set_device_cursor(
+    conn: &rusqlite::Connection,
+    device_id: &str,
+    seq: i64,
+    updated_at: &str,
+) -> Result<()> {
+    ensure_oplog_table(conn)?;
+    conn.execute(
+        "INSERT INTO oplog_device_cursors (device_id, seq, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(device_id) DO UPDATE SET seq = excluded.seq, updated_at = excluded.updated_at",
+        params![device_id, seq, updated_at],
+    )
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    Ok(())
+}
+
+/// The oldest cursor across every device that has ever synced, or `None` if
+/// no device has synced yet. Used to avoid checkpointing past work a slow
+/// device hasn't caught up on.
+pub fn min_device_cursor(conn: &rusqlite::Connection) -> Result<Option<i64>> {
+    ensure_oplog_table(conn)?;
+    conn.query_row("SELECT MIN(seq) FROM oplog_device_cursors", [], |row| {
+        row.get::<_, Option<i64>>(0)
+    })
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 use std::time::Duration;
 
@@ -6,24 +6,142 @@ use once_cell::sync::Lazy;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 
+use crate::data::sqlite::migrations;
+use crate::data::storage_paths::is_network_filesystem;
 use crate::error::{ErrorCodeString, Result};
 
 const DB_POOL_MAX_SIZE_FILE: u32 = 2;
 const DB_POOL_MIN_IDLE_FILE: u32 = 0;
 const DB_POOL_CONNECTION_TIMEOUT_SECS_FILE: u64 = 10;
 const DB_BUSY_TIMEOUT_SECS_FILE: u64 = 15;
+const DB_POOL_IDLE_TIMEOUT_SECS_DEFAULT: u64 = 300;
+
+/// Max connections per profile's pool. Overridable via
+/// `PM_DB_POOL_MAX_SIZE` for anyone running against a busier workload
+/// (e.g. several windows of the same profile) than the default of 2
+/// assumes; an invalid or absent value just falls back to the default.
+fn max_pool_size() -> u32 {
+    std::env::var("PM_DB_POOL_MAX_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DB_POOL_MAX_SIZE_FILE)
+}
+
+/// How long an idle pooled connection sits before r2d2 closes it.
+/// Overridable via `PM_DB_POOL_IDLE_TIMEOUT_SECS`.
+fn idle_timeout() -> Option<Duration> {
+    let secs = std::env::var("PM_DB_POOL_IDLE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DB_POOL_IDLE_TIMEOUT_SECS_DEFAULT);
+    Some(Duration::from_secs(secs))
+}
+
+// A network mount's locking isn't as trustworthy as a local disk's, and
+// round-trips are slower, so a network-backed `DbTarget::File` gets a
+// stricter sync mode and more patience on `SQLITE_BUSY` than the defaults
+// above.
+const DB_BUSY_TIMEOUT_SECS_NETWORK: u64 = 30;
 
 #[derive(Clone, Debug)]
 pub enum DbTarget {
     File(std::path::PathBuf),
     Uri(String),
+    /// A file opened with the `crsqlite` loadable extension and the
+    /// syncable tables upgraded to CRRs — see `data::sqlite::crdt`. Kept
+    /// out of `File`'s own pool key space (see `get_or_create_pool`) so a
+    /// profile's plain vault connections and its CRR-upgraded replica are
+    /// never served out of the same pool.
+    CrdtFile(std::path::PathBuf),
 }
 
 static POOLS: Lazy<Mutex<HashMap<String, r2d2::Pool<SqliteConnectionManager>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+const DB_BUSY_RETRY_BASE_MS: u64 = 10;
+const DB_BUSY_RETRY_MAX_MS: u64 = 500;
+const DB_BUSY_RETRY_MAX_ATTEMPTS: u32 = 8;
+
+/// Gates how many callers can be mid-`pool.get()` at once, independent of
+/// how many connections a pool itself can hand out. `max_pool_size()`
+/// connections per profile is the common case (see its doc comment) —
+/// exactly one profile is ever unlocked per session (`require_unlocked_
+/// active_profile`), so a `max_pool_size()`-sized semaphore already covers
+/// that profile's file pool without every `spawn_blocking` task racing
+/// `pool.get()` against each other the instant a burst of Tauri commands
+/// lands. Sized the same way the pool itself is (see `PM_DB_POOL_MAX_SIZE`),
+/// since the two numbers exist to describe the same capacity.
+static CONN_SEMAPHORE: Lazy<tokio::sync::Semaphore> =
+    Lazy::new(|| tokio::sync::Semaphore::new(max_pool_size() as usize));
+
+/// Pool keys (same keying scheme as `POOLS`) that have already had
+/// `migrations::migrate_to_latest` run against them this process. A brand
+/// new pool is only ever built once per key (`POOLS` itself already
+/// guarantees that), so in practice this set tracks the same thing — it
+/// exists as its own guard anyway so "has this file's schema been brought
+/// up to date" stays a question `get_or_create_pool` can answer on its own,
+/// without relying on `POOLS.get(&key)` staying `None` remaining the only
+/// thing standing between a caller and an unmigrated connection.
+static MIGRATED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Page size and KDF iteration count SQLCipher is told to use via
+/// `apply_encryption_pragmas`. These must be identical on every connection
+/// ever opened against a given encrypted file — changing either here would
+/// make existing vault files unreadable — so they're fixed constants rather
+/// than something a caller can tune per profile.
+const SQLCIPHER_PAGE_SIZE: u32 = 4096;
+const SQLCIPHER_KDF_ITER: u32 = 256_000;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Issues SQLCipher's `PRAGMA key` (as the `x'<hex>'` binary-key literal
+/// SQLCipher expects, since a raw-string key would additionally be run
+/// through its own KDF) plus the page-size/KDF-iteration pragmas that must
+/// match across every connection opened against the same file, then forces
+/// a real read. `PRAGMA key` alone never fails even against the wrong key —
+/// SQLCipher only validates it lazily, on first access to the database — so
+/// without this probe a bad key would surface confusingly on whatever query
+/// happened to run first instead of here, at connection setup.
+fn apply_encryption_pragmas(
+    conn: &rusqlite::Connection,
+    key: &[u8; 32],
+) -> std::result::Result<(), rusqlite::Error> {
+    conn.execute_batch(&format!(
+        "PRAGMA key = \"x'{}'\"; PRAGMA cipher_page_size = {SQLCIPHER_PAGE_SIZE}; PRAGMA kdf_iter = {SQLCIPHER_KDF_ITER};",
+        to_hex(key)
+    ))?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))?;
+    Ok(())
+}
+
+/// Re-encrypts an already-open connection under `new_key` via SQLCipher's
+/// `PRAGMA rekey`, for the master-password-change flow. Only rewrites this
+/// one connection's file in place — callers must follow up with
+/// `clear_pool` so the next `get_conn` for this profile opens fresh
+/// connections under `new_key` via `FilePragmas`/`NetworkFilePragmas`
+/// instead of the old key baked into the pool they just rekeyed out from
+/// under.
+pub fn rekey_connection(conn: &rusqlite::Connection, new_key: &[u8; 32]) -> Result<()> {
+    conn.execute_batch(&format!("PRAGMA rekey = \"x'{}'\"", to_hex(new_key)))
+        .map_err(|_| ErrorCodeString::new("DB_REKEY_FAILED"))
+}
+
 #[derive(Debug)]
-struct FilePragmas;
+struct FilePragmas {
+    /// Derived from the master password, never persisted in `POOLS` itself
+    /// — it only ever lives inside this customizer for as long as the pool
+    /// it was built with stays cached. In practice this is always `None`
+    /// for a `DbTarget::File` reached through `sqlite::backend`: a
+    /// protected profile's on-disk vault file isn't a SQLCipher file at
+    /// all (see `sqlite::backend`'s module doc), so `open_vault_connection`
+    /// never routes one through the file pool — only a passwordless
+    /// profile's real, unencrypted on-disk file ever gets here. `Some` stays
+    /// supported for any future caller that opens a `DbTarget::File` with a
+    /// real SQLCipher key directly, bypassing `open_vault_connection`.
+    encryption_key: Option<[u8; 32]>,
+}
 
 impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for FilePragmas {
     fn on_acquire(
@@ -31,6 +149,9 @@ impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for FilePr
         conn: &mut rusqlite::Connection,
     ) -> std::result::Result<(), rusqlite::Error> {
         conn.busy_timeout(Duration::from_secs(DB_BUSY_TIMEOUT_SECS_FILE))?;
+        if let Some(key) = &self.encryption_key {
+            apply_encryption_pragmas(conn, key)?;
+        }
         conn.execute_batch(
             r#"
             PRAGMA foreign_keys = ON;
@@ -40,6 +161,60 @@ impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for FilePr
     }
 }
 
+/// Same as `FilePragmas`, but for a vault file that lives on a detected
+/// network mount (see `storage_paths::is_network_filesystem`). `NORMAL`
+/// synchronous mode relies on the OS/disk to order writes correctly around
+/// a crash, which network filesystems don't reliably guarantee the way a
+/// local disk does — so this falls back to `FULL`, and gives `SQLITE_BUSY`
+/// more time to clear given the extra round-trip latency.
+#[derive(Debug)]
+struct NetworkFilePragmas {
+    encryption_key: Option<[u8; 32]>,
+}
+
+impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for NetworkFilePragmas {
+    fn on_acquire(
+        &self,
+        conn: &mut rusqlite::Connection,
+    ) -> std::result::Result<(), rusqlite::Error> {
+        conn.busy_timeout(Duration::from_secs(DB_BUSY_TIMEOUT_SECS_NETWORK))?;
+        if let Some(key) = &self.encryption_key {
+            apply_encryption_pragmas(conn, key)?;
+        }
+        conn.execute_batch(
+            r#"
+            PRAGMA foreign_keys = ON;
+            PRAGMA synchronous = FULL;
+            "#,
+        )
+    }
+}
+
+/// Installed on every connection handed out for a `DbTarget::CrdtFile`:
+/// loads the `crsqlite` extension (see `crdt::extension_path`) and, the
+/// first time this particular file sees it, upgrades `crdt::SYNCABLE_TABLES`
+/// to CRRs via `crsql_as_crr`. `busy_timeout`/`foreign_keys` match
+/// `FilePragmas` — cr-sqlite's extra shadow tables and triggers don't
+/// change what durability or FK behavior this connection otherwise wants.
+#[derive(Debug)]
+struct CrdtPragmas {
+    encryption_key: Option<[u8; 32]>,
+}
+
+impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for CrdtPragmas {
+    fn on_acquire(
+        &self,
+        conn: &mut rusqlite::Connection,
+    ) -> std::result::Result<(), rusqlite::Error> {
+        conn.busy_timeout(Duration::from_secs(DB_BUSY_TIMEOUT_SECS_FILE))?;
+        if let Some(key) = &self.encryption_key {
+            apply_encryption_pragmas(conn, key)?;
+        }
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        crate::data::sqlite::crdt::load_and_upgrade(conn)
+    }
+}
+
 #[derive(Debug)]
 struct MemoryPragmas;
 
@@ -59,18 +234,50 @@ impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for Memory
     }
 }
 
+/// Runs `migrations::migrate_to_latest` against the first connection out of
+/// a freshly-built file-backed pool, guarded by `MIGRATED` so a pool that
+/// outlives several `get_conn` calls doesn't re-run it on every single one.
+/// Only reachable from `DbTarget::File` — `DbTarget::Uri` targets are
+/// in-memory scratch connections (see `backend.rs`'s doc comment), not a
+/// profile's versioned vault schema, so they have nothing to migrate.
+fn run_pending_migrations(pool: &r2d2::Pool<SqliteConnectionManager>, key: &str) -> Result<()> {
+    let mut migrated = MIGRATED
+        .lock()
+        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+    if migrated.contains(key) {
+        return Ok(());
+    }
+
+    let conn = pool.get().map_err(|e| classify_pool_error(&e))?;
+    migrations::migrate_to_latest(&conn)?;
+
+    migrated.insert(key.to_string());
+    Ok(())
+}
+
+/// Same keying scheme `POOLS` (and `MIGRATED`) index by, pulled out so
+/// `get_conn` can log/retry against a pool's key without re-deriving it or
+/// taking `get_or_create_pool`'s lock first.
+fn pool_key(profile_id: &str, target: &DbTarget) -> String {
+    match target {
+        DbTarget::File(_) => format!("{profile_id}::file"),
+        DbTarget::Uri(uri) => format!("{profile_id}::uri::{uri}"),
+        // A distinct key space from `File`'s, even for the same profile —
+        // see `DbTarget::CrdtFile`'s doc comment.
+        DbTarget::CrdtFile(_) => format!("{profile_id}::crdt"),
+    }
+}
+
 fn get_or_create_pool(
     profile_id: &str,
     target: DbTarget,
+    encryption_key: Option<&[u8; 32]>,
 ) -> Result<r2d2::Pool<SqliteConnectionManager>> {
     let mut pools = POOLS
         .lock()
         .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
 
-    let key = match &target {
-        DbTarget::File(_) => format!("{profile_id}::file"),
-        DbTarget::Uri(uri) => format!("{profile_id}::uri::{uri}"),
-    };
+    let key = pool_key(profile_id, &target);
 
     log::info!("[DB][pool] profile_id={profile_id} target={target:?} key={key}");
 
@@ -80,17 +287,51 @@ fn get_or_create_pool(
 
     let pool = match target {
         DbTarget::File(path) => {
+            let on_network = is_network_filesystem(&path);
+            if on_network {
+                log::warn!("[DB][pool] {path:?} looks network-backed; using stricter durability pragmas");
+            }
             let manager = SqliteConnectionManager::file(path);
-            r2d2::Pool::builder()
-                .max_size(DB_POOL_MAX_SIZE_FILE)
+            let builder = r2d2::Pool::builder()
+                .max_size(max_pool_size())
+                .min_idle(Some(DB_POOL_MIN_IDLE_FILE))
+                .idle_timeout(idle_timeout())
+                .connection_timeout(Duration::from_secs(DB_POOL_CONNECTION_TIMEOUT_SECS_FILE));
+            let encryption_key = encryption_key.copied();
+            let builder = if on_network {
+                builder.connection_customizer(Box::new(NetworkFilePragmas { encryption_key }))
+            } else {
+                builder.connection_customizer(Box::new(FilePragmas { encryption_key }))
+            };
+            let pool = builder.build(manager).map_err(|e| {
+                log::error!("[DB][pool] build failed: {e:?}");
+                ErrorCodeString::new("DB_OPEN_FAILED")
+            })?;
+            run_pending_migrations(&pool, &key)?;
+            pool
+        }
+        DbTarget::CrdtFile(path) => {
+            let manager = SqliteConnectionManager::file(path);
+            let encryption_key = encryption_key.copied();
+            let pool = r2d2::Pool::builder()
+                .max_size(max_pool_size())
                 .min_idle(Some(DB_POOL_MIN_IDLE_FILE))
+                .idle_timeout(idle_timeout())
                 .connection_timeout(Duration::from_secs(DB_POOL_CONNECTION_TIMEOUT_SECS_FILE))
-                .connection_customizer(Box::new(FilePragmas))
+                .connection_customizer(Box::new(CrdtPragmas { encryption_key }))
                 .build(manager)
                 .map_err(|e| {
                     log::error!("[DB][pool] build failed: {e:?}");
                     ErrorCodeString::new("DB_OPEN_FAILED")
-                })?
+                })?;
+            // The very first connection is acquired before `run_pending_migrations`
+            // has created any tables, so `CrdtPragmas::on_acquire`'s upgrade
+            // attempt is a no-op for a brand new file — run the migrations,
+            // then upgrade explicitly now that the syncable tables exist.
+            run_pending_migrations(&pool, &key)?;
+            let conn = pool.get().map_err(|e| classify_pool_error(&e))?;
+            crate::data::sqlite::crdt::load_and_upgrade(&conn).map_err(|_| ErrorCodeString::new("CRDT_UPGRADE_FAILED"))?;
+            pool
         }
         DbTarget::Uri(uri) => {
             let flags = rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
@@ -99,8 +340,9 @@ fn get_or_create_pool(
                 | rusqlite::OpenFlags::SQLITE_OPEN_SHARED_CACHE;
             let manager = SqliteConnectionManager::file(uri).with_flags(flags);
             r2d2::Pool::builder()
-                .max_size(DB_POOL_MAX_SIZE_FILE)
+                .max_size(max_pool_size())
                 .min_idle(Some(DB_POOL_MIN_IDLE_FILE))
+                .idle_timeout(idle_timeout())
                 .connection_timeout(Duration::from_secs(DB_POOL_CONNECTION_TIMEOUT_SECS_FILE))
                 .connection_customizer(Box::new(MemoryPragmas))
                 .build(manager)
@@ -115,13 +357,90 @@ fn get_or_create_pool(
     Ok(pool)
 }
 
+/// Walks an r2d2 error's source chain looking for the `SQLITE_NOTADB`
+/// failure SQLCipher raises when `apply_encryption_pragmas`'s probe read
+/// runs against the wrong key, so a caller can tell "wrong password" apart
+/// from an ordinary open failure (missing file, permissions, a locked
+/// file, ...) and prompt for re-entry instead of a generic error.
+fn classify_pool_error(err: &r2d2::Error) -> ErrorCodeString {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if let Some(rusqlite::Error::SqliteFailure(sqlite_err, _)) = err.downcast_ref::<rusqlite::Error>() {
+            if sqlite_err.code == rusqlite::ErrorCode::NotADatabase {
+                return ErrorCodeString::new("DB_DECRYPT_FAILED");
+            }
+        }
+        source = err.source();
+    }
+    ErrorCodeString::new("DB_OPEN_FAILED")
+}
+
+/// `true` if `err` bottoms out in `SQLITE_BUSY`/`SQLITE_LOCKED` — worth
+/// retrying, since both mean another connection is mid-write, not that
+/// anything is actually wrong.
+fn is_busy_or_locked(err: &r2d2::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if let Some(rusqlite::Error::SqliteFailure(sqlite_err, _)) = err.downcast_ref::<rusqlite::Error>() {
+            if matches!(
+                sqlite_err.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ) {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// `encryption_key` is the 32-byte key SQLCipher derives the vault's
+/// master password down to — `None` opens the target unencrypted, which is
+/// correct for a passwordless profile and is what a protected profile's
+/// `DbTarget::File` callers get too until `AppState::vault_key_for` has an
+/// unlocked session to report one from (e.g. mid-login). A wrong key
+/// surfaces as `DB_DECRYPT_FAILED` (see `classify_pool_error`) rather than
+/// `DB_OPEN_FAILED`.
+///
+/// Acquiring a connection is gated by `CONN_SEMAPHORE` (so a burst of
+/// `spawn_blocking` tasks queues instead of thundering-herding `pool.get()`)
+/// and, once through the gate, retried with capped exponential backoff on
+/// `SQLITE_BUSY`/`SQLITE_LOCKED` — 10ms, 20ms, 40ms, ... up to 500ms, for up
+/// to `DB_BUSY_RETRY_MAX_ATTEMPTS` attempts — before giving up with
+/// `DB_BUSY`. Any other `pool.get()` failure is not retried.
 pub fn get_conn(
     profile_id: &str,
     target: DbTarget,
+    encryption_key: Option<&[u8; 32]>,
 ) -> Result<PooledConnection<SqliteConnectionManager>> {
-    let pool = get_or_create_pool(profile_id, target)?;
-    pool.get()
-        .map_err(|_| ErrorCodeString::new("DB_OPEN_FAILED"))
+    let key = pool_key(profile_id, &target);
+    let pool = get_or_create_pool(profile_id, target, encryption_key)?;
+
+    let _permit = tauri::async_runtime::block_on(CONN_SEMAPHORE.acquire())
+        .map_err(|_| ErrorCodeString::new("DB_BUSY"))?;
+
+    let mut wait = Duration::from_millis(DB_BUSY_RETRY_BASE_MS);
+    for attempt in 1..=DB_BUSY_RETRY_MAX_ATTEMPTS {
+        match pool.get() {
+            Ok(conn) => return Ok(conn),
+            Err(e) if is_busy_or_locked(&e) && attempt < DB_BUSY_RETRY_MAX_ATTEMPTS => {
+                log::warn!(
+                    "[DB][pool] key={key} busy/locked, attempt={attempt} retrying_after={wait:?}"
+                );
+                std::thread::sleep(wait);
+                wait = (wait * 2).min(Duration::from_millis(DB_BUSY_RETRY_MAX_MS));
+            }
+            Err(e) if is_busy_or_locked(&e) => {
+                log::error!(
+                    "[DB][pool] key={key} giving up after {attempt} attempts, last_wait={wait:?}"
+                );
+                return Err(ErrorCodeString::new("DB_BUSY"));
+            }
+            Err(e) => return Err(classify_pool_error(&e)),
+        }
+    }
+
+    unreachable!("loop above always returns by its last iteration")
 }
 
 pub fn clear_pool(profile_id: &str) {
@@ -1,42 +1,27 @@
 use chrono::Utc;
-use r2d2::PooledConnection;
-use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::params;
 use rusqlite::types::Type;
 use rusqlite::OptionalExtension;
 use uuid::Uuid;
 
-use super::pool::{self, DbTarget};
 use crate::app_state::AppState;
-use crate::data::profiles::paths::vault_db_path;
+use crate::data::crypto::encrypted_value::EncryptedValue;
+use crate::data::sqlite::backend::{ConnectionSource, VaultConnection};
+use crate::data::sqlite::row_extract;
 use crate::error::{ErrorCodeString, Result};
 use crate::types::{
-    AttachmentMeta, BankCard, CreateDataCardInput, DataCard, DataCardSummary, Folder,
-    PasswordHistoryRow, SetDataCardFavoriteInput, UpdateDataCardInput,
+    AttachmentMeta, AuditLogEntry, BankCard, CreateDataCardInput, DataCard, DataCardSummary,
+    Folder, PasswordHistoryRow, SetDataCardFavoriteInput, SortDirection, SortField,
+    UpdateDataCardInput, UriMatchMode,
 };
 
 use std::sync::Arc;
 
-fn db_target(state: &Arc<AppState>, profile_id: &str) -> DbTarget {
-    if let Ok(uri_guard) = state.vault_db_uri.lock() {
-        if let Some(uri) = uri_guard.clone() {
-            if let Ok(active) = state.logged_in_profile.lock() {
-                if active.as_deref() == Some(profile_id) {
-                    return DbTarget::Uri(uri);
-                }
-            }
-        }
-    }
-
-    DbTarget::File(vault_db_path(&state.storage_paths, profile_id))
-}
-
-fn open_connection(
-    state: &Arc<AppState>,
-    profile_id: &str,
-) -> Result<PooledConnection<SqliteConnectionManager>> {
-    let target = db_target(state, profile_id);
-    pool::get_conn(profile_id, target)
+/// Every query function below goes through here rather than touching the
+/// pool directly, so which physical database backs a profile is entirely
+/// `state.connection_source`'s call (see `data::sqlite::backend`).
+fn open_connection<'a>(state: &'a Arc<AppState>, profile_id: &str) -> Result<VaultConnection<'a>> {
+    state.connection_source.open_connection(state, profile_id)
 }
 
 fn deserialize_json<T: serde::de::DeserializeOwned>(value: String) -> rusqlite::Result<T> {
@@ -60,6 +45,33 @@ fn map_folder(row: &rusqlite::Row) -> rusqlite::Result<Folder> {
     })
 }
 
+/// `uri_match` is stored as the enum's snake_case tag (`"domain"`,
+/// `"starts_with"`, …) rather than as a JSON-quoted string, since it's a
+/// single scalar value rather than a structure like `tags_json`. An
+/// unrecognized value (e.g. a column predating this mode) falls back to
+/// `Domain`, the behavior every existing card already had.
+fn uri_match_to_db(mode: UriMatchMode) -> &'static str {
+    match mode {
+        UriMatchMode::Domain => "domain",
+        UriMatchMode::Host => "host",
+        UriMatchMode::StartsWith => "starts_with",
+        UriMatchMode::Exact => "exact",
+        UriMatchMode::RegularExpression => "regular_expression",
+        UriMatchMode::Never => "never",
+    }
+}
+
+fn uri_match_from_db(value: &str) -> UriMatchMode {
+    match value {
+        "host" => UriMatchMode::Host,
+        "starts_with" => UriMatchMode::StartsWith,
+        "exact" => UriMatchMode::Exact,
+        "regular_expression" => UriMatchMode::RegularExpression,
+        "never" => UriMatchMode::Never,
+        _ => UriMatchMode::Domain,
+    }
+}
+
 fn map_datacard(row: &rusqlite::Row) -> rusqlite::Result<DataCard> {
     Ok(DataCard {
         id: row.get("id")?,
@@ -72,6 +84,7 @@ fn map_datacard(row: &rusqlite::Row) -> rusqlite::Result<DataCard> {
         note: row.get("note")?,
         is_favorite: row.get::<_, i64>("is_favorite")? != 0,
         tags: deserialize_json(row.get::<_, String>("tags_json")?)?,
+        uri_match: uri_match_from_db(&row.get::<_, String>("uri_match")?),
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         deleted_at: row.get("deleted_at")?,
@@ -81,6 +94,7 @@ fn map_datacard(row: &rusqlite::Row) -> rusqlite::Result<DataCard> {
             None => None,
         },
         custom_fields: deserialize_json(row.get::<_, String>("custom_fields_json")?)?,
+        totp_uri: row.get("totp_uri")?,
     })
 }
 
@@ -96,6 +110,7 @@ fn map_datacard_summary(row: &rusqlite::Row) -> rusqlite::Result<DataCardSummary
         email: row.get("email")?,
         username: row.get("username")?,
         tags,
+        uri_match: uri_match_from_db(&row.get::<_, String>("uri_match")?),
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         deleted_at: row.get("deleted_at")?,
@@ -110,30 +125,81 @@ fn map_attachment(row: &rusqlite::Row) -> rusqlite::Result<AttachmentMeta> {
         file_name: row.get("file_name")?,
         mime_type: row.get("mime_type")?,
         byte_size: row.get("byte_size")?,
+        content_hash: row.get("content_hash")?,
+        source_mtime: row.get("source_mtime")?,
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         deleted_at: row.get("deleted_at")?,
     })
 }
 
-fn map_password_history_row(row: &rusqlite::Row) -> rusqlite::Result<PasswordHistoryRow> {
-    Ok(PasswordHistoryRow {
-        id: row.get("id")?,
-        datacard_id: row.get("datacard_id")?,
-        password_value: row.get("password_value")?,
-        created_at: row.get("created_at")?,
+/// `password_value` is stored as an encrypted BLOB once a vault key is
+/// available (see `insert_password_history`), but older rows written back
+/// when the profile was passwordless are still plain TEXT — so this reads
+/// whichever representation is actually on the row rather than assuming
+/// one, and leaves decrypting the encrypted case to `RawPasswordHistoryRow::reveal`,
+/// which has access to this crate's own `Result`/`ErrorCodeString` (a
+/// `rusqlite::Row` mapper does not).
+enum StoredPasswordValue {
+    Plaintext(String),
+    Encrypted(EncryptedValue),
+}
+
+struct RawPasswordHistoryRow {
+    id: String,
+    datacard_id: String,
+    created_at: String,
+    password_value: StoredPasswordValue,
+}
+
+impl RawPasswordHistoryRow {
+    fn reveal(self, vault_key: Option<[u8; 32]>) -> Result<PasswordHistoryRow> {
+        let password_value = match self.password_value {
+            StoredPasswordValue::Plaintext(value) => value,
+            StoredPasswordValue::Encrypted(encrypted) => {
+                let key = vault_key.ok_or_else(|| ErrorCodeString::new("DB_DECRYPT_FAILED"))?;
+                let plaintext = encrypted.decrypt(&key, self.datacard_id.as_bytes())?;
+                String::from_utf8(plaintext).map_err(|_| ErrorCodeString::new("DB_DECRYPT_FAILED"))?
+            }
+        };
+
+        Ok(PasswordHistoryRow {
+            id: self.id,
+            datacard_id: self.datacard_id,
+            password_value,
+            created_at: self.created_at,
+        })
+    }
+}
+
+fn map_password_history_row(row: &rusqlite::Row) -> rusqlite::Result<RawPasswordHistoryRow> {
+    let id = row.get("id")?;
+    let datacard_id: String = row.get("datacard_id")?;
+    let created_at = row.get("created_at")?;
+
+    let password_value = match row.get_ref("password_value")? {
+        rusqlite::types::ValueRef::Blob(_) => {
+            StoredPasswordValue::Encrypted(row.get("password_value")?)
+        }
+        _ => StoredPasswordValue::Plaintext(row.get("password_value")?),
+    };
+
+    Ok(RawPasswordHistoryRow {
+        id,
+        datacard_id,
+        created_at,
+        password_value,
     })
 }
 
-fn order_clause(sort_field: &str, sort_dir: &str) -> Option<&'static str> {
+fn order_clause(sort_field: SortField, sort_dir: SortDirection) -> &'static str {
     match (sort_field, sort_dir) {
-        ("updated_at", "DESC") => Some("ORDER BY updated_at DESC, title ASC"),
-        ("updated_at", "ASC") => Some("ORDER BY updated_at ASC, title ASC"),
-        ("created_at", "DESC") => Some("ORDER BY created_at DESC, title ASC"),
-        ("created_at", "ASC") => Some("ORDER BY created_at ASC, title ASC"),
-        ("title", "ASC") => Some("ORDER BY title ASC, updated_at DESC"),
-        ("title", "DESC") => Some("ORDER BY title DESC, updated_at DESC"),
-        _ => None,
+        (SortField::UpdatedAt, SortDirection::Desc) => "ORDER BY updated_at DESC, title ASC",
+        (SortField::UpdatedAt, SortDirection::Asc) => "ORDER BY updated_at ASC, title ASC",
+        (SortField::CreatedAt, SortDirection::Desc) => "ORDER BY created_at DESC, title ASC",
+        (SortField::CreatedAt, SortDirection::Asc) => "ORDER BY created_at ASC, title ASC",
+        (SortField::Title, SortDirection::Asc) => "ORDER BY title ASC, updated_at DESC",
+        (SortField::Title, SortDirection::Desc) => "ORDER BY title DESC, updated_at DESC",
     }
 }
 
@@ -238,6 +304,50 @@ pub fn purge_folder(state: &Arc<AppState>, profile_id: &str, id: &str) -> Result
     Ok(true)
 }
 
+pub fn soft_delete_folder(state: &Arc<AppState>, profile_id: &str, id: &str) -> Result<bool> {
+    let conn = open_connection(state, profile_id)?;
+    let now = Utc::now().to_rfc3339();
+    let rows = conn
+        .execute(
+            "UPDATE folders SET deleted_at = ?1, updated_at = ?2 WHERE id = ?3",
+            params![now.clone(), now, id],
+        )
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    if rows == 0 {
+        return Err(ErrorCodeString::new("FOLDER_NOT_FOUND"));
+    }
+    Ok(true)
+}
+
+pub fn restore_folder(state: &Arc<AppState>, profile_id: &str, id: &str) -> Result<bool> {
+    let conn = open_connection(state, profile_id)?;
+    let rows = conn
+        .execute(
+            "UPDATE folders SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    if rows == 0 {
+        return Err(ErrorCodeString::new("FOLDER_NOT_FOUND"));
+    }
+    Ok(true)
+}
+
+pub fn list_deleted_folders(state: &Arc<AppState>, profile_id: &str) -> Result<Vec<Folder>> {
+    let conn = open_connection(state, profile_id)?;
+    let mut stmt = conn
+        .prepare("SELECT * FROM folders WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC")
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    let folders = stmt
+        .query_map([], map_folder)
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    Ok(folders)
+}
+
 pub fn move_datacards_to_root(
     state: &Arc<AppState>,
     profile_id: &str,
@@ -265,17 +375,11 @@ pub fn list_datacard_ids_in_folder(
     } else {
         " AND deleted_at IS NULL".to_string()
     };
-    let mut stmt = conn
-        .prepare(&format!(
-            "SELECT id FROM datacards WHERE folder_id = ?1{clause}",
-        ))
-        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
-
-    let rows = stmt
-        .query_map(params![folder_id], |row| row.get("id"))
-        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
-        .collect::<rusqlite::Result<Vec<String>>>()
-        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    let sql = format!("SELECT id FROM datacards WHERE folder_id = ?1{clause}");
+    let rows = row_extract::query_all::<(String,), _>(&conn, &sql, params![folder_id])?
+        .into_iter()
+        .map(|(id,)| id)
+        .collect();
 
     Ok(rows)
 }
@@ -284,12 +388,11 @@ pub fn list_datacards(
     state: &Arc<AppState>,
     profile_id: &str,
     include_deleted: bool,
-    sort_field: &str,
-    sort_dir: &str,
+    sort_field: SortField,
+    sort_dir: SortDirection,
 ) -> Result<Vec<DataCard>> {
     let conn = open_connection(state, profile_id)?;
-    let clause = order_clause(sort_field, sort_dir)
-        .ok_or_else(|| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    let clause = order_clause(sort_field, sort_dir);
     let base_query = if include_deleted {
         format!("SELECT * FROM datacards {clause}")
     } else {
@@ -309,14 +412,13 @@ pub fn list_datacards(
 pub fn list_datacards_summary(
     state: &Arc<AppState>,
     profile_id: &str,
-    sort_field: &str,
-    sort_dir: &str,
+    sort_field: SortField,
+    sort_dir: SortDirection,
 ) -> Result<Vec<DataCardSummary>> {
     let conn = open_connection(state, profile_id)?;
-    let clause = order_clause(sort_field, sort_dir)
-        .ok_or_else(|| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    let clause = order_clause(sort_field, sort_dir);
     let query = format!(
-        "SELECT id, folder_id, title, url, email, username, tags_json, is_favorite, created_at, updated_at, deleted_at FROM datacards WHERE deleted_at IS NULL {clause}"
+        "SELECT id, folder_id, title, url, email, username, tags_json, uri_match, is_favorite, created_at, updated_at, deleted_at FROM datacards WHERE deleted_at IS NULL {clause}"
     );
     let mut stmt = conn
         .prepare(&query)
@@ -353,7 +455,7 @@ pub fn list_deleted_datacards_summary(
     let conn = open_connection(state, profile_id)?;
     let mut stmt = conn
         .prepare(
-            "SELECT id, folder_id, title, url, email, username, tags_json, is_favorite, created_at, updated_at, deleted_at FROM datacards WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+            "SELECT id, folder_id, title, url, email, username, tags_json, uri_match, is_favorite, created_at, updated_at, deleted_at FROM datacards WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
         )
         .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
 
@@ -396,7 +498,7 @@ pub fn create_datacard(
     let now = Utc::now().to_rfc3339();
     let id = Uuid::new_v4().to_string();
     conn.execute(
-        "INSERT INTO datacards (id, folder_id, title, url, email, username, mobile_phone, note, is_favorite, tags_json, password_value, bank_card_json, custom_fields_json, created_at, updated_at, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 0, ?9, ?10, ?11, ?12, ?13, ?14, NULL)",
+        "INSERT INTO datacards (id, folder_id, title, url, email, username, mobile_phone, note, is_favorite, tags_json, uri_match, password_value, bank_card_json, custom_fields_json, totp_uri, created_at, updated_at, deleted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 0, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, NULL)",
         params![
             id,
             input.folder_id,
@@ -407,9 +509,11 @@ pub fn create_datacard(
             input.mobile_phone,
             input.note,
             tags_json,
+            uri_match_to_db(input.uri_match),
             input.password,
             bank_card_json,
             custom_fields_json,
+            input.totp_uri,
             now,
             now
         ],
@@ -423,6 +527,8 @@ pub fn update_datacard(
     state: &Arc<AppState>,
     profile_id: &str,
     input: &UpdateDataCardInput,
+    vault_key: Option<[u8; 32]>,
+    password_history_retention_count: i64,
 ) -> Result<bool> {
     let conn = open_connection(state, profile_id)?;
     let tags_json = serialize_json(&input.tags)?;
@@ -459,11 +565,13 @@ pub fn update_datacard(
             &input.id,
             existing_password.as_deref().unwrap_or(""),
             &now,
+            vault_key,
+            password_history_retention_count,
         )?;
     }
     let rows = conn
         .execute(
-            "UPDATE datacards SET title = ?1, url = ?2, email = ?3, username = ?4, mobile_phone = ?5, note = ?6, tags_json = ?7, password_value = ?8, bank_card_json = ?9, custom_fields_json = ?10, folder_id = ?11, updated_at = ?12 WHERE id = ?13",
+            "UPDATE datacards SET title = ?1, url = ?2, email = ?3, username = ?4, mobile_phone = ?5, note = ?6, tags_json = ?7, uri_match = ?8, password_value = ?9, bank_card_json = ?10, custom_fields_json = ?11, totp_uri = ?12, folder_id = ?13, updated_at = ?14 WHERE id = ?15",
             params![
                 input.title,
                 input.url,
@@ -472,9 +580,11 @@ pub fn update_datacard(
                 input.mobile_phone,
                 input.note,
                 tags_json,
+                uri_match_to_db(input.uri_match),
                 input.password,
                 bank_card_json,
                 custom_fields_json,
+                input.totp_uri,
                 input.folder_id,
                 now,
                 input.id
@@ -620,14 +730,16 @@ pub fn insert_attachment(
 ) -> Result<()> {
     let conn = open_connection(state, profile_id)?;
     conn.execute(
-        "INSERT INTO attachments (id, datacard_id, file_name, mime_type, byte_size, created_at, updated_at, deleted_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO attachments (id, datacard_id, file_name, mime_type, byte_size, content_hash, source_mtime, created_at, updated_at, deleted_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
             meta.id,
             meta.datacard_id,
             meta.file_name,
             meta.mime_type,
             meta.byte_size,
+            meta.content_hash,
+            meta.source_mtime,
             meta.created_at,
             meta.updated_at,
             meta.deleted_at
@@ -638,6 +750,24 @@ pub fn insert_attachment(
     Ok(())
 }
 
+/// How many attachment rows (across every datacard, including soft-deleted
+/// ones) still reference `content_hash`'s blob. `purge_attachment` only
+/// removes the on-disk file once this reaches zero, so a blob shared by
+/// several attachments isn't deleted out from under the others.
+pub fn count_attachments_by_content_hash(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    content_hash: &str,
+) -> Result<i64> {
+    let conn = open_connection(state, profile_id)?;
+    conn.query_row(
+        "SELECT COUNT(*) FROM attachments WHERE content_hash = ?1",
+        params![content_hash],
+        |row| row.get(0),
+    )
+    .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
+
 pub fn list_attachments_by_datacard(
     state: &Arc<AppState>,
     profile_id: &str,
@@ -678,6 +808,28 @@ pub fn list_all_attachments_by_datacard(
     Ok(rows)
 }
 
+/// Soft-deleted attachment rows whose `deleted_at` is older than `cutoff`
+/// (an RFC3339 timestamp) — candidates for `retention_service::purge_expired`
+/// to hard-delete, across every datacard in the profile.
+pub fn list_expired_attachments(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    cutoff: &str,
+) -> Result<Vec<AttachmentMeta>> {
+    let conn = open_connection(state, profile_id)?;
+    let mut stmt = conn
+        .prepare("SELECT * FROM attachments WHERE deleted_at IS NOT NULL AND deleted_at < ?1")
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    let rows = stmt
+        .query_map(params![cutoff], map_attachment)
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    Ok(rows)
+}
+
 pub fn soft_delete_attachments_by_datacard(
     state: &Arc<AppState>,
     profile_id: &str,
@@ -766,21 +918,58 @@ pub fn purge_attachment(
     Ok(())
 }
 
+/// `vault_key` is `None` for a passwordless profile (there's nothing to
+/// derive an encryption key from), in which case the row is written as
+/// plain TEXT exactly as before — same optional-key fallback
+/// `attachments_service` already uses for attachment blobs.
+///
+/// `retention_count` bounds `datacard_password_history`'s growth: after the
+/// insert, every row for this `datacard_id` past the `retention_count` most
+/// recent (by `created_at`) is dropped, so a card that's had its password
+/// changed hundreds of times doesn't carry hundreds of rows forever.
 pub fn insert_password_history(
     state: &Arc<AppState>,
     profile_id: &str,
     datacard_id: &str,
     password_value: &str,
     created_at: &str,
+    vault_key: Option<[u8; 32]>,
+    retention_count: i64,
 ) -> Result<()> {
     let conn = open_connection(state, profile_id)?;
     let id = Uuid::new_v4().to_string();
-    conn.execute(
-        "INSERT INTO datacard_password_history (id, datacard_id, password_value, created_at) VALUES (?1, ?2, ?3, ?4)",
-        params![id, datacard_id, password_value, created_at],
-    )
+
+    match vault_key {
+        Some(key) => {
+            let encrypted =
+                EncryptedValue::encrypt(&key, datacard_id.as_bytes(), password_value.as_bytes())?;
+            conn.execute(
+                "INSERT INTO datacard_password_history (id, datacard_id, password_value, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![id, datacard_id, encrypted, created_at],
+            )
+        }
+        None => conn.execute(
+            "INSERT INTO datacard_password_history (id, datacard_id, password_value, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, datacard_id, password_value, created_at],
+        ),
+    }
     .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
 
+    let pruned = conn
+        .execute(
+            "DELETE FROM datacard_password_history WHERE datacard_id = ?1 AND id NOT IN (
+                SELECT id FROM datacard_password_history WHERE datacard_id = ?1 ORDER BY created_at DESC LIMIT ?2
+            )",
+            params![datacard_id, retention_count],
+        )
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    if pruned > 0 {
+        log::info!(
+            "[DB][password_history] pruned {pruned} row(s) past retention_count={retention_count} for datacard_id={datacard_id}"
+        );
+    }
+
     Ok(())
 }
 
@@ -788,6 +977,7 @@ pub fn list_password_history(
     state: &Arc<AppState>,
     profile_id: &str,
     datacard_id: &str,
+    vault_key: Option<[u8; 32]>,
 ) -> Result<Vec<PasswordHistoryRow>> {
     let conn = open_connection(state, profile_id)?;
     let mut stmt = conn
@@ -796,13 +986,13 @@ pub fn list_password_history(
         )
         .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
 
-    let rows = stmt
+    let raw_rows = stmt
         .query_map(params![datacard_id], map_password_history_row)
         .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
         .collect::<rusqlite::Result<Vec<_>>>()
         .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
 
-    Ok(rows)
+    raw_rows.into_iter().map(|row| row.reveal(vault_key)).collect()
 }
 
 pub fn clear_password_history(
@@ -820,3 +1010,48 @@ pub fn clear_password_history(
 
     Ok(deleted as usize)
 }
+
+fn map_audit_log_row(row: &rusqlite::Row) -> rusqlite::Result<AuditLogEntry> {
+    Ok(AuditLogEntry {
+        id: row.get("id")?,
+        table_name: row.get("table_name")?,
+        row_id: row.get("row_id")?,
+        action: row.get("action")?,
+        old_value_json: row.get("old_value_json")?,
+        changed_at: row.get("changed_at")?,
+    })
+}
+
+/// Every edit/delete trigger-captured entry for `row_id` (a `datacards`,
+/// `attachments`, or `datacard_password_history` primary key), newest
+/// first — mirrors `list_password_history`'s shape.
+pub fn list_audit_log(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    row_id: &str,
+) -> Result<Vec<AuditLogEntry>> {
+    let conn = open_connection(state, profile_id)?;
+    let mut stmt = conn
+        .prepare("SELECT * FROM audit_log WHERE row_id = ?1 ORDER BY changed_at DESC")
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    let rows = stmt
+        .query_map(params![row_id], map_audit_log_row)
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+
+    Ok(rows)
+}
+
+/// The profile's on-disk `PRAGMA user_version`, via whichever connection
+/// `state.connection_source` hands out — the same pooled connection every
+/// query above goes through, so this reports whatever
+/// `data::sqlite::pool::get_or_create_pool` already migrated it to rather
+/// than `migrations::CURRENT_DB_VERSION`, which is only the version this
+/// build of the app knows how to migrate *up to*.
+pub fn db_schema_version(state: &Arc<AppState>, profile_id: &str) -> Result<i32> {
+    let conn = open_connection(state, profile_id)?;
+    conn.query_row("PRAGMA user_version;", [], |row| row.get(0))
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
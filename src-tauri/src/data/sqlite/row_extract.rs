@@ -0,0 +1,64 @@
+//! A typed alternative to writing `row.get(0)?, row.get(1)?, ...` by hand in
+//! every `query_map`/`query_row` callback across `repo_impl` and friends —
+//! those positional calls compile fine even after a `SELECT` is reordered
+//! or a column is inserted in the middle, so a mismatch only shows up as a
+//! wrong value (or a runtime `InvalidColumnType`) at query time instead of
+//! a type error at the call site.
+//!
+//! `FromRow` is implemented for tuples of 1 to 12 `FromSql` types in
+//! column order, so `stmt.query_map(params, row_extract::<(String, i64)>)`
+//! reads columns 0 and 1 as a `(String, i64)` the same way `row.get(0)?`/
+//! `row.get(1)?` would, but fails to compile instead of silently reading
+//! the wrong column if the `SELECT` and the tuple type ever drift apart.
+//! Existing call sites aren't required to adopt this — it's additive,
+//! for new and rewritten queries to prefer going forward.
+
+use rusqlite::types::FromSql;
+use rusqlite::{Connection, Params, Row};
+
+use crate::error::{ErrorCodeString, Result};
+
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> rusqlite::Result<Self>;
+}
+
+macro_rules! impl_from_row_for_tuple {
+    ($($T:ident : $idx:tt),+) => {
+        impl<$($T: FromSql),+> FromRow for ($($T,)+) {
+            fn from_row(row: &Row) -> rusqlite::Result<Self> {
+                Ok(($(row.get::<_, $T>($idx)?,)+))
+            }
+        }
+    };
+}
+
+impl_from_row_for_tuple!(A: 0);
+impl_from_row_for_tuple!(A: 0, B: 1);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+impl_from_row_for_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
+
+/// A `query_map`/`query_row` callback that reads `T`'s columns positionally
+/// via `FromRow` — pass it by turbofish, e.g.
+/// `stmt.query_map(params, row_extract::<(String, i64)>)`.
+pub fn row_extract<T: FromRow>(row: &Row) -> rusqlite::Result<T> {
+    T::from_row(row)
+}
+
+/// Runs `sql` and collects every row as a `T`, mapping any rusqlite failure
+/// (prepare, bind, or a row that doesn't fit `T`) to `DB_QUERY_FAILED` the
+/// same way the rest of `repo_impl`'s query functions do.
+pub fn query_all<T: FromRow, P: Params>(conn: &Connection, sql: &str, params: P) -> Result<Vec<T>> {
+    let mut stmt = conn.prepare(sql).map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?;
+    stmt.query_map(params, row_extract::<T>)
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))?
+        .collect::<rusqlite::Result<Vec<T>>>()
+        .map_err(|_| ErrorCodeString::new("DB_QUERY_FAILED"))
+}
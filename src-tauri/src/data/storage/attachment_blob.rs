@@ -0,0 +1,124 @@
+//! Persistence for attachment ciphertext — pulled out of the `std::fs`
+//! calls `attachments_service` used to make directly, the same way
+//! `vault_blob` already pulled the vault DB/master key out of
+//! `security_service`/`master_key`, so a remote (e.g. S3-compatible)
+//! backend can eventually stand in for the local one while the DB metadata
+//! stays put. Because only already-sealed ciphertext ever crosses this
+//! boundary, a backend never sees plaintext or a usable key.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::sync::Mutex;
+
+use crate::data::profiles::paths::attachment_file_path;
+use crate::data::storage_paths::StoragePaths;
+use crate::error::{ErrorCodeString, Result};
+
+/// Keyed by `(profile_id, content_hash)` rather than a single flat string —
+/// mirrors `VaultBlobStorage::read_blob(profile_id, kind)` rather than
+/// forcing every caller to format/parse a composite key by hand.
+pub trait AttachmentBlobStorage: Send + Sync {
+    fn put(&self, profile_id: &str, content_hash: &str, bytes: &[u8]) -> Result<()>;
+
+    /// A reader over the blob's sealed bytes, so a caller (see
+    /// `attachments_service::read_attachment_blob`/`save_attachment_to_path`)
+    /// can decrypt it one `stream_cipher` frame at a time instead of
+    /// buffering the whole ciphertext in memory first.
+    fn get(&self, profile_id: &str, content_hash: &str) -> Result<Box<dyn Read + Send>>;
+    fn delete(&self, profile_id: &str, content_hash: &str) -> Result<()>;
+    fn exists(&self, profile_id: &str, content_hash: &str) -> Result<bool>;
+
+    /// True only for the built-in local-filesystem backend. Same purpose as
+    /// `VaultBlobStorage::is_local_default`: lets `AppState` re-point the
+    /// default backend at a new workspace directory without clobbering a
+    /// backend the user explicitly installed.
+    fn is_local_default(&self) -> bool {
+        false
+    }
+}
+
+/// Default backend: the same content-addressed files `attachment_file_path`
+/// has always pointed at.
+pub struct LocalFsAttachmentBlobStorage {
+    storage_paths: StoragePaths,
+}
+
+impl LocalFsAttachmentBlobStorage {
+    pub fn new(storage_paths: StoragePaths) -> Self {
+        Self { storage_paths }
+    }
+}
+
+impl AttachmentBlobStorage for LocalFsAttachmentBlobStorage {
+    fn put(&self, profile_id: &str, content_hash: &str, bytes: &[u8]) -> Result<()> {
+        let path = attachment_file_path(&self.storage_paths, profile_id, content_hash)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
+        }
+        fs::write(&path, bytes).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))
+    }
+
+    fn get(&self, profile_id: &str, content_hash: &str) -> Result<Box<dyn Read + Send>> {
+        let path = attachment_file_path(&self.storage_paths, profile_id, content_hash)?;
+        let file = fs::File::open(&path).map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+        Ok(Box::new(file))
+    }
+
+    fn delete(&self, profile_id: &str, content_hash: &str) -> Result<()> {
+        let path = attachment_file_path(&self.storage_paths, profile_id, content_hash)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
+        }
+        Ok(())
+    }
+
+    fn exists(&self, profile_id: &str, content_hash: &str) -> Result<bool> {
+        Ok(attachment_file_path(&self.storage_paths, profile_id, content_hash)?.exists())
+    }
+
+    fn is_local_default(&self) -> bool {
+        true
+    }
+}
+
+/// Backs attachments with a plain in-memory map instead of the filesystem,
+/// so the service layer can be exercised without touching disk.
+#[derive(Default)]
+pub struct InMemoryAttachmentBlobStorage {
+    blobs: Mutex<HashMap<(String, String), Vec<u8>>>,
+}
+
+impl InMemoryAttachmentBlobStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AttachmentBlobStorage for InMemoryAttachmentBlobStorage {
+    fn put(&self, profile_id: &str, content_hash: &str, bytes: &[u8]) -> Result<()> {
+        let mut blobs = self.blobs.lock().map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        blobs.insert((profile_id.to_string(), content_hash.to_string()), bytes.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, profile_id: &str, content_hash: &str) -> Result<Box<dyn Read + Send>> {
+        let blobs = self.blobs.lock().map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        let bytes = blobs
+            .get(&(profile_id.to_string(), content_hash.to_string()))
+            .cloned()
+            .ok_or_else(|| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+        Ok(Box::new(Cursor::new(bytes)))
+    }
+
+    fn delete(&self, profile_id: &str, content_hash: &str) -> Result<()> {
+        let mut blobs = self.blobs.lock().map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        blobs.remove(&(profile_id.to_string(), content_hash.to_string()));
+        Ok(())
+    }
+
+    fn exists(&self, profile_id: &str, content_hash: &str) -> Result<bool> {
+        let blobs = self.blobs.lock().map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        Ok(blobs.contains_key(&(profile_id.to_string(), content_hash.to_string())))
+    }
+}
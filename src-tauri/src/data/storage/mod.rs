@@ -0,0 +1,15 @@
+//! Blob-storage backends: where the vault DB/master-key blob, attachment
+//! ciphertext, and chunk-store payloads actually live. See `vault_blob`,
+//! `attachment_blob`, and `vault_chunk_store` for the traits and the
+//! built-in local-filesystem implementations.
+//!
+//! This module used to also define a `RowStore`/`BlobStore`/`VaultStorage`
+//! trait meant to let `vault_service`/`datacards_service`/`bank_cards_service`
+//! run against a backend other than `data::sqlite::repo_impl`. Nothing ever
+//! called it outside its own tests — every real service went straight to
+//! `repo_impl` from day one — so it was dropped rather than left sitting
+//! alongside `repo_impl` as a second, inert persistence stack nobody used.
+
+pub mod attachment_blob;
+pub mod vault_blob;
+pub mod vault_chunk_store;
@@ -0,0 +1,106 @@
+//! Persistence for the encrypted blobs a vault is actually made of: the
+//! encrypted SQLite database and the wrapped master key. `security_service`
+//! and `data::crypto::master_key` used to reach straight into
+//! `data::profiles::paths`/`std::fs` for these, which hard-wires them to
+//! the local disk. `VaultBlobStorage` pulls that boundary out into a trait
+//! so a remote implementation (see `s3_vault_blob`) can stand in for the
+//! local one, the same way `data::sync::VaultSyncTransport` already
+//! separates "the blob" from "how it moves" for opt-in sync. Because only
+//! already-encrypted bytes ever cross this boundary, a remote backend never
+//! sees plaintext or a usable key.
+
+pub mod s3_vault_blob;
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::data::fs::atomic_write::write_atomic;
+use crate::data::profiles::paths::{vault_db_path, vault_key_path, vault_sync_log_path};
+use crate::data::storage_paths::StoragePaths;
+use crate::error::{ErrorCodeString, Result};
+
+/// Which on-disk blob an implementation is being asked to read/write. Kept
+/// as a small closed enum (rather than a free-form string key) so a backend
+/// can't be handed a blob this crate doesn't know how to interpret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultBlobKind {
+    VaultDb,
+    MasterKey,
+    /// The CRDT-style operation/checkpoint log — see
+    /// `data::sync::vault_log`. Riding the same backend as `VaultDb` means a
+    /// remote storage backend syncs the log alongside the vault blob for
+    /// free, with no transport of its own to configure.
+    SyncLog,
+}
+
+impl VaultBlobKind {
+    fn file_name(self) -> &'static str {
+        match self {
+            VaultBlobKind::VaultDb => "vault.db",
+            VaultBlobKind::MasterKey => "vault_key.bin",
+            VaultBlobKind::SyncLog => "vault_sync_log.bin",
+        }
+    }
+}
+
+pub trait VaultBlobStorage: Send + Sync {
+    fn read_blob(&self, profile_id: &str, kind: VaultBlobKind) -> Result<Vec<u8>>;
+    fn write_blob(&self, profile_id: &str, kind: VaultBlobKind, bytes: &[u8]) -> Result<()>;
+    fn exists(&self, profile_id: &str, kind: VaultBlobKind) -> Result<bool>;
+    fn delete(&self, profile_id: &str, kind: VaultBlobKind) -> Result<()>;
+
+    /// True only for the built-in local-filesystem backend. Lets
+    /// `AppState` re-point the default backend at a new workspace directory
+    /// on `set_workspace_root` without clobbering a backend the user
+    /// explicitly chose via `set_vault_blob_storage`.
+    fn is_local_default(&self) -> bool {
+        false
+    }
+}
+
+/// Default backend: the same local files `vault_db_path`/`vault_key_path`
+/// have always pointed at.
+pub struct LocalFsVaultBlobStorage {
+    storage_paths: StoragePaths,
+}
+
+impl LocalFsVaultBlobStorage {
+    pub fn new(storage_paths: StoragePaths) -> Self {
+        Self { storage_paths }
+    }
+
+    fn path_for(&self, profile_id: &str, kind: VaultBlobKind) -> Result<PathBuf> {
+        match kind {
+            VaultBlobKind::VaultDb => vault_db_path(&self.storage_paths, profile_id),
+            VaultBlobKind::MasterKey => vault_key_path(&self.storage_paths, profile_id),
+            VaultBlobKind::SyncLog => vault_sync_log_path(&self.storage_paths, profile_id),
+        }
+    }
+}
+
+impl VaultBlobStorage for LocalFsVaultBlobStorage {
+    fn read_blob(&self, profile_id: &str, kind: VaultBlobKind) -> Result<Vec<u8>> {
+        fs::read(self.path_for(profile_id, kind)?).map_err(|_| ErrorCodeString::new("VAULT_BLOB_READ_FAILED"))
+    }
+
+    fn write_blob(&self, profile_id: &str, kind: VaultBlobKind, bytes: &[u8]) -> Result<()> {
+        write_atomic(&self.path_for(profile_id, kind)?, bytes)
+            .map_err(|_| ErrorCodeString::new("VAULT_BLOB_WRITE_FAILED"))
+    }
+
+    fn exists(&self, profile_id: &str, kind: VaultBlobKind) -> Result<bool> {
+        Ok(self.path_for(profile_id, kind)?.exists())
+    }
+
+    fn delete(&self, profile_id: &str, kind: VaultBlobKind) -> Result<()> {
+        let path = self.path_for(profile_id, kind)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|_| ErrorCodeString::new("VAULT_BLOB_DELETE_FAILED"))?;
+        }
+        Ok(())
+    }
+
+    fn is_local_default(&self) -> bool {
+        true
+    }
+}
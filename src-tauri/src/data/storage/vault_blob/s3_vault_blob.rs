@@ -0,0 +1,169 @@
+//! An S3-compatible `VaultBlobStorage` so encrypted vault/key blobs can be
+//! synced across machines through an object store instead of a single local
+//! disk. Signs requests with AWS SigV4 by hand instead of pulling in the
+//! full `aws-sdk-s3` dependency tree — the same small-hand-rolled-client
+//! tradeoff this crate already makes for `ipc::manifest`'s raw Windows
+//! registry FFI rather than a registry crate. Payloads are sent as
+//! `UNSIGNED-PAYLOAD` (valid under SigV4 over HTTPS) so signing doesn't need
+//! to buffer and hash the body twice.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use reqwest::Method;
+use sha2::{Digest, Sha256};
+
+use super::{VaultBlobKind, VaultBlobStorage};
+use crate::error::{ErrorCodeString, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Connection details for an S3-compatible bucket. `endpoint` is the
+/// scheme+host the bucket is reachable at (e.g.
+/// `https://s3.us-east-1.amazonaws.com` or a MinIO/Backblaze equivalent);
+/// objects are addressed path-style as `{endpoint}/{bucket}/{key}`.
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+pub struct S3VaultBlobStorage {
+    config: S3Config,
+    client: Client,
+}
+
+impl S3VaultBlobStorage {
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    fn object_key(&self, profile_id: &str, kind: VaultBlobKind) -> String {
+        format!("{}/{}", profile_id, kind.file_name())
+    }
+
+    fn host(&self) -> Result<String> {
+        self.config
+            .endpoint
+            .split("://")
+            .nth(1)
+            .map(str::to_string)
+            .ok_or_else(|| ErrorCodeString::new("S3_CONFIG_INVALID"))
+    }
+
+    fn sign_and_send(&self, method: Method, key: &str, body: Vec<u8>) -> Result<reqwest::blocking::Response> {
+        let host = self.host()?;
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+        let canonical_uri = format!("/{}/{}", self.config.bucket, key);
+
+        let canonical_headers = format!(
+            "host:{host}\nx-amz-content-sha256:UNSIGNED-PAYLOAD\nx-amz-date:{amz_date}\n"
+        );
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+        let canonical_request = format!(
+            "{method}\n{canonical_uri}\n\n{canonical_headers}\n{signed_headers}\nUNSIGNED-PAYLOAD"
+        );
+        let canonical_request_hash = to_hex(&Sha256::digest(canonical_request.as_bytes()));
+
+        let credential_scope = format!("{date_stamp}/{}/s3/aws4_request", self.config.region);
+        let string_to_sign =
+            format!("AWS4-HMAC-SHA256\n{amz_date}\n{credential_scope}\n{canonical_request_hash}");
+
+        let signature = self.signature(&date_stamp, &string_to_sign)?;
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{credential_scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.config.access_key
+        );
+
+        let url = format!("{}{canonical_uri}", self.config.endpoint.trim_end_matches('/'));
+        self.client
+            .request(method, url)
+            .header("host", host)
+            .header("x-amz-content-sha256", "UNSIGNED-PAYLOAD")
+            .header("x-amz-date", amz_date)
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .map_err(|_| ErrorCodeString::new("S3_REQUEST_FAILED"))
+    }
+
+    fn signature(&self, date_stamp: &str, string_to_sign: &str) -> Result<String> {
+        let hmac_new = |key: &[u8]| HmacSha256::new_from_slice(key).map_err(|_| ErrorCodeString::new("S3_SIGNING_FAILED"));
+
+        let k_date = hmac_new(format!("AWS4{}", self.config.secret_key).as_bytes())?
+            .chain_update(date_stamp.as_bytes())
+            .finalize()
+            .into_bytes();
+        let k_region = hmac_new(&k_date)?
+            .chain_update(self.config.region.as_bytes())
+            .finalize()
+            .into_bytes();
+        let k_service = hmac_new(&k_region)?.chain_update(b"s3").finalize().into_bytes();
+        let k_signing = hmac_new(&k_service)?
+            .chain_update(b"aws4_request")
+            .finalize()
+            .into_bytes();
+
+        let signature = hmac_new(&k_signing)?
+            .chain_update(string_to_sign.as_bytes())
+            .finalize()
+            .into_bytes();
+        Ok(to_hex(&signature))
+    }
+}
+
+impl VaultBlobStorage for S3VaultBlobStorage {
+    fn read_blob(&self, profile_id: &str, kind: VaultBlobKind) -> Result<Vec<u8>> {
+        let key = self.object_key(profile_id, kind);
+        let resp = self.sign_and_send(Method::GET, &key, Vec::new())?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ErrorCodeString::new("VAULT_BLOB_NOT_FOUND"));
+        }
+        if !resp.status().is_success() {
+            return Err(ErrorCodeString::new("VAULT_BLOB_READ_FAILED"));
+        }
+        resp.bytes()
+            .map(|b| b.to_vec())
+            .map_err(|_| ErrorCodeString::new("VAULT_BLOB_READ_FAILED"))
+    }
+
+    fn write_blob(&self, profile_id: &str, kind: VaultBlobKind, bytes: &[u8]) -> Result<()> {
+        let key = self.object_key(profile_id, kind);
+        let resp = self.sign_and_send(Method::PUT, &key, bytes.to_vec())?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(ErrorCodeString::new("VAULT_BLOB_WRITE_FAILED"))
+        }
+    }
+
+    fn exists(&self, profile_id: &str, kind: VaultBlobKind) -> Result<bool> {
+        let key = self.object_key(profile_id, kind);
+        let resp = self.sign_and_send(Method::HEAD, &key, Vec::new())?;
+        Ok(resp.status().is_success())
+    }
+
+    fn delete(&self, profile_id: &str, kind: VaultBlobKind) -> Result<()> {
+        let key = self.object_key(profile_id, kind);
+        let resp = self.sign_and_send(Method::DELETE, &key, Vec::new())?;
+        if resp.status().is_success() || resp.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(ErrorCodeString::new("VAULT_BLOB_DELETE_FAILED"))
+        }
+    }
+}
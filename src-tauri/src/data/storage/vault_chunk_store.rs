@@ -0,0 +1,131 @@
+//! Chunked, deduplicated persistence for the vault database, modeled on
+//! proxmox-backup's chunk store (see `data::backup::chunk_store` for the
+//! sibling used by full backups). `persist_active_vault` used to encrypt
+//! and write the *entire* serialized SQLite DB on every lock, which gets
+//! expensive once a vault accumulates attachments. Instead, the serialized
+//! bytes are split into content-defined chunks with
+//! `data::backup::chunking::chunk`, each chunk is encrypted individually
+//! and written once under the hex SHA-256 of its plaintext (skipping chunks
+//! already on disk from a previous persist), and only a small encrypted
+//! manifest listing the ordered chunk hashes is stored as the vault's
+//! addressable blob. Because CDC keeps boundaries stable across small
+//! edits, a typical lock only touches a few chunks.
+//!
+//! Differences from `data::backup::chunk_store::ChunkStore`: each chunk
+//! lives under its own profile directory rather than one shared store, and
+//! each chunk's AEAD binds its own hash into the AAD (so a chunk's
+//! ciphertext can't be replayed under a different hash) instead of using
+//! `cipher::encrypt_placeholder`.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::data::backup::chunking::chunk;
+use crate::data::crypto::cipher;
+use crate::data::fs::atomic_write::write_atomic;
+use crate::data::profiles::paths::profile_dir;
+use crate::data::storage_paths::StoragePaths;
+use crate::error::{ErrorCodeString, Result};
+
+/// The vault's addressable blob is this, encrypted under the vault key
+/// (see `cipher::encrypt_vault_blob`/`decrypt_vault_blob`) — not the
+/// serialized database itself anymore.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultManifest {
+    pub chunk_hashes: Vec<String>,
+}
+
+pub fn chunk_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn chunk_aad(profile_id: &str, hash: &str) -> Vec<u8> {
+    format!("vault_chunk:{profile_id}:{hash}").into_bytes()
+}
+
+pub struct VaultChunkStore {
+    chunks_dir: PathBuf,
+}
+
+impl VaultChunkStore {
+    pub fn new(sp: &StoragePaths, profile_id: &str) -> Result<Self> {
+        Ok(Self {
+            chunks_dir: profile_dir(sp, profile_id)?.join("vault_chunks"),
+        })
+    }
+
+    /// Points a store at an arbitrary directory instead of the profile's
+    /// canonical `vault_chunks`. Every chunk here is content-addressed by
+    /// plaintext hash but encrypted under one specific `vault_key` — so a
+    /// key rotation (`profiles_service::rotate_master_password`) can't
+    /// reuse `new`'s directory to write chunks under a *new* key, since
+    /// `put_chunks` would see the old ciphertext already on disk under the
+    /// same hash and skip rewriting it. Rotation instead stages the new
+    /// key's chunks in a fresh sibling directory built with this
+    /// constructor, then swaps it in for `vault_chunks` only once every
+    /// chunk and the manifest pointing at them are written successfully.
+    pub fn new_in_dir(chunks_dir: PathBuf) -> Self {
+        Self { chunks_dir }
+    }
+
+    fn chunk_path(&self, hash: &str) -> PathBuf {
+        self.chunks_dir.join(format!("{hash}.chunk"))
+    }
+
+    /// Splits `plaintext` into content-defined chunks and writes each one
+    /// that isn't already on disk, encrypted under `vault_key` with its own
+    /// hash bound into the AAD. Returns the ordered chunk hashes to persist
+    /// as the manifest.
+    pub fn put_chunks(&self, profile_id: &str, vault_key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<String>> {
+        fs::create_dir_all(&self.chunks_dir).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_WRITE"))?;
+
+        let mut hashes = Vec::new();
+        for piece in chunk(plaintext) {
+            let hash = chunk_hash(piece);
+            let path = self.chunk_path(&hash);
+            if !path.exists() {
+                let encrypted = cipher::encrypt_bytes(vault_key, &chunk_aad(profile_id, &hash), piece)?;
+                write_atomic(&path, &encrypted).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_WRITE"))?;
+            }
+            hashes.push(hash);
+        }
+        Ok(hashes)
+    }
+
+    /// Reads and decrypts every chunk in `hashes`, in order, reassembling
+    /// the original plaintext.
+    pub fn get_chunks(&self, profile_id: &str, vault_key: &[u8; 32], hashes: &[String]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for hash in hashes {
+            let encrypted =
+                fs::read(self.chunk_path(hash)).map_err(|_| ErrorCodeString::new("VAULT_CORRUPTED"))?;
+            let plaintext = cipher::decrypt_bytes(vault_key, &chunk_aad(profile_id, hash), &encrypted)?;
+            out.extend_from_slice(&plaintext);
+        }
+        Ok(out)
+    }
+
+    /// Deletes any chunk file not referenced by `live_hashes`. Only call
+    /// this after the new manifest has already been written successfully —
+    /// GC-before-manifest-write would risk deleting a chunk the old
+    /// manifest still points to if the process crashes in between.
+    pub fn gc(&self, live_hashes: &HashSet<String>) -> Result<()> {
+        let Ok(entries) = fs::read_dir(&self.chunks_dir) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !live_hashes.contains(stem) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,11 +2,119 @@ use std::path::{Path, PathBuf};
 
 use crate::error::{ErrorCodeString, Result};
 
+/// Filesystem types that back a network mount, as reported in the third
+/// column of `/proc/mounts`. SQLite's locking protocol assumes a local
+/// filesystem; on these, `pool::get_or_create_pool` falls back to stricter
+/// durability pragmas (see its doc comment).
+#[cfg(target_os = "linux")]
+const NETWORK_FSTYPES: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "smbfs", "fuse.sshfs"];
+
+/// Best-effort check for whether `path` lives on a network-backed mount
+/// (NFS/CIFS/SMB/SFTP-over-FUSE, a mapped Windows network drive or UNC
+/// share, a macOS AFP/SMB/WebDAV mount). Used to warn the user that a
+/// workspace they picked may make SQLite's normal durability assumptions
+/// unsafe, and to pick stronger sync pragmas for it (see `sqlite::pool`).
+///
+/// Implemented on Linux (walking `/proc/mounts`), Windows
+/// (`GetDriveTypeW`/UNC prefix) and macOS (`statfs`'s `f_fstypename`). On
+/// any other platform this always returns `false` — not because those
+/// platforms are immune, but because there's no cheap, dependency-free way
+/// to ask the question there; we'd rather under-warn than guess wrong.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_network_filesystem(path: &Path) -> bool {
+    let Ok(contents) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    let mut best_match: Option<(&Path, &str)> = None;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(mount_point), Some(fstype)) = (fields.next(), fields.nth(1)) else {
+            continue;
+        };
+        let mount_point = Path::new(mount_point);
+        if !path.starts_with(mount_point) {
+            continue;
+        }
+        let is_longer = best_match
+            .map(|(current, _)| mount_point.as_os_str().len() > current.as_os_str().len())
+            .unwrap_or(true);
+        if is_longer {
+            best_match = Some((mount_point, fstype));
+        }
+    }
+    best_match
+        .map(|(_, fstype)| NETWORK_FSTYPES.contains(&fstype))
+        .unwrap_or(false)
+}
+
+/// A UNC path (`\\server\share\...`) is always remote; a drive letter is
+/// checked with `GetDriveTypeW`, which distinguishes `DRIVE_REMOTE` (a
+/// mapped network share) from local/removable/fixed drives without
+/// needing to open the path at all.
+#[cfg(windows)]
+pub(crate) fn is_network_filesystem(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{GetDriveTypeW, DRIVE_REMOTE};
+
+    let Some(path_str) = path.to_str() else {
+        return false;
+    };
+    if path_str.starts_with(r"\\") {
+        return true;
+    }
+
+    let Some(drive_letter) = path_str.chars().next() else {
+        return false;
+    };
+    if !drive_letter.is_ascii_alphabetic() || !path_str[1..].starts_with(':') {
+        return false;
+    }
+
+    let root = format!("{drive_letter}:\\");
+    let wide: Vec<u16> = std::ffi::OsStr::new(&root)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let drive_type = unsafe { GetDriveTypeW(wide.as_ptr()) };
+    drive_type == DRIVE_REMOTE
+}
+
+/// `statfs`'s `f_fstypename` names the mount's filesystem type directly, the
+/// same signal `is_network_filesystem`'s Linux impl reads out of
+/// `/proc/mounts`'s fstype column — no `/proc` equivalent exists on macOS,
+/// so this calls into libc instead.
+#[cfg(target_os = "macos")]
+pub(crate) fn is_network_filesystem(path: &Path) -> bool {
+    const NETWORK_FSTYPES: &[&str] = &["nfs", "smbfs", "afpfs", "webdav", "fuse", "ftp"];
+
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_encoded_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+
+    let fstypename = stat
+        .f_fstypename
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| b as u8 as char)
+        .collect::<String>();
+    NETWORK_FSTYPES.contains(&fstypename.as_str())
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+pub(crate) fn is_network_filesystem(_path: &Path) -> bool {
+    false
+}
+
 #[derive(Debug, Clone)]
 pub struct StoragePaths {
     app_dir: PathBuf,
     workspace_root: Option<PathBuf>,
     profiles_root: Option<PathBuf>,
+    network_workspace: bool,
 }
 
 impl StoragePaths {
@@ -22,6 +130,7 @@ impl StoragePaths {
             app_dir,
             workspace_root: None,
             profiles_root: None,
+            network_workspace: false,
         })
     }
 
@@ -39,6 +148,7 @@ impl StoragePaths {
             .map_err(|_| ErrorCodeString::new("WORKSPACE_NOT_WRITABLE"))?;
         let _ = std::fs::remove_file(&write_test);
 
+        self.network_workspace = is_network_filesystem(&workspace_root);
         self.workspace_root = Some(workspace_root);
         self.profiles_root = Some(profiles_root);
         Ok(())
@@ -47,6 +157,17 @@ impl StoragePaths {
     pub fn clear_workspace(&mut self) {
         self.workspace_root = None;
         self.profiles_root = None;
+        self.network_workspace = false;
+    }
+
+    /// Whether the currently configured workspace sits on a detected
+    /// network mount. The command layer uses this to warn the user that a
+    /// network drive can make SQLite's file locking unreliable;
+    /// `sqlite::pool` uses it to pick stronger durability pragmas. Always
+    /// `false` before a workspace is configured, and on platforms
+    /// `is_network_filesystem` doesn't support.
+    pub fn is_network_workspace(&self) -> bool {
+        self.network_workspace
     }
 
     pub fn workspace_root(&self) -> Result<&PathBuf> {
@@ -0,0 +1,43 @@
+//! A `VaultSyncTransport` backed by a plain directory on disk. Stands in
+//! for a real remote (S3, a sync server, ...) in environments where none is
+//! configured yet — e.g. a directory on a USB drive or a synced folder
+//! managed by Dropbox/Syncthing outside this app entirely.
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::VaultSyncTransport;
+use crate::data::fs::atomic_write::write_atomic;
+use crate::error::{ErrorCodeString, Result};
+
+pub struct LocalDirSyncTransport {
+    root: PathBuf,
+}
+
+impl LocalDirSyncTransport {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn blob_path(&self, profile_id: &str) -> PathBuf {
+        self.root.join(format!("{profile_id}.vaultsync"))
+    }
+}
+
+impl VaultSyncTransport for LocalDirSyncTransport {
+    fn push(&self, profile_id: &str, encrypted_blob: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.root).map_err(|_| ErrorCodeString::new("SYNC_WRITE_FAILED"))?;
+        write_atomic(&self.blob_path(profile_id), encrypted_blob)
+            .map_err(|_| ErrorCodeString::new("SYNC_WRITE_FAILED"))
+    }
+
+    fn pull(&self, profile_id: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.blob_path(profile_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        fs::read(&path)
+            .map(Some)
+            .map_err(|_| ErrorCodeString::new("SYNC_READ_FAILED"))
+    }
+}
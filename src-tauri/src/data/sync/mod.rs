@@ -0,0 +1,23 @@
+//! Transport abstraction for pushing/pulling encrypted vault blobs to a
+//! remote sync target. Nothing in this module understands vault internals
+//! — it only moves opaque encrypted bytes keyed by profile id, the same way
+//! `data::storage` abstracts the local row/blob store from its backend.
+//!
+//! Sync is opt-in: `AppState::sync_transport` is `None` unless a transport
+//! has been installed, and `services::sync_service` treats that as "sync
+//! disabled" rather than an error.
+
+pub mod local_dir;
+pub mod vault_log;
+
+use crate::error::Result;
+
+pub trait VaultSyncTransport: Send + Sync {
+    /// Uploads `encrypted_blob` as the latest copy for `profile_id`,
+    /// replacing whatever was there before.
+    fn push(&self, profile_id: &str, encrypted_blob: &[u8]) -> Result<()>;
+
+    /// Downloads the latest copy for `profile_id`, or `None` if the remote
+    /// has never seen this profile.
+    fn pull(&self, profile_id: &str) -> Result<Option<Vec<u8>>>;
+}
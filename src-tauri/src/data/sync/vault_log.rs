@@ -0,0 +1,285 @@
+//! A log-structured CRDT layered on top of the single-writer vault blob:
+//! every mutation appends a timestamped, sealed operation record keyed by a
+//! hybrid logical clock, and every `CHECKPOINT_EVERY_N_OPS` operations a
+//! full sealed snapshot of the materialized vault state is folded in and
+//! everything older is compacted away. Two devices that each append to
+//! their own copy of this log and then exchange it (over the pluggable
+//! `VaultBlobStorage` backend this log rides on, or any shared directory
+//! synced by other means) can merge by replaying each other's operations in
+//! HLC order from the newest checkpoint forward, converging on the same
+//! state regardless of which device recorded first.
+//!
+//! Deliberately a separate mechanism from `services::oplog_service` /
+//! `data::sqlite::oplog`, which tracks per-device ack cursors over the
+//! SQLite row log so one already-agreed-upon source of truth can hand out
+//! incremental "what changed since I last synced" answers. This module
+//! instead answers "how do two independently-mutated copies converge" — so
+//! unlike the oplog, its records are sealed with real AEAD
+//! (`cipher::encrypt_sync_log_entry`) rather than `cipher::encrypt_placeholder`,
+//! since this log is meant to leave the device.
+//!
+//! Wiring `append_operation` into `datacards_service`/`folders_service`/
+//! `attachments_service`'s mutation paths and actually applying
+//! `load_since_checkpoint`'s replayed operations back into a live vault are
+//! left as follow-up work — see the doc comments below for exactly where
+//! each would plug in.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::data::crypto::cipher;
+use crate::data::storage::vault_blob::VaultBlobKind;
+use crate::error::{ErrorCodeString, Result};
+
+/// Folds the log back down to a checkpoint once this many operations have
+/// accumulated since the last one. Smaller than
+/// `oplog_service::CHECKPOINT_EVERY_N_OPS` because a checkpoint here carries
+/// the entire materialized vault blob, not just a compaction marker.
+const CHECKPOINT_EVERY_N_OPS: usize = 64;
+
+/// A hybrid logical clock: wall-clock milliseconds, a tiebreaker counter for
+/// multiple events in the same millisecond, and the device that minted it
+/// as a final tiebreaker so two devices can never mint the same value.
+/// Field order matters for the derived `Ord` — millis first, then counter,
+/// then device id — which is exactly HLC precedence.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Hlc {
+    pub millis: i64,
+    pub counter: u32,
+    pub device_id: String,
+}
+
+impl Hlc {
+    /// Smaller than any clock a real device would mint, so "replay
+    /// everything after the epoch" means "replay the whole log".
+    pub fn epoch() -> Hlc {
+        Hlc { millis: 0, counter: 0, device_id: String::new() }
+    }
+
+    /// Advances past `self`, following the standard HLC rule: if the wall
+    /// clock has moved on, adopt it and reset the counter; otherwise (equal
+    /// or behind, e.g. clock skew) stay at `self.millis` and bump the
+    /// counter so ordering is still strictly increasing.
+    pub fn next(&self, device_id: &str, now_millis: i64) -> Hlc {
+        if now_millis > self.millis {
+            Hlc {
+                millis: now_millis,
+                counter: 0,
+                device_id: device_id.to_string(),
+            }
+        } else {
+            Hlc {
+                millis: self.millis,
+                counter: self.counter + 1,
+                device_id: device_id.to_string(),
+            }
+        }
+    }
+}
+
+/// One vault mutation worth recording in the sync log. Deliberately
+/// separate from `oplog_service::VaultOperation` — this one additionally
+/// covers attachments and settings, which the ack-cursor oplog doesn't
+/// track today.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncOperation {
+    DataCardUpserted { id: String },
+    DataCardDeleted { id: String },
+    FolderUpserted { id: String },
+    FolderDeleted { id: String },
+    AttachmentUpserted { id: String },
+    AttachmentDeleted { id: String },
+    SettingsChanged,
+}
+
+/// Sealed payload of a checkpoint record: the vault's materialized state at
+/// the time it was taken. `vault_blob` is read straight out of
+/// `VaultBlobKind::VaultDb` — the same already-sealed manifest bytes
+/// `security_service::persist_active_vault` writes — rather than
+/// re-serializing the database a second time, so a checkpoint always opens
+/// exactly the way a fresh login would decrypt it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CheckpointPayload {
+    vault_blob: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SealedRecord {
+    Operation(Vec<u8>),
+    Checkpoint(Vec<u8>),
+}
+
+/// One entry in the on-disk log. `hlc` is stored unsealed — a timestamp and
+/// device id leak nothing about vault contents, and keeping it in the clear
+/// lets `append_operation` find the latest clock without decrypting every
+/// record already in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredRecord {
+    hlc: Hlc,
+    sealed: SealedRecord,
+}
+
+/// A decrypted operation paired with the clock it was recorded under, as
+/// returned by `load_since_checkpoint`.
+#[derive(Debug, Clone)]
+pub struct ReplayOperation {
+    pub hlc: Hlc,
+    pub op: SyncOperation,
+}
+
+/// The result of replaying the log from its newest checkpoint forward: the
+/// checkpoint's materialized vault blob (sealed the same way
+/// `VaultBlobKind::VaultDb` is), plus every operation recorded after it, in
+/// the order they must be applied for two merged logs to converge.
+pub struct ReplayState {
+    pub checkpoint_hlc: Hlc,
+    pub checkpoint_vault_blob: Vec<u8>,
+    pub operations: Vec<ReplayOperation>,
+}
+
+fn read_log(state: &Arc<AppState>, profile_id: &str) -> Result<Vec<StoredRecord>> {
+    let blob_storage = state.get_vault_blob_storage()?;
+    if !blob_storage.exists(profile_id, VaultBlobKind::SyncLog)? {
+        return Ok(Vec::new());
+    }
+    let bytes = blob_storage.read_blob(profile_id, VaultBlobKind::SyncLog)?;
+    serde_json::from_slice(&bytes).map_err(|_| ErrorCodeString::new("SYNC_LOG_DESERIALIZE_FAILED"))
+}
+
+fn write_log(state: &Arc<AppState>, profile_id: &str, records: &[StoredRecord]) -> Result<()> {
+    let blob_storage = state.get_vault_blob_storage()?;
+    let bytes = serde_json::to_vec(records).map_err(|_| ErrorCodeString::new("SYNC_LOG_SERIALIZE_FAILED"))?;
+    blob_storage.write_blob(profile_id, VaultBlobKind::SyncLog, &bytes)
+}
+
+fn latest_checkpoint(records: &[StoredRecord]) -> Option<&StoredRecord> {
+    records
+        .iter()
+        .filter(|r| matches!(r.sealed, SealedRecord::Checkpoint(_)))
+        .max_by(|a, b| a.hlc.cmp(&b.hlc))
+}
+
+/// Appends `op` to `profile_id`'s sync log under a clock advanced past the
+/// highest one already recorded, then folds the log down to a fresh
+/// checkpoint once `CHECKPOINT_EVERY_N_OPS` operations have accumulated
+/// since the last one.
+///
+/// Not yet called from `datacards_service`/`folders_service`/
+/// `attachments_service`, or wherever settings are persisted — each should
+/// call this right after its own mutation commits, the same way they
+/// already call `oplog_service::record`, once this log is wired into the
+/// sync flow end to end.
+pub fn append_operation(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    device_id: &str,
+    key: &[u8; 32],
+    now_millis: i64,
+    op: &SyncOperation,
+) -> Result<Hlc> {
+    let mut records = read_log(state, profile_id)?;
+
+    let last_hlc = records.iter().map(|r| r.hlc.clone()).max().unwrap_or_else(Hlc::epoch);
+    let hlc = last_hlc.next(device_id, now_millis);
+
+    let op_bytes = serde_json::to_vec(op).map_err(|_| ErrorCodeString::new("SYNC_LOG_SERIALIZE_FAILED"))?;
+    let sealed = cipher::encrypt_sync_log_entry(profile_id, key, &op_bytes)?;
+    records.push(StoredRecord {
+        hlc: hlc.clone(),
+        sealed: SealedRecord::Operation(sealed),
+    });
+
+    maybe_checkpoint(state, profile_id, key, &mut records)?;
+
+    write_log(state, profile_id, &records)?;
+    Ok(hlc)
+}
+
+/// Takes a full checkpoint once enough operations have accumulated since
+/// the last one, then compacts: every record at or before the new
+/// checkpoint's clock is dropped, since a device replaying forward from
+/// this checkpoint will never need them again. A no-op, left for the next
+/// `append_operation` call to retry, if the vault has never been persisted
+/// for this profile yet.
+fn maybe_checkpoint(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    key: &[u8; 32],
+    records: &mut Vec<StoredRecord>,
+) -> Result<()> {
+    let since_checkpoint = match latest_checkpoint(records) {
+        Some(checkpoint) => records.iter().filter(|r| r.hlc > checkpoint.hlc).count(),
+        None => records.len(),
+    };
+    if since_checkpoint < CHECKPOINT_EVERY_N_OPS {
+        return Ok(());
+    }
+
+    let blob_storage = state.get_vault_blob_storage()?;
+    if !blob_storage.exists(profile_id, VaultBlobKind::VaultDb)? {
+        return Ok(());
+    }
+    let vault_blob = blob_storage.read_blob(profile_id, VaultBlobKind::VaultDb)?;
+
+    let checkpoint_hlc = records
+        .last()
+        .map(|r| r.hlc.clone())
+        .unwrap_or_else(Hlc::epoch);
+    let payload = CheckpointPayload { vault_blob };
+    let payload_bytes =
+        serde_json::to_vec(&payload).map_err(|_| ErrorCodeString::new("SYNC_LOG_SERIALIZE_FAILED"))?;
+    let sealed = cipher::encrypt_sync_log_entry(profile_id, key, &payload_bytes)?;
+
+    records.retain(|r| r.hlc > checkpoint_hlc);
+    records.push(StoredRecord {
+        hlc: checkpoint_hlc,
+        sealed: SealedRecord::Checkpoint(sealed),
+    });
+    Ok(())
+}
+
+/// Locates the newest checkpoint in `profile_id`'s sync log, decrypts it,
+/// and decrypts every operation recorded after it in ascending clock order
+/// — the order a caller must apply them in for two merged logs to converge
+/// on the same state. Returns `Ok(None)` if the log has no checkpoint yet
+/// (e.g. this profile has never accumulated `CHECKPOINT_EVERY_N_OPS`
+/// operations).
+pub fn load_since_checkpoint(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    key: &[u8; 32],
+) -> Result<Option<ReplayState>> {
+    let records = read_log(state, profile_id)?;
+    let Some(checkpoint_record) = latest_checkpoint(&records) else {
+        return Ok(None);
+    };
+    let checkpoint_hlc = checkpoint_record.hlc.clone();
+    let SealedRecord::Checkpoint(sealed_checkpoint) = &checkpoint_record.sealed else {
+        unreachable!("latest_checkpoint only returns Checkpoint records");
+    };
+    let payload_bytes = cipher::decrypt_sync_log_entry(profile_id, key, sealed_checkpoint)?;
+    let payload: CheckpointPayload = serde_json::from_slice(&payload_bytes)
+        .map_err(|_| ErrorCodeString::new("SYNC_LOG_DESERIALIZE_FAILED"))?;
+
+    let mut operations = Vec::new();
+    for record in &records {
+        if record.hlc <= checkpoint_hlc {
+            continue;
+        }
+        if let SealedRecord::Operation(sealed_op) = &record.sealed {
+            let op_bytes = cipher::decrypt_sync_log_entry(profile_id, key, sealed_op)?;
+            let op: SyncOperation = serde_json::from_slice(&op_bytes)
+                .map_err(|_| ErrorCodeString::new("SYNC_LOG_DESERIALIZE_FAILED"))?;
+            operations.push(ReplayOperation { hlc: record.hlc.clone(), op });
+        }
+    }
+    operations.sort_by(|a, b| a.hlc.cmp(&b.hlc));
+
+    Ok(Some(ReplayState {
+        checkpoint_hlc,
+        checkpoint_vault_blob: payload.vault_blob,
+        operations,
+    }))
+}
@@ -1,16 +1,59 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Error, Serialize, Clone)]
+/// One link in an `ErrorCodeString`'s cause chain: the lower-level failure
+/// `code` was mapped from (an `io::ErrorKind`, a serde error, ...) plus a
+/// human-readable message, kept purely for diagnostics. Callers must still
+/// match on the top-level `code` — `source` is never meant to be matched
+/// on, only logged or shown to a developer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorLink {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Error, Serialize, Deserialize, Clone, Default)]
 #[error("{code}")]
 pub struct ErrorCodeString {
     pub code: String,
+    /// The chain of lower-level failures `code` was mapped from, oldest
+    /// cause first. Empty for the common `ErrorCodeString::new` case, so
+    /// every existing call site's wire format is unchanged; `wrap_io`/
+    /// `wrap_json` are the only way to populate it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source: Vec<ErrorLink>,
 }
 
 impl ErrorCodeString {
     pub fn new(code: &str) -> Self {
         Self {
             code: code.to_string(),
+            source: Vec::new(),
+        }
+    }
+
+    /// Builds an `ErrorCodeString` that also records `cause` (an
+    /// `io::Error`) as the first link in its source chain, so a caller
+    /// collapsing e.g. a missing-file vs. a permission error behind one
+    /// `code` doesn't lose which one actually happened.
+    pub fn wrap_io(code: &str, cause: &std::io::Error) -> Self {
+        Self {
+            code: code.to_string(),
+            source: vec![ErrorLink {
+                code: format!("{:?}", cause.kind()),
+                message: cause.to_string(),
+            }],
+        }
+    }
+
+    /// Same as `wrap_io`, for a `serde_json::Error` cause.
+    pub fn wrap_json(code: &str, cause: &serde_json::Error) -> Self {
+        Self {
+            code: code.to_string(),
+            source: vec![ErrorLink {
+                code: "SERDE_JSON".to_string(),
+                message: cause.to_string(),
+            }],
         }
     }
 }
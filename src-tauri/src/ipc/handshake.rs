@@ -0,0 +1,76 @@
+//! x25519 + AEAD handshake for the native-messaging <-> app loopback bridge
+//! (see `ipc::server`). `forward_to_app` used to send `BridgeRequest`s as
+//! plaintext JSON, so any other local process on the loopback interface
+//! could read the bearer token and vault payloads off the wire. This reuses
+//! the same ephemeral-key-then-AES-GCM pattern `data::crypto::sharing`
+//! already uses for cross-profile item sharing, just per-*connection*
+//! instead of per-item: the app publishes a long-term x25519 public key in
+//! `native-host.json`, the host generates a one-time keypair for each
+//! connection, and the resulting shared key seals every frame on that
+//! connection.
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::data::crypto::sharing;
+use crate::error::Result;
+
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// Distinct from `data::crypto::sharing`'s own HKDF info string: the
+/// `pm-native-host` binary can't depend on this crate to reuse that
+/// function, so it re-derives the same key by hand from this constant
+/// instead — keep the two in sync if either changes.
+const HKDF_INFO: &[u8] = b"pm-native-bridge-v1";
+
+/// Binds sealed bridge frames to this protocol so a ciphertext produced
+/// elsewhere (e.g. a sharing envelope) can't be replayed onto this channel.
+const FRAME_AAD: &[u8] = b"pm-native-bridge-frame-v1";
+
+fn derive_bridge_key(our_secret: &StaticSecret, their_public: &PublicKey) -> [u8; 32] {
+    let shared_secret = our_secret.diffie_hellman(their_public);
+    let hk = Hkdf::<Sha256>::new(None, shared_secret.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(HKDF_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+pub fn generate_server_identity() -> StaticSecret {
+    sharing::generate_identity()
+}
+
+pub fn encode_public_key(secret: &StaticSecret) -> String {
+    general_purpose::STANDARD.encode(sharing::public_key_of(secret).as_bytes())
+}
+
+/// The sealed channel for one connection, keyed by the x25519 shared secret
+/// derived from the server's long-term key and the peer's one-time key.
+/// Every frame after the handshake's initial key exchange is sealed with
+/// this same key.
+pub struct SealedChannel {
+    key: [u8; 32],
+}
+
+impl SealedChannel {
+    pub fn from_dh(our_secret: &StaticSecret, their_public: &PublicKey) -> Self {
+        Self {
+            key: derive_bridge_key(our_secret, their_public),
+        }
+    }
+
+    /// Seals `plaintext` (a serialized `BridgeRequest`/`BridgeResponse`)
+    /// into `nonce || ciphertext`, ready to hand to `write_frame`.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        sharing::encrypt_envelope(&self.key, FRAME_AAD, plaintext)
+    }
+
+    /// Reverses `seal`; fails if the AEAD tag doesn't verify, which also
+    /// covers a frame sealed under the wrong connection's key.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        sharing::decrypt_envelope(&self.key, FRAME_AAD, sealed)
+    }
+}
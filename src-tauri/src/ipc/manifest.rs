@@ -0,0 +1,239 @@
+//! Native messaging host manifest — the file that tells a browser where
+//! the `pm-native-host` binary lives and which extension is allowed to
+//! launch it. Distinct from `registry::NativeHostIpcInfo` (which is *our*
+//! app telling the host binary where to connect); this file is what lets
+//! the browser find and start the host binary in the first place.
+//!
+//! Chrome-family browsers and Firefox both read a JSON manifest from a
+//! fixed, per-browser directory, keyed by `name`; the manifest's `path`
+//! must be an absolute path to the host executable. Chrome-family browsers
+//! authorize callers by extension id (`allowed_origins`); Firefox
+//! authorizes by extension id too, but under `allowed_extensions` and a
+//! different id shape, so the two manifests aren't quite interchangeable.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::{ErrorCodeString, Result};
+
+/// Registered with the browser as this host's name; must match whatever
+/// the extension passes to `chrome.runtime.connectNative`/`browser.runtime.connectNative`.
+pub const HOST_NAME: &str = "com.passwordmanager.native_host";
+
+#[derive(Debug, Serialize)]
+struct ChromeManifest<'a> {
+    name: &'a str,
+    description: &'a str,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FirefoxManifest<'a> {
+    name: &'a str,
+    description: &'a str,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    allowed_extensions: Vec<String>,
+}
+
+fn write_manifest_json<T: Serialize>(dir: &Path, manifest: &T) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).map_err(|_| ErrorCodeString::new("MANIFEST_WRITE_FAILED"))?;
+    let path = dir.join(format!("{HOST_NAME}.json"));
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|_| ErrorCodeString::new("MANIFEST_SERIALIZE_FAILED"))?;
+    std::fs::write(&path, json).map_err(|_| ErrorCodeString::new("MANIFEST_WRITE_FAILED"))?;
+    Ok(path)
+}
+
+/// Directories Chrome-family browsers (Chrome, Chromium, Edge, Brave) scan
+/// for native messaging host manifests on this platform.
+fn chrome_family_dirs() -> Vec<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        let Some(home) = dirs_home() else { return Vec::new() };
+        vec![
+            home.join(".config/google-chrome/NativeMessagingHosts"),
+            home.join(".config/chromium/NativeMessagingHosts"),
+            home.join(".config/microsoft-edge/NativeMessagingHosts"),
+        ]
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let Some(home) = dirs_home() else { return Vec::new() };
+        vec![
+            home.join("Library/Application Support/Google/Chrome/NativeMessagingHosts"),
+            home.join("Library/Application Support/Chromium/NativeMessagingHosts"),
+            home.join("Library/Application Support/Microsoft Edge/NativeMessagingHosts"),
+        ]
+    }
+    #[cfg(windows)]
+    {
+        Vec::new() // Windows locates the manifest via the registry instead; see `register_windows`.
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        Vec::new()
+    }
+}
+
+/// Directory Firefox scans for native messaging host manifests on this
+/// platform (Windows again goes through the registry).
+#[cfg(not(windows))]
+fn firefox_dir() -> Option<PathBuf> {
+    let home = dirs_home()?;
+    #[cfg(target_os = "linux")]
+    return Some(home.join(".mozilla/native-messaging-hosts"));
+    #[cfg(target_os = "macos")]
+    return Some(home.join("Library/Application Support/Mozilla/NativeMessagingHosts"));
+}
+
+#[cfg(not(windows))]
+fn dirs_home() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// Writes the Chrome-family and Firefox manifests pointing at
+/// `host_binary_path`, scoped to `extension_id` (a `chrome-extension://…`
+/// origin for Chrome-family browsers, a Firefox extension id/UUID for
+/// Firefox), and returns every path actually written. Best-effort: a
+/// browser that isn't installed just means its directory never gets
+/// created, which is fine — the manifest only matters once that browser
+/// goes looking for it.
+#[cfg(not(windows))]
+pub fn install_native_messaging_manifests(
+    host_binary_path: &Path,
+    chrome_extension_id: &str,
+    firefox_extension_id: &str,
+) -> Result<Vec<PathBuf>> {
+    let path_str = host_binary_path.to_string_lossy().to_string();
+    let mut written = Vec::new();
+
+    let chrome_manifest = ChromeManifest {
+        name: HOST_NAME,
+        description: "Password Manager native messaging host",
+        path: path_str.clone(),
+        kind: "stdio",
+        allowed_origins: vec![format!("chrome-extension://{chrome_extension_id}/")],
+    };
+    for dir in chrome_family_dirs() {
+        written.push(write_manifest_json(&dir, &chrome_manifest)?);
+    }
+
+    if let Some(dir) = firefox_dir() {
+        let firefox_manifest = FirefoxManifest {
+            name: HOST_NAME,
+            description: "Password Manager native messaging host",
+            path: path_str,
+            kind: "stdio",
+            allowed_extensions: vec![firefox_extension_id.to_string()],
+        };
+        written.push(write_manifest_json(&dir, &firefox_manifest)?);
+    }
+
+    Ok(written)
+}
+
+/// Windows has no per-user manifest directory to drop a file in: instead,
+/// a registry value under `HKEY_CURRENT_USER` names the manifest file's
+/// path, and the manifest itself can live anywhere (we keep it next to the
+/// host binary). `browser_registry_subkey` is e.g.
+/// `r"Software\Google\Chrome\NativeMessagingHosts"` for Chrome or
+/// `r"Software\Mozilla\NativeMessagingHosts"` for Firefox.
+#[cfg(windows)]
+pub fn register_windows_native_messaging_host(
+    host_binary_path: &Path,
+    browser_registry_subkey: &str,
+    chrome_extension_id: Option<&str>,
+    firefox_extension_id: Option<&str>,
+) -> Result<PathBuf> {
+    let dir = host_binary_path
+        .parent()
+        .ok_or_else(|| ErrorCodeString::new("MANIFEST_WRITE_FAILED"))?;
+    let manifest_path = if let Some(chrome_extension_id) = chrome_extension_id {
+        let manifest = ChromeManifest {
+            name: HOST_NAME,
+            description: "Password Manager native messaging host",
+            path: host_binary_path.to_string_lossy().to_string(),
+            kind: "stdio",
+            allowed_origins: vec![format!("chrome-extension://{chrome_extension_id}/")],
+        };
+        write_manifest_json(dir, &manifest)?
+    } else {
+        let firefox_extension_id =
+            firefox_extension_id.ok_or_else(|| ErrorCodeString::new("MANIFEST_WRITE_FAILED"))?;
+        let manifest = FirefoxManifest {
+            name: HOST_NAME,
+            description: "Password Manager native messaging host",
+            path: host_binary_path.to_string_lossy().to_string(),
+            kind: "stdio",
+            allowed_extensions: vec![firefox_extension_id.to_string()],
+        };
+        write_manifest_json(dir, &manifest)?
+    };
+
+    windows_registry::set_manifest_path(browser_registry_subkey, &manifest_path)?;
+    Ok(manifest_path)
+}
+
+#[cfg(windows)]
+mod windows_registry {
+    use super::PathBuf;
+    use crate::error::{ErrorCodeString, Result};
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::System::Registry::{
+        RegCloseKey, RegCreateKeyExW, RegSetValueExW, HKEY_CURRENT_USER, KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ,
+    };
+
+    fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    /// Writes `HKEY_CURRENT_USER\<subkey>\<HOST_NAME>` (default value) =
+    /// the manifest's absolute path, creating the key if needed.
+    pub(super) fn set_manifest_path(subkey: &str, manifest_path: &PathBuf) -> Result<()> {
+        let full_subkey = format!("{subkey}\\{}", super::HOST_NAME);
+        let subkey_wide = wide(&full_subkey);
+        let mut hkey = std::ptr::null_mut();
+
+        unsafe {
+            let status = RegCreateKeyExW(
+                HKEY_CURRENT_USER,
+                subkey_wide.as_ptr(),
+                0,
+                std::ptr::null_mut(),
+                REG_OPTION_NON_VOLATILE,
+                KEY_WRITE,
+                std::ptr::null_mut(),
+                &mut hkey,
+                std::ptr::null_mut(),
+            );
+            if status != 0 {
+                return Err(ErrorCodeString::new("REGISTRY_WRITE_FAILED"));
+            }
+
+            let value = wide(&manifest_path.to_string_lossy());
+            let value_bytes =
+                std::slice::from_raw_parts(value.as_ptr() as *const u8, value.len() * 2);
+            let status = RegSetValueExW(
+                hkey,
+                std::ptr::null(),
+                0,
+                REG_SZ,
+                value_bytes.as_ptr(),
+                value_bytes.len() as u32,
+            );
+            RegCloseKey(hkey);
+
+            if status != 0 {
+                return Err(ErrorCodeString::new("REGISTRY_WRITE_FAILED"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -5,7 +5,9 @@ use crate::data::fs::atomic_write::write_atomic;
 use crate::error::{ErrorCodeString, Result};
 
 const IPC_INFO_FILE: &str = "native-host.json";
-const IPC_INFO_SCHEMA_VERSION: u8 = 1;
+// Bumped for the addition of `server_public_key`: a host built against
+// schema 1 has no way to start the x25519 handshake, so it must not try.
+const IPC_INFO_SCHEMA_VERSION: u8 = 2;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NativeHostIpcInfo {
@@ -13,6 +15,11 @@ pub struct NativeHostIpcInfo {
     pub port: u16,
     pub token: String,
     pub created_at_ms: u128,
+    /// Base64 (standard, padded) x25519 public key the app will use for
+    /// every connection's handshake. Long-term relative to the per-connection
+    /// ephemeral key the host generates; it's only as long-lived as this
+    /// app process, and is reissued (with a new matching secret) on restart.
+    pub server_public_key: String,
 }
 
 fn primary_ipc_info_path(app_dir: &Path) -> PathBuf {
@@ -3,19 +3,111 @@ use serde_json::Value;
 use std::borrow::Cow;
 use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use uuid::Uuid;
+use x25519_dalek::StaticSecret;
 
 use crate::app_state::AppState;
-use crate::error::{ErrorCodeString, Result};
+use crate::data::crypto::totp;
+use crate::error::{ErrorCodeString, ErrorLink, Result};
+use crate::ipc::handshake::{self, SealedChannel};
 use crate::ipc::registry::{remove_ipc_info, write_ipc_info, NativeHostIpcInfo};
 use crate::services::{datacards_service, profiles_service, security_service};
+use crate::types::{CreateDataCardInput, UpdateDataCardInput, UriMatchMode};
 
 const MAX_FRAME_LEN: usize = 1024 * 1024; // 1MB
 
+/// How often the token is rotated unconditionally, invalidating every
+/// session the extension had cached. `native-host.json` is rewritten each
+/// time so the next request from the extension (or `pm-native-host`) picks
+/// the new one up.
+const TOKEN_ROTATE_INTERVAL_MS: u64 = 15 * 60 * 1000;
+
+/// Safety-net expiry on top of the rotation interval: if the rotation
+/// thread ever stalls (system sleep, starvation), a token this old is
+/// rejected outright rather than accepted indefinitely.
+const TOKEN_MAX_AGE_MS: u128 = 20 * 60 * 1000;
+
+/// The live token plus the issue time it was rotated in with, shared
+/// between the rotation thread and every connection handler thread. Also
+/// carries the bridge's long-term x25519 identity: unlike the token, the
+/// identity doesn't rotate on a timer — it's published once per process
+/// launch, and each *connection* layers its own ephemeral key on top of it
+/// (see `ipc::handshake`).
+struct SharedToken {
+    app_dir: std::path::PathBuf,
+    port: u16,
+    server_secret: StaticSecret,
+    server_public_key: String,
+    current: Mutex<(String, u128)>,
+}
+
+impl SharedToken {
+    /// Starts with an empty, already-expired token so no request can
+    /// authenticate until the first `rotate()` call fills it in.
+    fn new(app_dir: std::path::PathBuf, port: u16) -> Self {
+        let server_secret = handshake::generate_server_identity();
+        let server_public_key = handshake::encode_public_key(&server_secret);
+        Self {
+            app_dir,
+            port,
+            server_secret,
+            server_public_key,
+            current: Mutex::new((String::new(), 0)),
+        }
+    }
+
+    fn snapshot(&self) -> Result<(String, u128)> {
+        Ok(self
+            .current
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
+            .clone())
+    }
+
+    /// Generates a fresh token, persists it to `native-host.json`, and only
+    /// then swaps it in — so a crash mid-write leaves the old (still valid)
+    /// token in place rather than an info file nobody can use yet.
+    fn rotate(&self) -> Result<()> {
+        let token = Uuid::new_v4().to_string();
+        let created_at_ms = now_ms();
+        let info = NativeHostIpcInfo {
+            schema_version: 2,
+            port: self.port,
+            token: token.clone(),
+            created_at_ms,
+            server_public_key: self.server_public_key.clone(),
+        };
+        write_ipc_info(&self.app_dir, &info)?;
+
+        let mut slot = self
+            .current
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        *slot = (token, created_at_ms);
+        Ok(())
+    }
+}
+
+/// Byte-length-revealing but not content-revealing: compares in time
+/// proportional only to the shorter input, never short-circuiting once
+/// both slices are the same length. Tokens are fixed-length UUIDs, so this
+/// is enough to keep a timing side channel from narrowing down the token
+/// byte-by-byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff: u8 = 0;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BridgeRequest {
     pub id: String,
@@ -28,6 +120,12 @@ pub struct BridgeRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BridgeError {
     pub code: String,
+    /// The requesting error's `ErrorCodeString::source` chain, carried
+    /// across the bridge unchanged so the extension/logs can tell apart,
+    /// say, a missing file from a permission error behind the same `code`.
+    /// Empty (and omitted from the wire JSON) for the common case.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source: Vec<ErrorLink>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -60,6 +158,33 @@ struct GetCredentialPayload {
     credential_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct SaveCredentialPayload {
+    #[serde(rename = "profileId")]
+    profile_id: String,
+    origin: String,
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateCredentialPayload {
+    #[serde(rename = "profileId")]
+    profile_id: String,
+    origin: String,
+    #[serde(rename = "credentialId")]
+    credential_id: String,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SaveCredentialResult {
+    id: String,
+}
+
 #[derive(Debug, Serialize)]
 struct ListProfilesResult {
     profiles: Vec<crate::types::ProfileMeta>,
@@ -86,6 +211,29 @@ struct ListCredentialsResult {
 struct CredentialForFillResult {
     username: String,
     password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    totp: Option<TotpResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct TotpResult {
+    code: String,
+    #[serde(rename = "secondsRemaining")]
+    seconds_remaining: u64,
+}
+
+/// Computes the current TOTP code for a card, if it has a `totp_uri`
+/// enrolled. A malformed or unparseable URI is treated the same as no
+/// TOTP at all — fill still proceeds with just username/password.
+fn current_totp(totp_uri: Option<&str>) -> Option<TotpResult> {
+    let uri = totp_uri?;
+    let params = totp::parse_otpauth_uri(uri).ok()?;
+    let unix_seconds = now_ms() as u64 / 1000;
+    let code = totp::generate(&params, unix_seconds).ok()?;
+    Some(TotpResult {
+        code: code.code,
+        seconds_remaining: code.seconds_remaining,
+    })
 }
 
 fn now_ms() -> u128 {
@@ -233,21 +381,128 @@ fn is_same_or_subdomain(host: &str, base: &str) -> bool {
     host.as_bytes().get(dot_pos) == Some(&b'.') && host.ends_with(base)
 }
 
-fn origin_matches_url(card_url: &str, requested_origin: &str) -> bool {
-    let Ok(card_origin) = parse_origin(card_url) else { return false; };
-    if card_origin == requested_origin {
-        return true;
+/// Caps how large a `RegularExpression`-mode `url` can be before we even try
+/// to compile it, and how much memory the compiled program may use — so a
+/// pathologically large pattern on one card can't stall or blow up memory
+/// for every `list_credentials` call. The `regex` crate itself is immune to
+/// catastrophic backtracking (it compiles to a fixed-size automaton rather
+/// than backtracking), so these two limits are the whole guard.
+const MAX_URI_MATCH_REGEX_LEN: usize = 512;
+const URI_MATCH_REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+/// Lowercases scheme/host and drops a default port and trailing slash, the
+/// same normalization `parse_origin` applies to the origin, but keeping the
+/// path/query/fragment instead of discarding it — used by `Exact` mode,
+/// which (unlike `Domain`/`Host`) cares about the whole URL, not just where
+/// it's hosted.
+fn normalize_full_url(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
     }
+    let work: Cow<'_, str> = if trimmed.contains("://") {
+        Cow::Borrowed(trimmed)
+    } else if trimmed.starts_with("//") {
+        Cow::Owned(format!("https:{}", trimmed))
+    } else {
+        Cow::Owned(format!("https://{}", trimmed))
+    };
 
-    let Some(req) = origin_parts(requested_origin) else { return false; };
-    let Some(card) = origin_parts(&card_origin) else { return false; };
-    if req.scheme != card.scheme || req.port != card.port {
-        return false;
+    let scheme_split = work.find("://")?;
+    let scheme = work[..scheme_split].to_ascii_lowercase();
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+
+    let rest = &work[(scheme_split + 3)..];
+    let path_split = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let host_port = &rest[..path_split];
+    let remainder = rest[path_split..].trim_end_matches('/');
+
+    let (host, port_opt) = match host_port.rsplit_once(':') {
+        Some((h, p)) if !h.is_empty() && p.chars().all(|c| c.is_ascii_digit()) => {
+            let port: u16 = p.parse().ok()?;
+            (h, Some(port))
+        }
+        _ => (host_port, None),
+    };
+    if host.is_empty() {
+        return None;
     }
+    let host = host.to_ascii_lowercase();
+    let default_port = if scheme == "http" { 80 } else { 443 };
+
+    let authority = match port_opt {
+        Some(port) if port != default_port => format!("{host}:{port}"),
+        _ => host,
+    };
+    Some(format!("{scheme}://{authority}{remainder}"))
+}
+
+/// Compiles `pattern` as a regex under the size/length guards, returning
+/// `None` (never a match) rather than propagating the error — a bad pattern
+/// on one card should make that card stop being offered, not break
+/// `list_credentials` for every other card.
+fn compile_uri_match_regex(pattern: &str) -> Option<regex::Regex> {
+    if pattern.is_empty() || pattern.len() > MAX_URI_MATCH_REGEX_LEN {
+        return None;
+    }
+    regex::RegexBuilder::new(pattern)
+        .size_limit(URI_MATCH_REGEX_SIZE_LIMIT)
+        .build()
+        .ok()
+}
+
+/// Dispatches on the card's `UriMatchMode` instead of always applying the
+/// base-domain/subdomain heuristic. `requested_raw` is the page URL as the
+/// extension sent it (before `parse_origin` reduces it to just an origin);
+/// `StartsWith`/`Exact`/`RegularExpression` match against that full string,
+/// while `Domain`/`Host` only ever cared about where it's hosted.
+fn origin_matches_url(
+    card_url: &str,
+    requested_origin: &str,
+    requested_raw: &str,
+    mode: UriMatchMode,
+) -> bool {
+    match mode {
+        UriMatchMode::Never => false,
+        UriMatchMode::Domain => {
+            let Ok(card_origin) = parse_origin(card_url) else { return false; };
+            if card_origin == requested_origin {
+                return true;
+            }
+
+            let Some(req) = origin_parts(requested_origin) else { return false; };
+            let Some(card) = origin_parts(&card_origin) else { return false; };
+            if req.scheme != card.scheme || req.port != card.port {
+                return false;
+            }
 
-    let req_host = strip_www(req.host);
-    let card_base = strip_www(card.host);
-    is_same_or_subdomain(req_host, card_base)
+            let req_host = strip_www(req.host);
+            let card_base = strip_www(card.host);
+            is_same_or_subdomain(req_host, card_base)
+        }
+        UriMatchMode::Host => {
+            let Ok(card_origin) = parse_origin(card_url) else { return false; };
+            card_origin == requested_origin
+        }
+        UriMatchMode::StartsWith => {
+            let stored = card_url.trim();
+            !stored.is_empty() && requested_raw.starts_with(stored)
+        }
+        UriMatchMode::Exact => {
+            let (Some(stored), Some(requested)) =
+                (normalize_full_url(card_url), normalize_full_url(requested_raw))
+            else {
+                return false;
+            };
+            stored == requested
+        }
+        UriMatchMode::RegularExpression => {
+            let Some(re) = compile_uri_match_regex(card_url) else { return false; };
+            re.is_match(requested_raw)
+        }
+    }
 }
 
 fn datacard_origin(url: &str) -> Option<String> {
@@ -261,6 +516,19 @@ fn error_response(id: String, code: &str) -> BridgeResponse {
         result: None,
         error: Some(BridgeError {
             code: code.to_string(),
+            source: Vec::new(),
+        }),
+    }
+}
+
+fn error_response_from_err(id: String, err: &ErrorCodeString) -> BridgeResponse {
+    BridgeResponse {
+        id,
+        ok: false,
+        result: None,
+        error: Some(BridgeError {
+            code: err.code.clone(),
+            source: err.source.clone(),
         }),
     }
 }
@@ -274,8 +542,15 @@ fn ok_response(id: String, value: Value) -> BridgeResponse {
     }
 }
 
-fn handle_request(state: &Arc<AppState>, shared_token: &str, req: BridgeRequest) -> BridgeResponse {
-    if req.token != shared_token {
+fn handle_request(state: &Arc<AppState>, shared: &SharedToken, req: BridgeRequest) -> BridgeResponse {
+    let (current_token, created_at_ms) = match shared.snapshot() {
+        Ok(v) => v,
+        Err(_) => return error_response(req.id, "STATE_UNAVAILABLE"),
+    };
+    if now_ms().saturating_sub(created_at_ms) > TOKEN_MAX_AGE_MS {
+        return error_response(req.id, "TOKEN_EXPIRED");
+    }
+    if !constant_time_eq(req.token.as_bytes(), current_token.as_bytes()) {
         return error_response(req.id, "UNAUTHORIZED");
     }
 
@@ -308,6 +583,15 @@ fn handle_request(state: &Arc<AppState>, shared_token: &str, req: BridgeRequest)
                 Ok(serde_json::to_value(StatusResult { locked })
                     .map_err(|_| ErrorCodeString::new("IPC_SERIALIZE_FAILED"))?)
             }
+            "unlock_from_keychain" => {
+                let payload: ProfileIdPayload = serde_json::from_value(req.payload)
+                    .map_err(|_| ErrorCodeString::new("IPC_BAD_PAYLOAD"))?;
+
+                security_service::unlock_from_keychain(&payload.profile_id, state)?;
+
+                Ok(serde_json::to_value(StatusResult { locked: false })
+                    .map_err(|_| ErrorCodeString::new("IPC_SERIALIZE_FAILED"))?)
+            }
             "list_credentials" => {
                 let payload: ListCredentialsPayload = serde_json::from_value(req.payload)
                     .map_err(|_| ErrorCodeString::new("IPC_BAD_PAYLOAD"))?;
@@ -325,7 +609,9 @@ fn handle_request(state: &Arc<AppState>, shared_token: &str, req: BridgeRequest)
                 let mut items: Vec<CredentialListItem> = Vec::new();
                 for row in rows {
                     let Some(url) = row.url.as_deref() else { continue };
-                    if !origin_matches_url(url, &origin) { continue; }
+                    if !origin_matches_url(url, &origin, &payload.origin, row.uri_match) {
+                        continue;
+                    }
                     let username = row
                         .email
                         .clone()
@@ -360,7 +646,7 @@ fn handle_request(state: &Arc<AppState>, shared_token: &str, req: BridgeRequest)
                 let Some(url) = card.url.as_deref() else {
                     return Err(ErrorCodeString::new("CREDENTIAL_URL_MISSING"));
                 };
-                if !origin_matches_url(url, &origin) {
+                if !origin_matches_url(url, &origin, &payload.origin, card.uri_match) {
                     return Err(ErrorCodeString::new("ORIGIN_MISMATCH"));
                 }
 
@@ -378,7 +664,165 @@ fn handle_request(state: &Arc<AppState>, shared_token: &str, req: BridgeRequest)
                     return Err(ErrorCodeString::new("PASSWORD_MISSING"));
                 }
 
-                Ok(serde_json::to_value(CredentialForFillResult { username, password })
+                let totp = current_totp(card.totp_uri.as_deref());
+
+                Ok(
+                    serde_json::to_value(CredentialForFillResult { username, password, totp })
+                        .map_err(|_| ErrorCodeString::new("IPC_SERIALIZE_FAILED"))?,
+                )
+            }
+            "get_totp" => {
+                let payload: GetCredentialPayload = serde_json::from_value(req.payload)
+                    .map_err(|_| ErrorCodeString::new("IPC_BAD_PAYLOAD"))?;
+
+                let origin = parse_origin(&payload.origin)?;
+
+                let active = security_service::require_unlocked_active_profile(state)
+                    .map_err(|_| ErrorCodeString::new("LOCKED"))?;
+                if active.profile_id != payload.profile_id {
+                    return Err(ErrorCodeString::new("LOCKED"));
+                }
+
+                let card = datacards_service::get_datacard(payload.credential_id, state)?;
+                let Some(url) = card.url.as_deref() else {
+                    return Err(ErrorCodeString::new("CREDENTIAL_URL_MISSING"));
+                };
+                if !origin_matches_url(url, &origin, &payload.origin, card.uri_match) {
+                    return Err(ErrorCodeString::new("ORIGIN_MISMATCH"));
+                }
+
+                let totp = current_totp(card.totp_uri.as_deref())
+                    .ok_or_else(|| ErrorCodeString::new("TOTP_NOT_ENROLLED"))?;
+
+                Ok(serde_json::to_value(totp).map_err(|_| ErrorCodeString::new("IPC_SERIALIZE_FAILED"))?)
+            }
+            "save_credential" => {
+                let payload: SaveCredentialPayload = serde_json::from_value(req.payload)
+                    .map_err(|_| ErrorCodeString::new("IPC_BAD_PAYLOAD"))?;
+
+                let origin = parse_origin(&payload.origin)?;
+
+                let active = security_service::require_unlocked_active_profile(state)
+                    .map_err(|_| ErrorCodeString::new("LOCKED"))?;
+                if active.profile_id != payload.profile_id {
+                    return Err(ErrorCodeString::new("LOCKED"));
+                }
+
+                if payload.username.trim().is_empty() || payload.password.is_empty() {
+                    return Err(ErrorCodeString::new("IPC_BAD_PAYLOAD"));
+                }
+
+                // A page the extension already has a saved login for
+                // should update that card instead of piling up a
+                // duplicate — same origin match the read path already
+                // uses, narrowed to the row whose stored username is the
+                // one just submitted.
+                let existing_id = datacards_service::list_datacards_summary(state)?
+                    .into_iter()
+                    .find(|row| {
+                        row.url
+                            .as_deref()
+                            .map(|url| origin_matches_url(url, &origin, &payload.origin, row.uri_match))
+                            .unwrap_or(false)
+                            && row.email.as_deref().or(row.username.as_deref()) == Some(payload.username.as_str())
+                    })
+                    .map(|row| row.id);
+
+                let id = if let Some(existing_id) = existing_id {
+                    let card = datacards_service::get_datacard(existing_id, state)?;
+                    let update_input = UpdateDataCardInput {
+                        id: card.id.clone(),
+                        title: card.title,
+                        url: card.url,
+                        email: card.email,
+                        username: Some(payload.username),
+                        mobile_phone: card.mobile_phone,
+                        note: card.note,
+                        tags: card.tags,
+                        password: Some(payload.password),
+                        bank_card: card.bank_card,
+                        custom_fields: card.custom_fields,
+                        folder_id: card.folder_id,
+                        uri_match: card.uri_match,
+                        totp_uri: card.totp_uri,
+                    };
+                    datacards_service::update_datacard(update_input, state)?;
+                    card.id
+                } else {
+                    let input = CreateDataCardInput {
+                        title: origin.clone(),
+                        url: Some(origin),
+                        email: None,
+                        username: Some(payload.username),
+                        mobile_phone: None,
+                        note: None,
+                        tags: Vec::new(),
+                        password: Some(payload.password),
+                        bank_card: None,
+                        custom_fields: Vec::new(),
+                        folder_id: None,
+                        uri_match: Default::default(),
+                        totp_uri: None,
+                    };
+                    datacards_service::create_datacard(input, state)?.id
+                };
+
+                Ok(serde_json::to_value(SaveCredentialResult { id })
+                    .map_err(|_| ErrorCodeString::new("IPC_SERIALIZE_FAILED"))?)
+            }
+            "update_credential" => {
+                let payload: UpdateCredentialPayload = serde_json::from_value(req.payload)
+                    .map_err(|_| ErrorCodeString::new("IPC_BAD_PAYLOAD"))?;
+
+                let origin = parse_origin(&payload.origin)?;
+
+                let active = security_service::require_unlocked_active_profile(state)
+                    .map_err(|_| ErrorCodeString::new("LOCKED"))?;
+                if active.profile_id != payload.profile_id {
+                    return Err(ErrorCodeString::new("LOCKED"));
+                }
+
+                let mut card = datacards_service::get_datacard(payload.credential_id, state)?;
+                let Some(url) = card.url.as_deref() else {
+                    return Err(ErrorCodeString::new("CREDENTIAL_URL_MISSING"));
+                };
+                if !origin_matches_url(url, &origin, &payload.origin, card.uri_match) {
+                    return Err(ErrorCodeString::new("ORIGIN_MISMATCH"));
+                }
+
+                if let Some(username) = payload.username {
+                    if username.trim().is_empty() {
+                        return Err(ErrorCodeString::new("IPC_BAD_PAYLOAD"));
+                    }
+                    card.username = Some(username);
+                }
+                if let Some(password) = payload.password {
+                    if password.is_empty() {
+                        return Err(ErrorCodeString::new("IPC_BAD_PAYLOAD"));
+                    }
+                    card.password = Some(password);
+                }
+
+                let update_input = UpdateDataCardInput {
+                    id: card.id.clone(),
+                    title: card.title,
+                    url: card.url,
+                    email: card.email,
+                    username: card.username,
+                    mobile_phone: card.mobile_phone,
+                    note: card.note,
+                    tags: card.tags,
+                    password: card.password,
+                    bank_card: card.bank_card,
+                    custom_fields: card.custom_fields,
+                    folder_id: card.folder_id,
+                    uri_match: card.uri_match,
+                    totp_uri: card.totp_uri,
+                };
+                let id = update_input.id.clone();
+                datacards_service::update_datacard(update_input, state)?;
+
+                Ok(serde_json::to_value(SaveCredentialResult { id })
                     .map_err(|_| ErrorCodeString::new("IPC_SERIALIZE_FAILED"))?)
             }
             _ => Err(ErrorCodeString::new("IPC_UNKNOWN_REQUEST")),
@@ -387,17 +831,41 @@ fn handle_request(state: &Arc<AppState>, shared_token: &str, req: BridgeRequest)
 
     match result {
         Ok(val) => ok_response(req.id, val),
-        Err(err) => error_response(req.id, &err.code),
+        Err(err) => error_response_from_err(req.id, &err),
+    }
+}
+
+/// The handshake's only unencrypted step: the host's one-time x25519
+/// public key, sent as the very first frame on the connection, in the
+/// clear (it's a DH public key — only useful combined with our secret).
+/// Every frame after this one is sealed under the resulting shared key.
+fn perform_handshake(stream: &TcpStream, shared: &SharedToken) -> Option<SealedChannel> {
+    let frame = read_frame(stream).ok().flatten()?;
+    if frame.len() != handshake::PUBLIC_KEY_LEN {
+        return None;
     }
+    let mut key_bytes = [0u8; handshake::PUBLIC_KEY_LEN];
+    key_bytes.copy_from_slice(&frame);
+    let their_public = x25519_dalek::PublicKey::from(key_bytes);
+    Some(SealedChannel::from_dh(&shared.server_secret, &their_public))
 }
 
-fn handle_client(stream: TcpStream, state: Arc<AppState>, token: String) {
+fn handle_client(stream: TcpStream, state: Arc<AppState>, shared: Arc<SharedToken>) {
+    let Some(channel) = perform_handshake(&stream, &shared) else {
+        return;
+    };
+
     loop {
-        let frame = match read_frame(&stream) {
+        let sealed = match read_frame(&stream) {
             Ok(Some(bytes)) => bytes,
             Ok(None) => break,
             Err(_) => break,
         };
+        let Ok(frame) = channel.open(&sealed) else {
+            // Fails the AEAD tag (wrong key, tampered frame, or a replay
+            // from a different connection) — nothing to recover from.
+            break;
+        };
         let req: BridgeRequest = match serde_json::from_slice(&frame) {
             Ok(v) => v,
             Err(_) => {
@@ -406,10 +874,14 @@ fn handle_client(stream: TcpStream, state: Arc<AppState>, token: String) {
             }
         };
 
-        let resp = handle_request(&state, &token, req);
-        if let Ok(bytes) = serde_json::to_vec(&resp) {
-            let _ = write_frame(&stream, &bytes);
-        } else {
+        let resp = handle_request(&state, &shared, req);
+        let Ok(bytes) = serde_json::to_vec(&resp) else {
+            break;
+        };
+        let Ok(sealed_resp) = channel.seal(&bytes) else {
+            break;
+        };
+        if write_frame(&stream, &sealed_resp).is_err() {
             break;
         }
     }
@@ -433,8 +905,6 @@ pub fn start_native_bridge(state: Arc<AppState>) -> Result<()> {
         .map_err(|_| ErrorCodeString::new("IPC_BIND_FAILED"))?
         .port();
 
-    let token = Uuid::new_v4().to_string();
-
     let app_dir = {
         let sp = state
             .storage_paths
@@ -444,28 +914,30 @@ pub fn start_native_bridge(state: Arc<AppState>) -> Result<()> {
         sp.app_dir().to_path_buf()
     };
 
-    let info = NativeHostIpcInfo {
-        schema_version: 1,
-        port,
-        token: token.clone(),
-        created_at_ms: now_ms(),
-    };
-    let written_to = match write_ipc_info(&app_dir, &info) {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("[IPC] write_ipc_info failed: {}", e.code);
-            return Err(e);
-        }
-    };
-    eprintln!("[IPC] native-host.json written: {}", written_to.display());
+    // Rotating the token on every launch (not just reusing whatever is on
+    // disk) means a previous run's crashed-and-restarted extension session
+    // can't keep using a token from before this process existed.
+    let shared = Arc::new(SharedToken::new(app_dir, port));
+    shared.rotate()?;
+    eprintln!("[IPC] native-host.json written, listening on 127.0.0.1:{port}");
+
+    {
+        let shared = shared.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(TOKEN_ROTATE_INTERVAL_MS));
+            if let Err(e) = shared.rotate() {
+                eprintln!("[IPC] token rotation failed: {}", e.code);
+            }
+        });
+    }
 
     thread::spawn(move || {
         for incoming in listener.incoming() {
             match incoming {
                 Ok(stream) => {
                     let st = state.clone();
-                    let t = token.clone();
-                    thread::spawn(move || handle_client(stream, st, t));
+                    let shared = shared.clone();
+                    thread::spawn(move || handle_client(stream, st, shared));
                 }
                 Err(_) => break,
             }
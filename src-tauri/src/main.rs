@@ -4,10 +4,20 @@ mod app_state;
 mod commands;
 mod data {
     pub mod storage_paths;
+    pub mod backup {
+        pub mod chunk_store;
+        pub mod chunking;
+    }
     pub mod crypto {
         pub mod cipher;
+        pub mod encrypted_value;
         pub mod kdf;
         pub mod key_check;
+        pub mod master_key;
+        pub mod secret_store;
+        pub mod sharing;
+        pub mod stream_cipher;
+        pub mod totp;
     }
     pub mod profiles {
         pub mod paths;
@@ -16,11 +26,30 @@ mod data {
     pub mod settings {
         pub mod config;
     }
+    pub mod storage;
+    pub mod sqlite {
+        pub mod backend;
+        pub mod oplog;
+    }
+    pub mod sync;
 }
 mod error;
+mod ipc {
+    pub mod handshake;
+    pub mod manifest;
+    pub mod registry;
+    pub mod server;
+}
 mod services {
+    pub mod audit_log_service;
+    pub mod backup_job_service;
+    pub mod backup_service;
+    pub mod oplog_service;
+    pub mod password_exposure_service;
     pub mod profiles_service;
     pub mod security_service;
+    pub mod sharing_service;
+    pub mod sync_service;
 }
 mod types;
 
@@ -54,9 +83,16 @@ fn main() {
             set_active_profile,
             login_vault,
             lock_vault,
+            change_master_password,
+            rotate_master_password,
+            enable_keychain_unlock,
+            disable_keychain_unlock,
+            enroll_totp,
+            disable_totp,
             is_logged_in,
             auto_lock_cleanup,
-            health_check
+            health_check,
+            db_schema_version
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,73 +1,60 @@
 use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::sync::Arc;
 
 use base64::engine::general_purpose;
 use base64::Engine;
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
 use tauri::AppHandle;
 use tauri::Manager;
 use uuid::Uuid;
 
 use crate::app_state::AppState;
 use crate::data::crypto::cipher;
-use crate::data::profiles::paths::{attachment_file_path, attachments_preview_root};
+use crate::data::crypto::stream_cipher::FRAME_SIZE;
+use crate::data::profiles::paths::attachments_preview_root;
+use crate::data::storage::attachment_blob::AttachmentBlobStorage;
 use crate::data::sqlite::repo_impl;
 use crate::error::{ErrorCodeString, Result};
-use crate::types::{AttachmentMeta, AttachmentPreviewPayload};
+use crate::services::security_service;
+use crate::services::settings_service::get_settings;
+use crate::types::{AttachmentMeta, AttachmentPreviewPayload, AttachmentPurgeReport, AttachmentRangePayload};
 
-const MAX_ATTACHMENT_SIZE_BYTES: u64 = 50 * 1024 * 1024;
 const MAX_PREVIEW_BYTES: usize = 8 * 1024 * 1024;
+/// Bytes sniffed from the front of a new attachment to guess its MIME type
+/// (see `sniff_mime_type`) — independent of `stream_cipher::FRAME_SIZE`,
+/// since sniffing only ever needs a handful of leading bytes.
+const SNIFF_PREFIX_LEN: usize = 512;
 
 struct ActiveSession {
     state: Arc<AppState>,
     profile_id: String,
     vault_key: Option<[u8; 32]>,
+    blob_storage: Arc<dyn AttachmentBlobStorage>,
 }
 
 fn require_logged_in(app: &AppHandle) -> Result<ActiveSession> {
     let app_state = app.state::<Arc<AppState>>().inner().clone();
-    let active_profile = app_state
-        .active_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-    let logged_in_profile = app_state
-        .logged_in_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-
-    match (active_profile, logged_in_profile) {
-        (Some(active), Some(logged)) if active == logged => {
-            let vault_key = app_state
-                .vault_key
-                .lock()
-                .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-                .as_ref()
-                .map(|k| **k);
-
-            Ok(ActiveSession {
-                state: app_state,
-                profile_id: active,
-                vault_key,
-            })
-        }
-        _ => Err(ErrorCodeString::new("VAULT_LOCKED")),
-    }
+    let session = security_service::require_unlocked_active_profile(&app_state)?;
+    let blob_storage = app_state.get_attachment_blob_storage()?;
+
+    Ok(ActiveSession {
+        state: app_state,
+        profile_id: session.profile_id,
+        vault_key: session.vault_key,
+        blob_storage,
+    })
 }
 
-fn read_source_file(path: &Path) -> Result<Vec<u8>> {
+fn open_source_file(path: &Path) -> Result<fs::File> {
     let metadata =
         fs::metadata(path).map_err(|_| ErrorCodeString::new("ATTACHMENT_SOURCE_NOT_FOUND"))?;
     if !metadata.is_file() {
         return Err(ErrorCodeString::new("ATTACHMENT_SOURCE_NOT_FOUND"));
     }
-    if metadata.len() > MAX_ATTACHMENT_SIZE_BYTES {
-        return Err(ErrorCodeString::new("ATTACHMENT_TOO_LARGE"));
-    }
-
-    fs::read(path).map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))
+    fs::File::open(path).map_err(|_| ErrorCodeString::new("ATTACHMENT_SOURCE_NOT_FOUND"))
 }
 
 fn ensure_target_dir(path: &Path) -> Result<()> {
@@ -77,6 +64,104 @@ fn ensure_target_dir(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Hashes `reader`'s entire content one `FRAME_SIZE` chunk at a time — the
+/// same size `stream_cipher` seals frames under, so this pass and the
+/// sealing pass that follows it touch memory the same way — while keeping
+/// only the first `SNIFF_PREFIX_LEN` bytes around for `sniff_mime_type`.
+/// Returns the hex digest, the total byte count, and that sniff prefix.
+fn hash_and_sniff(reader: &mut impl Read) -> Result<(String, i64, Vec<u8>)> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; FRAME_SIZE];
+    let mut prefix = Vec::with_capacity(SNIFF_PREFIX_LEN);
+    let mut total: i64 = 0;
+
+    loop {
+        let n = reader
+            .read(&mut buf)
+            .map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        if prefix.len() < SNIFF_PREFIX_LEN {
+            let take = (SNIFF_PREFIX_LEN - prefix.len()).min(n);
+            prefix.extend_from_slice(&buf[..take]);
+        }
+        total += n as i64;
+    }
+
+    let hash = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+    Ok((hash, total, prefix))
+}
+
+/// Reads the source file's last-modified time, for display ("added 2 years
+/// ago") and so `save_attachment_to_path` can restore it onto the extracted
+/// copy. Best-effort: some filesystems/platforms don't report mtimes, so a
+/// missing one just means the UI falls back to `created_at`.
+fn source_mtime_rfc3339(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(chrono::DateTime::<Utc>::from(modified).to_rfc3339())
+}
+
+/// Magic-byte sniffing for the handful of attachment types users actually
+/// attach (documents, images, archives), falling back to the extension and
+/// then to a generic octet-stream. Deliberately not `infer`/`tree_magic`:
+/// those pull in a large signature database for formats this vault never
+/// needs to distinguish.
+fn sniff_mime_type(bytes: &[u8], file_name: &str) -> String {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"RIFF", "image/webp"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+
+    for (magic, mime) in SIGNATURES {
+        if bytes.starts_with(magic) {
+            return mime.to_string();
+        }
+    }
+
+    if bytes.iter().take(512).all(|b| *b != 0) {
+        if let Some(ext) = Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+        {
+            match ext.as_str() {
+                "txt" | "md" | "csv" | "log" => return "text/plain".to_string(),
+                "json" => return "application/json".to_string(),
+                "xml" => return "application/xml".to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    "application/octet-stream".to_string()
+}
+
+/// Stores `source_path` under `datacard_id`: hashes the plaintext, sniffs
+/// its MIME type from magic bytes, and reuses the existing blob in
+/// `session.blob_storage` if another `AttachmentMeta` (on this or any other
+/// datacard) was already stored under the same `content_hash` —
+/// `purge_attachment`'s own `count_attachments_by_content_hash` check is
+/// what keeps that shared blob alive until every row pointing at it is
+/// gone.
+///
+/// Reads `source_path` twice rather than once: both the blob's key and the
+/// AEAD's own associated data are keyed by `content_hash`, which isn't
+/// known until the source has been hashed in full, so encryption can't
+/// begin during the same pass that computes it. `hash_and_sniff` streams
+/// its pass one `FRAME_SIZE` chunk at a time regardless of file size; the
+/// sealing pass does too while reading, but — because `AttachmentBlobStorage::put`
+/// takes a single byte slice rather than a `Write` sink, so a remote
+/// backend sees one complete object per call instead of a partial upload —
+/// its sealed output is buffered in full before that one `put` call.
 pub fn add_attachment_from_path(
     app: &AppHandle,
     datacard_id: String,
@@ -84,45 +169,49 @@ pub fn add_attachment_from_path(
 ) -> Result<AttachmentMeta> {
     let session = require_logged_in(app)?;
     let source = Path::new(&source_path);
-    if source.file_name().is_none() {
-        return Err(ErrorCodeString::new("ATTACHMENT_SOURCE_NOT_FOUND"));
-    }
+    let file_name = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| ErrorCodeString::new("ATTACHMENT_SOURCE_NOT_FOUND"))?
+        .to_string();
 
     // Validate datacard exists for this profile
     let _ = repo_impl::get_datacard(&session.state, &session.profile_id, &datacard_id)?;
 
-    let bytes = read_source_file(source)?;
     let attachment_id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
-    let file_name = source
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or_default()
-        .to_string();
+
+    let source_file = open_source_file(source)?;
+    let (hash, byte_size, sniff_prefix) = hash_and_sniff(&mut BufReader::new(source_file))?;
 
     let meta = AttachmentMeta {
         id: attachment_id.clone(),
         datacard_id,
-        file_name,
-        mime_type: None,
-        byte_size: bytes.len() as i64,
+        file_name: file_name.clone(),
+        mime_type: Some(sniff_mime_type(&sniff_prefix, &file_name)),
+        byte_size,
+        content_hash: hash.clone(),
+        source_mtime: source_mtime_rfc3339(source),
         created_at: now.clone(),
         updated_at: now,
         deleted_at: None,
     };
 
-    let file_path =
-        attachment_file_path(&session.state.storage_paths, &session.profile_id, &meta.id);
-    ensure_target_dir(&file_path)?;
-
-    if let Some(key) = session.vault_key {
-        let encrypted =
-            cipher::encrypt_attachment_blob(&session.profile_id, &meta.id, &key, &bytes)?;
-        fs::write(&file_path, &encrypted)
-            .map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
-    } else {
-        fs::write(&file_path, &bytes)
-            .map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
+    // Content-addressed: if another attachment (on this or any datacard)
+    // already stored this exact content, its blob is reused as-is instead
+    // of being re-encrypted and written a second time.
+    if !session.blob_storage.exists(&session.profile_id, &hash)? {
+        let mut reader = BufReader::new(open_source_file(source)?);
+        let mut sealed = Vec::new();
+
+        if let Some(key) = session.vault_key {
+            cipher::encrypt_attachment_stream(&session.profile_id, &hash, &key, &mut reader, &mut sealed)?;
+        } else {
+            reader
+                .read_to_end(&mut sealed)
+                .map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+        }
+        session.blob_storage.put(&session.profile_id, &hash, &sealed)?;
     }
 
     repo_impl::insert_attachment(&session.state, &session.profile_id, &meta)?;
@@ -146,10 +235,20 @@ pub fn purge_attachment(app: &AppHandle, attachment_id: String) -> Result<()> {
     let meta = repo_impl::get_attachment(&session.state, &session.profile_id, &attachment_id)?
         .ok_or_else(|| ErrorCodeString::new("ATTACHMENT_NOT_FOUND"))?;
 
-    let file_path =
-        attachment_file_path(&session.state.storage_paths, &session.profile_id, &meta.id);
-    let _ = fs::remove_file(file_path);
-    repo_impl::purge_attachment(&session.state, &session.profile_id, &attachment_id)
+    repo_impl::purge_attachment(&session.state, &session.profile_id, &attachment_id)?;
+
+    // Only the row is guaranteed gone above; the blob itself is shared, so
+    // it's only safe to delete once nothing else still references its hash.
+    let remaining = repo_impl::count_attachments_by_content_hash(
+        &session.state,
+        &session.profile_id,
+        &meta.content_hash,
+    )?;
+    if remaining == 0 {
+        let _ = session.blob_storage.delete(&session.profile_id, &meta.content_hash);
+    }
+
+    Ok(())
 }
 
 pub fn save_attachment_to_path(
@@ -164,19 +263,63 @@ pub fn save_attachment_to_path(
         return Err(ErrorCodeString::new("ATTACHMENT_NOT_FOUND"));
     }
 
-    let stored_path =
-        attachment_file_path(&session.state.storage_paths, &session.profile_id, &meta.id);
-    let bytes =
-        fs::read(&stored_path).map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
-    let output_bytes = if let Some(key) = session.vault_key {
-        cipher::decrypt_attachment_blob(&session.profile_id, &meta.id, &key, &bytes)?
-    } else {
-        bytes
-    };
-
+    let mut sealed = session.blob_storage.get(&session.profile_id, &meta.content_hash)?;
     let target = Path::new(&target_path);
     ensure_target_dir(target)?;
-    fs::write(target, &output_bytes).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))
+
+    let mut writer = BufWriter::new(
+        fs::File::create(target).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?,
+    );
+    if let Some(key) = session.vault_key {
+        cipher::decrypt_attachment_stream(
+            &session.profile_id,
+            &meta.content_hash,
+            &key,
+            &mut sealed,
+            &mut writer,
+        )?;
+    } else {
+        std::io::copy(&mut sealed, &mut writer)
+            .map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
+    }
+    writer
+        .flush()
+        .map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
+    drop(writer);
+
+    // Best-effort: restore the original mtime so the extracted copy sorts
+    // and displays the same as the file the user originally attached.
+    if let Some(mtime) = meta.source_mtime.as_deref().and_then(parse_rfc3339) {
+        if let Ok(file) = fs::OpenOptions::new().write(true).open(target) {
+            let _ = file.set_modified(mtime);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_rfc3339(value: &str) -> Option<std::time::SystemTime> {
+    Some(chrono::DateTime::parse_from_rfc3339(value).ok()?.into())
+}
+
+/// Fetches and decrypts an attachment's blob in full via `session`'s
+/// `AttachmentBlobStorage`, for `get_attachment_preview`, which is already
+/// bound by `MAX_PREVIEW_BYTES` and genuinely needs the whole decrypted
+/// attachment in memory to return it as one payload — unlike
+/// `get_attachment_range`, which decrypts only the frames it needs via
+/// `cipher::decrypt_attachment_stream_range`, or `save_attachment_to_path`,
+/// which streams straight to disk.
+fn read_attachment_blob(session: &ActiveSession, content_hash: &str) -> Result<Vec<u8>> {
+    let mut sealed = session.blob_storage.get(&session.profile_id, content_hash)?;
+    let mut output = Vec::new();
+    if let Some(key) = session.vault_key {
+        cipher::decrypt_attachment_stream(&session.profile_id, content_hash, &key, &mut sealed, &mut output)?;
+    } else {
+        sealed
+            .read_to_end(&mut output)
+            .map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+    }
+    Ok(output)
 }
 
 pub fn get_attachment_preview(
@@ -194,16 +337,7 @@ pub fn get_attachment_preview(
         return Err(ErrorCodeString::new("ATTACHMENT_TOO_LARGE_FOR_PREVIEW"));
     }
 
-    let stored_path =
-        attachment_file_path(&session.state.storage_paths, &session.profile_id, &meta.id);
-    let bytes =
-        fs::read(&stored_path).map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
-
-    let output_bytes = if let Some(key) = session.vault_key {
-        cipher::decrypt_attachment_blob(&session.profile_id, &meta.id, &key, &bytes)?
-    } else {
-        bytes
-    };
+    let output_bytes = read_attachment_blob(&session, &meta.content_hash)?;
 
     if output_bytes.len() > MAX_PREVIEW_BYTES {
         return Err(ErrorCodeString::new("ATTACHMENT_TOO_LARGE_FOR_PREVIEW"));
@@ -225,6 +359,85 @@ pub fn get_attachment_preview(
     })
 }
 
+/// Discards the next `n` bytes of `reader` without buffering more than one
+/// read's worth at a time — used by `get_attachment_range` to skip a
+/// passwordless (unencrypted) blob's leading bytes, mirroring what
+/// `decrypt_stream_range` does for encrypted ones by never decrypting the
+/// frames it skips.
+fn skip_bytes(reader: &mut impl Read, mut n: u64) -> Result<()> {
+    let mut buf = [0u8; 64 * 1024];
+    while n > 0 {
+        let chunk = n.min(buf.len() as u64) as usize;
+        let read = reader
+            .read(&mut buf[..chunk])
+            .map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+        if read == 0 {
+            break;
+        }
+        n -= read as u64;
+    }
+    Ok(())
+}
+
+/// Returns one byte range of an attachment's decrypted content, so a
+/// caller (a large-file preview, a resumed download) can page through it
+/// instead of fetching it whole via `get_attachment_preview`. `offset`
+/// past the end of the content yields an empty range rather than an
+/// error, matching how HTTP range requests treat an out-of-bounds start.
+/// Only the `stream_cipher` frames overlapping `[offset, offset + length)`
+/// are ever decrypted — see `cipher::decrypt_attachment_stream_range` —
+/// so a page near the end of a large attachment doesn't pay to decrypt
+/// everything before it.
+pub fn get_attachment_range(
+    app: &AppHandle,
+    attachment_id: String,
+    offset: i64,
+    length: i64,
+) -> Result<AttachmentRangePayload> {
+    if offset < 0 || length < 0 {
+        return Err(ErrorCodeString::new("ATTACHMENT_RANGE_INVALID"));
+    }
+
+    let session = require_logged_in(app)?;
+    let meta = repo_impl::get_attachment(&session.state, &session.profile_id, &attachment_id)?
+        .ok_or_else(|| ErrorCodeString::new("ATTACHMENT_NOT_FOUND"))?;
+    if meta.deleted_at.is_some() {
+        return Err(ErrorCodeString::new("ATTACHMENT_NOT_FOUND"));
+    }
+
+    let total = meta.byte_size.max(0) as u64;
+    let start = (offset as u64).min(total);
+    let end = start.saturating_add(length as u64).min(total);
+
+    let mut sealed = session.blob_storage.get(&session.profile_id, &meta.content_hash)?;
+    let mut data = Vec::new();
+    if let Some(key) = session.vault_key {
+        cipher::decrypt_attachment_stream_range(
+            &session.profile_id,
+            &meta.content_hash,
+            &key,
+            &mut sealed,
+            &mut data,
+            start,
+            end,
+        )?;
+    } else {
+        skip_bytes(&mut sealed, start)?;
+        sealed
+            .by_ref()
+            .take(end - start)
+            .read_to_end(&mut data)
+            .map_err(|_| ErrorCodeString::new("ATTACHMENT_READ_FAILED"))?;
+    }
+
+    Ok(AttachmentRangePayload {
+        attachment_id: meta.id,
+        offset: start as i64,
+        data,
+        total_byte_size: meta.byte_size,
+    })
+}
+
 pub fn get_attachment_bytes_base64(
     app: &AppHandle,
     attachment_id: String,
@@ -233,9 +446,62 @@ pub fn get_attachment_bytes_base64(
 }
 
 pub fn clear_previews_for_profile(state: &Arc<AppState>, profile_id: &str) -> Result<()> {
-    let preview_root = attachments_preview_root(&state.storage_paths, profile_id);
+    let storage_paths = state.get_storage_paths()?;
+    let preview_root = attachments_preview_root(&storage_paths, profile_id)?;
     if preview_root.exists() {
         let _ = fs::remove_dir_all(&preview_root);
     }
     Ok(())
 }
+
+/// Hard-deletes attachment rows that have sat soft-deleted past the
+/// profile's `trash_retention_days`, freeing their on-disk blob too wherever
+/// nothing else still references it — `soft_delete_attachment` alone never
+/// reclaims that space. Meant to be called the same way
+/// `backup_service::backup_create_if_due_auto` is: periodically, from a
+/// scheduled command, with the returned counts there to log.
+pub fn purge_expired(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    now: &str,
+) -> Result<AttachmentPurgeReport> {
+    let storage_paths = state.get_storage_paths()?;
+    let settings = get_settings(&storage_paths, profile_id)?;
+
+    let parsed_now = chrono::DateTime::parse_from_rfc3339(now)
+        .map_err(|_| ErrorCodeString::new("INVALID_TIMESTAMP"))?
+        .with_timezone(&Utc);
+    let cutoff = (parsed_now - Duration::days(settings.trash_retention_days)).to_rfc3339();
+
+    let expired = repo_impl::list_expired_attachments(state, profile_id, &cutoff)?;
+
+    let mut rows_purged = 0usize;
+    let mut files_removed = 0usize;
+    for meta in expired {
+        if let Err(err) = repo_impl::purge_attachment(state, profile_id, &meta.id) {
+            if err.code == "ATTACHMENT_NOT_FOUND" {
+                continue;
+            }
+            return Err(err);
+        }
+        rows_purged += 1;
+
+        // Same shared-blob caveat as `purge_attachment` above: only remove
+        // the blob once nothing else still points at its content hash.
+        let remaining =
+            repo_impl::count_attachments_by_content_hash(state, profile_id, &meta.content_hash)?;
+        if remaining == 0 && state.get_attachment_blob_storage()?.delete(profile_id, &meta.content_hash).is_ok() {
+            files_removed += 1;
+        }
+    }
+
+    Ok(AttachmentPurgeReport { rows_purged, files_removed })
+}
+
+/// `purge_expired` for whichever profile is currently logged in — the shape
+/// a periodic command actually calls, same as
+/// `backup_service::backup_create_if_due_auto`.
+pub fn purge_expired_for_active_profile(state: &Arc<AppState>) -> Result<AttachmentPurgeReport> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    purge_expired(state, &profile_id, &Utc::now().to_rfc3339())
+}
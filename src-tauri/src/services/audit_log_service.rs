@@ -0,0 +1,15 @@
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::data::sqlite::repo_impl;
+use crate::error::Result;
+use crate::services::security_service;
+use crate::types::AuditLogEntry;
+
+/// Trigger-captured edit/delete history for a single `datacards`,
+/// `attachments`, or `datacard_password_history` row — see
+/// `data::sqlite::migrations::migrate_v7_to_v8_audit_log`.
+pub fn list_audit_log(state: &Arc<AppState>, row_id: &str) -> Result<Vec<AuditLogEntry>> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    repo_impl::list_audit_log(state, &profile_id, row_id)
+}
@@ -0,0 +1,204 @@
+//! Resumable, chunk-at-a-time backup jobs.
+//!
+//! `backup_service::backup_create` does the whole backup in one blocking
+//! call, which is fine for small vaults but gives the caller no way to
+//! pause or recover progress if the app closes mid-backup. A job here
+//! processes exactly one content-defined chunk per `backup_job_step` call
+//! and persists its progress to disk after every chunk, so:
+//! - "pausing" is just not calling `backup_job_step` again — there's no
+//!   separate pause action to get wrong.
+//! - "resuming" (even after the app restarted) is calling `backup_job_step`
+//!   again; `backup_job_resume` re-hydrates the job state from disk first.
+//!
+//! The vault is snapshotted to a job-private file at `backup_job_start` so
+//! later steps chunk a stable, unchanging copy regardless of what happens
+//! to the live vault in the meantime.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::data::backup::chunk_store::ChunkStore;
+use crate::data::backup::chunking;
+use crate::data::fs::atomic_write::write_atomic;
+use crate::data::profiles::paths::{backups_dir, vault_db_path};
+use crate::error::{ErrorCodeString, Result};
+use crate::services::{backup_service, security_service};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BackupJobStatus {
+    InProgress,
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupJobProgress {
+    pub job_id: String,
+    pub status: BackupJobStatus,
+    pub processed_chunks: usize,
+    pub total_chunks: usize,
+    /// Set once `status` is `Completed`: the finished archive's path.
+    pub completed_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupJobState {
+    job_id: String,
+    profile_id: String,
+    destination: PathBuf,
+    payload_sha256: String,
+    total_chunks: usize,
+    chunk_hashes: Vec<String>,
+    status: BackupJobStatus,
+    completed_path: Option<String>,
+}
+
+impl From<&BackupJobState> for BackupJobProgress {
+    fn from(state: &BackupJobState) -> Self {
+        BackupJobProgress {
+            job_id: state.job_id.clone(),
+            status: state.status.clone(),
+            processed_chunks: state.chunk_hashes.len(),
+            total_chunks: state.total_chunks,
+            completed_path: state.completed_path.clone(),
+        }
+    }
+}
+
+fn jobs_dir(sp: &crate::data::storage_paths::StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(backups_dir(sp, profile_id)?.join("jobs"))
+}
+
+fn job_state_path(jobs_dir: &std::path::Path, job_id: &str) -> PathBuf {
+    jobs_dir.join(format!("{job_id}.json"))
+}
+
+fn snapshot_path(jobs_dir: &std::path::Path, job_id: &str) -> PathBuf {
+    jobs_dir.join(format!("{job_id}.snapshot"))
+}
+
+fn save_job_state(jobs_dir: &std::path::Path, state: &BackupJobState) -> Result<()> {
+    let serialized =
+        serde_json::to_vec_pretty(state).map_err(|_| ErrorCodeString::new("BACKUP_SERIALIZE_FAILED"))?;
+    write_atomic(&job_state_path(jobs_dir, &state.job_id), &serialized)
+        .map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))
+}
+
+fn load_job_state(jobs_dir: &std::path::Path, job_id: &str) -> Result<BackupJobState> {
+    let bytes = fs::read(job_state_path(jobs_dir, job_id))
+        .map_err(|_| ErrorCodeString::new("BACKUP_JOB_NOT_FOUND"))?;
+    serde_json::from_slice(&bytes).map_err(|_| ErrorCodeString::new("BACKUP_CORRUPTED"))
+}
+
+/// Snapshots the active vault and registers a new job against it. Doesn't
+/// write any chunks yet — the first `backup_job_step` call does that.
+pub fn backup_job_start(
+    state: &Arc<AppState>,
+    destination_path: Option<String>,
+    use_default_path: bool,
+) -> Result<BackupJobProgress> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let storage_paths = state.get_storage_paths()?;
+    security_service::persist_active_vault(state)?;
+
+    let db_bytes = fs::read(vault_db_path(&storage_paths, &profile_id)?)
+        .map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+
+    let jobs_dir = jobs_dir(&storage_paths, &profile_id)?;
+    fs::create_dir_all(&jobs_dir).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+
+    let job_id = Uuid::new_v4().to_string();
+    write_atomic(&snapshot_path(&jobs_dir, &job_id), &db_bytes)
+        .map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+
+    let destination = if let Some(path) = destination_path.filter(|_| !use_default_path) {
+        PathBuf::from(path)
+    } else {
+        let dir = backups_dir(&storage_paths, &profile_id)?;
+        fs::create_dir_all(&dir).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+        dir.join(format!("{}.pmbk", Utc::now().format("%Y%m%dT%H%M%S")))
+    };
+
+    let total_chunks = chunking::chunk(&db_bytes).len();
+    let job_state = BackupJobState {
+        job_id,
+        profile_id,
+        destination,
+        payload_sha256: backup_service::sha256_hex(&db_bytes),
+        total_chunks,
+        chunk_hashes: Vec::new(),
+        status: BackupJobStatus::InProgress,
+        completed_path: None,
+    };
+    save_job_state(&jobs_dir, &job_state)?;
+
+    Ok((&job_state).into())
+}
+
+/// Processes exactly one more chunk of an in-progress job. Safe to call
+/// repeatedly, including from a fresh process after a restart — progress
+/// lives entirely in the job state file and the shared chunk store.
+pub fn backup_job_step(state: &Arc<AppState>, job_id: &str) -> Result<BackupJobProgress> {
+    let storage_paths = state.get_storage_paths()?;
+    let session = security_service::require_unlocked_active_profile(state)?;
+    let active_profile_id = session.profile_id;
+    let vault_key = session.vault_key;
+    let jobs_dir = jobs_dir(&storage_paths, &active_profile_id)?;
+    let mut job_state = load_job_state(&jobs_dir, job_id)?;
+
+    if job_state.status == BackupJobStatus::Completed {
+        return Ok((&job_state).into());
+    }
+
+    let snapshot = fs::read(snapshot_path(&jobs_dir, job_id))
+        .map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+    let chunks = chunking::chunk(&snapshot);
+    let next_index = job_state.chunk_hashes.len();
+    let Some(piece) = chunks.get(next_index) else {
+        return Err(ErrorCodeString::new("BACKUP_JOB_CORRUPTED"));
+    };
+
+    let store = ChunkStore::new(backups_dir(&storage_paths, &job_state.profile_id)?.join("chunks"));
+    let (hash, _written) = store.put(&job_state.profile_id, vault_key.as_ref(), piece)?;
+    job_state.chunk_hashes.push(hash);
+
+    if job_state.chunk_hashes.len() == job_state.total_chunks {
+        let archive = backup_service::encode_archive_for_chunks(
+            &job_state.profile_id,
+            &job_state.payload_sha256,
+            &job_state.chunk_hashes,
+        )?;
+        write_atomic(&job_state.destination, &archive)
+            .map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+        job_state.status = BackupJobStatus::Completed;
+        job_state.completed_path = Some(job_state.destination.to_string_lossy().to_string());
+        let _ = fs::remove_file(snapshot_path(&jobs_dir, job_id));
+    }
+
+    save_job_state(&jobs_dir, &job_state)?;
+    Ok((&job_state).into())
+}
+
+pub fn backup_job_status(state: &Arc<AppState>, job_id: &str) -> Result<BackupJobProgress> {
+    let storage_paths = state.get_storage_paths()?;
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let job_state = load_job_state(&jobs_dir(&storage_paths, &profile_id)?, job_id)?;
+    Ok((&job_state).into())
+}
+
+/// Abandons a job: removes its state and snapshot. Chunks it already wrote
+/// stay in the shared chunk store (they may be referenced by other
+/// backups, and are harmless dedup fodder either way).
+pub fn backup_job_cancel(state: &Arc<AppState>, job_id: &str) -> Result<bool> {
+    let storage_paths = state.get_storage_paths()?;
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let dir = jobs_dir(&storage_paths, &profile_id)?;
+    let _ = fs::remove_file(job_state_path(&dir, job_id));
+    let _ = fs::remove_file(snapshot_path(&dir, job_id));
+    Ok(true)
+}
@@ -1,43 +1,758 @@
+//! Versioned, encrypted full-profile backup archives.
+//!
+//! Archive layout (all integers little-endian):
+//!   magic:          4 bytes, b"PMBK"
+//!   format_version: u8
+//!   metadata_len:   u32
+//!   metadata:       `metadata_len` bytes of JSON (`BackupMetadata`)
+//!   payload:        remaining bytes; meaning depends on `format_version`
+//!
+//! - v1: `payload` is the encrypted vault database, nothing else.
+//! - v2: `payload` is the vault database, zlib-compressed then encrypted;
+//!   `metadata.payload_sha256` lets `backup_verify` catch corruption.
+//! - v3: `payload` is empty. The database is instead split into
+//!   content-defined chunks (`data::backup::chunking`) and each chunk is
+//!   written, encrypted, into a profile-wide content-addressed chunk store
+//!   (`data::backup::chunk_store`) — `metadata.chunk_hashes` is the ordered
+//!   manifest. Chunks already on disk from an earlier backup aren't
+//!   rewritten, so incremental backups of a mostly-unchanged vault are
+//!   cheap and don't duplicate data.
+//! - v4 (current): same as v3, plus `metadata.attachment_manifests` — each
+//!   file under the profile's `attachments/` directory is chunked the same
+//!   way and its chunks land in the same chunk store, so a chunk shared
+//!   between the database and an attachment (or between two attachments)
+//!   is still only written once.
+//!
+//! `format_version` exists so archives old and new can all still be read by
+//! `backup_restore`/`backup_verify` without breaking backward compatibility.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use tauri::AppHandle;
+use chrono::Utc;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
 use crate::app_state::AppState;
+use crate::data::backup::chunk_store::ChunkStore;
+use crate::data::backup::chunking;
+use crate::data::crypto::cipher;
+use crate::data::fs::atomic_write::write_atomic;
+use crate::data::profiles::paths::{attachment_file_path, attachments_dir, backups_dir, vault_db_path};
+use crate::data::profiles::registry;
+use crate::data::storage_paths::StoragePaths;
 use crate::error::{ErrorCodeString, Result};
+use crate::services::{security_service, settings_service};
+use crate::types::BackupFrequency;
+
+const MAGIC: &[u8; 4] = b"PMBK";
+const FORMAT_VERSION: u8 = 4;
+
+/// One attachment blob carried by a v4+ archive: `content_hash` is the
+/// attachment's own content-addressed file name (see
+/// `data::profiles::paths::attachment_file_path`), `chunk_hashes` the
+/// ordered pieces it was split into. `blob_sha256` checksums the on-disk
+/// bytes that were chunked — not the same thing as `content_hash`, which
+/// identifies the attachment's *plaintext*, since the file on disk is
+/// already AEAD-encrypted.
+#[derive(Debug, Serialize, Deserialize)]
+struct AttachmentBackupEntry {
+    content_hash: String,
+    chunk_hashes: Vec<String>,
+    blob_sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupMetadata {
+    format_version: u8,
+    profile_id: String,
+    created_at: String,
+    /// Hex-encoded SHA-256 of the plaintext, uncompressed vault database.
+    /// Absent (empty) on v1 archives, which predate verification.
+    #[serde(default)]
+    payload_sha256: String,
+    /// Ordered content hashes of the chunks making up the database, v3+
+    /// only. Empty on older archives, which carry the database inline in
+    /// the archive's payload instead.
+    #[serde(default)]
+    chunk_hashes: Vec<String>,
+    /// Every attachment blob that existed for the profile at backup time,
+    /// v4+ only. Empty on older archives, which didn't back up attachments.
+    #[serde(default)]
+    attachment_manifests: Vec<AttachmentBackupEntry>,
+}
+
+fn chunks_dir(sp: &crate::data::storage_paths::StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    Ok(backups_dir(sp, profile_id)?.join("chunks"))
+}
+
+fn compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .map_err(|_| ErrorCodeString::new("BACKUP_COMPRESS_FAILED"))?;
+    encoder
+        .finish()
+        .map_err(|_| ErrorCodeString::new("BACKUP_COMPRESS_FAILED"))
+}
+
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = ZlibDecoder::new(bytes);
+    let mut out = Vec::new();
+    decoder
+        .read_to_end(&mut out)
+        .map_err(|_| ErrorCodeString::new("BACKUP_CORRUPTED"))?;
+    Ok(out)
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Builds an archive (header + metadata, empty payload) from a chunk
+/// manifest that's already been written into the chunk store. Used by
+/// `backup_job_service` once a resumable job finishes writing every chunk.
+/// Resumable jobs only ever chunk the database, not attachments, so the
+/// resulting archive's `attachment_manifests` is always empty — the same as
+/// any other archive made before v4 added attachment backup.
+pub(crate) fn encode_archive_for_chunks(
+    profile_id: &str,
+    payload_sha256: &str,
+    chunk_hashes: &[String],
+) -> Result<Vec<u8>> {
+    let metadata = BackupMetadata {
+        format_version: FORMAT_VERSION,
+        profile_id: profile_id.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        payload_sha256: payload_sha256.to_string(),
+        chunk_hashes: chunk_hashes.to_vec(),
+        attachment_manifests: Vec::new(),
+    };
+    encode_archive(&metadata, &[])
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupListItem {
+    pub file_name: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+}
+
+fn encode_archive(metadata: &BackupMetadata, payload: &[u8]) -> Result<Vec<u8>> {
+    let metadata_json = serde_json::to_vec(metadata)
+        .map_err(|_| ErrorCodeString::new("BACKUP_SERIALIZE_FAILED"))?;
+
+    let mut out = Vec::with_capacity(4 + 1 + 4 + metadata_json.len() + payload.len());
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&(metadata_json.len() as u32).to_le_bytes());
+    out.extend_from_slice(&metadata_json);
+    out.extend_from_slice(payload);
+    Ok(out)
+}
+
+fn decode_archive(bytes: &[u8]) -> Result<(BackupMetadata, Vec<u8>)> {
+    if bytes.len() < 9 || &bytes[0..4] != MAGIC {
+        return Err(ErrorCodeString::new("BACKUP_CORRUPTED"));
+    }
+    let format_version = bytes[4];
+    if format_version == 0 || format_version > FORMAT_VERSION {
+        return Err(ErrorCodeString::new("BACKUP_UNSUPPORTED_VERSION"));
+    }
+    let metadata_len = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]) as usize;
+    let metadata_start = 9;
+    let metadata_end = metadata_start
+        .checked_add(metadata_len)
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| ErrorCodeString::new("BACKUP_CORRUPTED"))?;
+
+    let metadata: BackupMetadata = serde_json::from_slice(&bytes[metadata_start..metadata_end])
+        .map_err(|_| ErrorCodeString::new("BACKUP_CORRUPTED"))?;
+    let payload = bytes[metadata_end..].to_vec();
+    Ok((metadata, payload))
+}
+
+/// The key `ChunkStore` should seal/open `profile_id`'s v3+ chunks under —
+/// `None` for a passwordless profile (nothing to key them with), or the
+/// active session's key for a protected one. A protected profile that isn't
+/// currently unlocked has no key available at all, so this fails closed
+/// with `VAULT_LOCKED` rather than silently falling back to the
+/// placeholder and producing a "verified" backup nobody can actually
+/// restore.
+fn resolve_vault_key(
+    state: &Arc<AppState>,
+    storage_paths: &StoragePaths,
+    profile_id: &str,
+) -> Result<Option<[u8; 32]>> {
+    let profile = registry::get_profile(storage_paths, profile_id)?
+        .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    if !profile.has_password {
+        return Ok(None);
+    }
+    state
+        .vault_key_for(profile_id)?
+        .map(Some)
+        .ok_or_else(|| ErrorCodeString::new("VAULT_LOCKED"))
+}
+
+/// Same as `resolve_vault_key`, except for read-only archive operations
+/// (`backup_verify`/`backup_repair`/`decrypt_backup_to_temp`) that take a
+/// `backup_path` rather than an active session, and so can be handed a v1/v2
+/// archive or one left behind by a profile that's since been deleted. Those
+/// archives' `decode_payload_verified` branch never touches the chunk store
+/// at all, so demanding a key for them would turn "check this old backup"
+/// into a spurious `VAULT_LOCKED`/`PROFILE_NOT_FOUND` — only resolve one
+/// when the archive actually has v3+ chunk-store content to unseal.
+fn resolve_vault_key_for_archive(
+    state: &Arc<AppState>,
+    storage_paths: &StoragePaths,
+    metadata: &BackupMetadata,
+) -> Result<Option<[u8; 32]>> {
+    if metadata.chunk_hashes.is_empty() && metadata.attachment_manifests.is_empty() {
+        return Ok(None);
+    }
+    resolve_vault_key(state, storage_paths, &metadata.profile_id)
+}
+
+/// Reassembles the plaintext vault database an archive was made from,
+/// dispatching on `format_version`. v3 reads its chunks out of `chunks_dir`
+/// instead of `encrypted_payload` (which is empty for v3 archives).
+fn decode_payload_verified(
+    metadata: &BackupMetadata,
+    encrypted_payload: &[u8],
+    chunks_dir: &std::path::Path,
+    vault_key: Option<&[u8; 32]>,
+) -> Result<Vec<u8>> {
+    if metadata.format_version == 1 {
+        return Ok(cipher::decrypt_placeholder(encrypted_payload));
+    }
+
+    if metadata.format_version == 2 {
+        let decrypted = cipher::decrypt_placeholder(encrypted_payload);
+        let db_bytes = decompress(&decrypted)?;
+        if sha256_hex(&db_bytes) != metadata.payload_sha256 {
+            return Err(ErrorCodeString::new("BACKUP_CHECKSUM_MISMATCH"));
+        }
+        return Ok(db_bytes);
+    }
+
+    let store = ChunkStore::new(chunks_dir.to_path_buf());
+    let mut db_bytes = Vec::new();
+    for hash in &metadata.chunk_hashes {
+        db_bytes.extend(store.get(&metadata.profile_id, vault_key, hash)?);
+    }
+    if sha256_hex(&db_bytes) != metadata.payload_sha256 {
+        return Err(ErrorCodeString::new("BACKUP_CHECKSUM_MISMATCH"));
+    }
+    Ok(db_bytes)
+}
+
+/// Chunks every file currently under the profile's `attachments/` directory
+/// into `store`, alongside whatever database chunks the caller already put
+/// there. Attachments are named by their own content hash on disk, so that
+/// hash is carried in the manifest entry rather than re-derived from the
+/// (already chunk-store-addressed) pieces.
+fn chunk_attachments(
+    sp: &StoragePaths,
+    profile_id: &str,
+    vault_key: Option<&[u8; 32]>,
+    store: &ChunkStore,
+) -> Result<Vec<AttachmentBackupEntry>> {
+    let dir = attachments_dir(sp, profile_id)?;
+    let Ok(read_dir) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut manifests = Vec::new();
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let Some(content_hash) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let Ok(bytes) = fs::read(&path) else { continue };
+
+        let blob_sha256 = sha256_hex(&bytes);
+        let mut chunk_hashes = Vec::new();
+        for piece in chunking::chunk(&bytes) {
+            let (hash, _written) = store.put(profile_id, vault_key, piece)?;
+            chunk_hashes.push(hash);
+        }
+        manifests.push(AttachmentBackupEntry {
+            content_hash: content_hash.to_string(),
+            chunk_hashes,
+            blob_sha256,
+        });
+    }
+    Ok(manifests)
+}
+
+/// Reassembles every attachment blob an archive recorded and writes each one
+/// back under its own content-addressed path, overwriting whatever (if
+/// anything) is already there. A no-op for pre-v4 archives, which didn't
+/// back up attachments.
+fn restore_attachments(
+    metadata: &BackupMetadata,
+    store: &ChunkStore,
+    vault_key: Option<&[u8; 32]>,
+    sp: &StoragePaths,
+    profile_id: &str,
+) -> Result<()> {
+    if metadata.attachment_manifests.is_empty() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(attachments_dir(sp, profile_id)?)
+        .map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
 
-#[derive(Debug, Clone)]
-pub enum ExportBackupMode {
-    UseProfilePassword,
-    CustomPassword(String),
+    for entry in &metadata.attachment_manifests {
+        let mut bytes = Vec::new();
+        for hash in &entry.chunk_hashes {
+            bytes.extend(store.get(profile_id, vault_key, hash)?);
+        }
+        let path = attachment_file_path(sp, profile_id, &entry.content_hash)?;
+        write_atomic(&path, &bytes).map_err(|_| ErrorCodeString::new("BACKUP_RESTORE_FAILED"))?;
+    }
+    Ok(())
 }
 
-pub fn export_backup(
-    _app: &AppHandle,
-    _state: &Arc<AppState>,
-    _output_path: String,
-    _mode: ExportBackupMode,
-) -> Result<bool> {
-    Err(ErrorCodeString::new("BACKUP_UNSUPPORTED_VERSION"))
+fn default_backup_path(sp: &crate::data::storage_paths::StoragePaths, profile_id: &str) -> Result<PathBuf> {
+    let dir = backups_dir(sp, profile_id)?;
+    fs::create_dir_all(&dir).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+    let file_name = format!("{}.pmbk", Utc::now().format("%Y%m%dT%H%M%S"));
+    Ok(dir.join(file_name))
 }
 
-pub fn decrypt_backup_to_temp(
-    _app: &AppHandle,
-    _state: &Arc<AppState>,
-    _backup_path: String,
-    _password: String,
+pub fn backup_create(
+    state: &Arc<AppState>,
+    destination_path: Option<String>,
+    use_default_path: bool,
 ) -> Result<String> {
-    Err(ErrorCodeString::new("BACKUP_UNSUPPORTED_VERSION"))
+    let session = security_service::require_unlocked_active_profile(state)?;
+    let profile_id = session.profile_id;
+    let vault_key = session.vault_key;
+    let storage_paths = state.get_storage_paths()?;
+
+    security_service::persist_active_vault(state)?;
+
+    let db_bytes = fs::read(vault_db_path(&storage_paths, &profile_id)?)
+        .map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+    let payload_sha256 = sha256_hex(&db_bytes);
+
+    let store = ChunkStore::new(chunks_dir(&storage_paths, &profile_id)?);
+    let mut chunk_hashes = Vec::new();
+    for piece in chunking::chunk(&db_bytes) {
+        let (hash, _written) = store.put(&profile_id, vault_key.as_ref(), piece)?;
+        chunk_hashes.push(hash);
+    }
+
+    let attachment_manifests = chunk_attachments(&storage_paths, &profile_id, vault_key.as_ref(), &store)?;
+
+    let metadata = BackupMetadata {
+        format_version: FORMAT_VERSION,
+        profile_id: profile_id.clone(),
+        created_at: Utc::now().to_rfc3339(),
+        payload_sha256,
+        attachment_manifests,
+        chunk_hashes,
+    };
+    let archive = encode_archive(&metadata, &[])?;
+
+    let destination = if let Some(path) = destination_path.filter(|_| !use_default_path) {
+        PathBuf::from(path)
+    } else {
+        default_backup_path(&storage_paths, &profile_id)?
+    };
+
+    write_atomic(&destination, &archive).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+
+    prune_old_backups(&storage_paths, &profile_id)?;
+
+    Ok(destination.to_string_lossy().to_string())
+}
+
+pub fn backup_list(state: &Arc<AppState>) -> Result<Vec<BackupListItem>> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let storage_paths = state.get_storage_paths()?;
+    let dir = backups_dir(&storage_paths, &profile_id)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut items = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))? {
+        let entry = entry.map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+        let metadata = entry.metadata().map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let bytes = fs::read(entry.path()).map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+        let (archive_metadata, _) = match decode_archive(&bytes) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        items.push(BackupListItem {
+            file_name: entry.file_name().to_string_lossy().to_string(),
+            created_at: archive_metadata.created_at,
+            size_bytes: metadata.len(),
+        });
+    }
+
+    items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(items)
+}
+
+pub fn backup_restore(state: &Arc<AppState>, backup_path: String) -> Result<bool> {
+    let session = security_service::require_unlocked_active_profile(state)?;
+    let profile_id = session.profile_id;
+    let vault_key = session.vault_key;
+    let storage_paths = state.get_storage_paths()?;
+
+    let bytes = fs::read(&backup_path).map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+    let (metadata, encrypted_payload) = decode_archive(&bytes)?;
+    if metadata.profile_id != profile_id {
+        return Err(ErrorCodeString::new("BACKUP_PROFILE_MISMATCH"));
+    }
+
+    let db_bytes = decode_payload_verified(
+        &metadata,
+        &encrypted_payload,
+        &chunks_dir(&storage_paths, &profile_id)?,
+        vault_key.as_ref(),
+    )?;
+
+    crate::data::sqlite::pool::clear_pool(&profile_id);
+    write_atomic(&vault_db_path(&storage_paths, &profile_id)?, &db_bytes)
+        .map_err(|_| ErrorCodeString::new("BACKUP_RESTORE_FAILED"))?;
+
+    let store = ChunkStore::new(chunks_dir(&storage_paths, &profile_id)?);
+    restore_attachments(&metadata, &store, vault_key.as_ref(), &storage_paths, &profile_id)?;
+
+    Ok(true)
+}
+
+/// Per-chunk outcome of `backup_verify` against a v3+ archive's chunk
+/// store. v1/v2 archives carry the database inline instead, so they're
+/// reported as a single `payload_ok` with no per-chunk detail.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStatus {
+    Ok,
+    Missing,
+    Corrupted,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkVerifyEntry {
+    pub chunk_hash: String,
+    pub status: ChunkStatus,
+}
+
+/// Verify outcome for one attachment blob the archive recorded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentVerifyEntry {
+    pub content_hash: String,
+    pub chunks: Vec<ChunkVerifyEntry>,
+    /// Whether every chunk in `chunks` is `Ok` and the reassembled bytes
+    /// match the checksum recorded for this attachment at backup time.
+    pub ok: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupVerifyReport {
+    pub backup_path: String,
+    pub chunks: Vec<ChunkVerifyEntry>,
+    /// Whether the archive reassembles into a database matching its
+    /// recorded checksum. `false` whenever any `chunks` entry isn't `Ok`.
+    pub payload_ok: bool,
+    /// Empty for archives older than v4, which didn't back up attachments.
+    pub attachments: Vec<AttachmentVerifyEntry>,
+}
+
+/// Checks that a backup archive's chunks are all present and intact, and
+/// that it still reassembles to its recorded checksum, without restoring
+/// it. Each chunk is re-hashed from its decrypted content and compared
+/// against the hash that names it in the chunk store, so bit rot on disk
+/// is caught even though reading the file itself succeeds.
+pub fn backup_verify(state: &Arc<AppState>, backup_path: String) -> Result<BackupVerifyReport> {
+    let bytes = fs::read(&backup_path).map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+    let (metadata, encrypted_payload) = decode_archive(&bytes)?;
+    let storage_paths = state.get_storage_paths()?;
+    let dir = chunks_dir(&storage_paths, &metadata.profile_id)?;
+    let vault_key = resolve_vault_key_for_archive(state, &storage_paths, &metadata)?;
+
+    if metadata.chunk_hashes.is_empty() {
+        let payload_ok =
+            decode_payload_verified(&metadata, &encrypted_payload, &dir, vault_key.as_ref()).is_ok();
+        return Ok(BackupVerifyReport {
+            backup_path,
+            chunks: Vec::new(),
+            payload_ok,
+            attachments: Vec::new(),
+        });
+    }
+
+    let store = ChunkStore::new(dir);
+    let mut reassembled = Vec::new();
+    let chunks: Vec<ChunkVerifyEntry> = metadata
+        .chunk_hashes
+        .iter()
+        .map(|hash| {
+            let status = verify_chunk(&store, &metadata.profile_id, vault_key.as_ref(), hash, &mut reassembled);
+            ChunkVerifyEntry {
+                chunk_hash: hash.clone(),
+                status,
+            }
+        })
+        .collect();
+
+    let payload_ok = chunks.iter().all(|entry| entry.status == ChunkStatus::Ok)
+        && sha256_hex(&reassembled) == metadata.payload_sha256;
+
+    let attachments = metadata
+        .attachment_manifests
+        .iter()
+        .map(|entry| {
+            let mut reassembled = Vec::new();
+            let chunks: Vec<ChunkVerifyEntry> = entry
+                .chunk_hashes
+                .iter()
+                .map(|hash| {
+                    let status =
+                        verify_chunk(&store, &metadata.profile_id, vault_key.as_ref(), hash, &mut reassembled);
+                    ChunkVerifyEntry {
+                        chunk_hash: hash.clone(),
+                        status,
+                    }
+                })
+                .collect();
+            let ok = chunks.iter().all(|c| c.status == ChunkStatus::Ok)
+                && sha256_hex(&reassembled) == entry.blob_sha256;
+            AttachmentVerifyEntry {
+                content_hash: entry.content_hash.clone(),
+                chunks,
+                ok,
+            }
+        })
+        .collect();
+
+    Ok(BackupVerifyReport {
+        backup_path,
+        chunks,
+        payload_ok,
+        attachments,
+    })
+}
+
+fn verify_chunk(
+    store: &ChunkStore,
+    profile_id: &str,
+    vault_key: Option<&[u8; 32]>,
+    hash: &str,
+    reassembled: &mut Vec<u8>,
+) -> ChunkStatus {
+    if !store.exists(hash) {
+        return ChunkStatus::Missing;
+    }
+    match store.get(profile_id, vault_key, hash) {
+        Ok(plaintext) if crate::data::backup::chunk_store::chunk_hash(&plaintext) == hash => {
+            reassembled.extend(plaintext);
+            ChunkStatus::Ok
+        }
+        _ => ChunkStatus::Corrupted,
+    }
 }
 
-pub fn finalize_restore(_state: &Arc<AppState>, _temp_id: String) -> Result<bool> {
-    Err(ErrorCodeString::new("BACKUP_RESTORE_REQUIRES_UNLOCKED_PROFILE"))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupRepairReport {
+    pub repaired: Vec<String>,
+    pub still_broken: Vec<String>,
 }
 
-pub fn finalize_import_as_new_profile(
-    _state: &Arc<AppState>,
-    _temp_id: String,
-    _new_profile_name: String,
-    _password: String,
-) -> Result<bool> {
-    Err(ErrorCodeString::new("BACKUP_UNSUPPORTED_VERSION"))
+/// Attempts to rebuild every missing/corrupted chunk `backup_verify` found
+/// for `backup_path`, by pulling the same bytes out of some other intact
+/// source for this profile and re-writing them into the shared chunk
+/// store under their original hash.
+///
+/// Chunks are content-addressed and shared across every v3+ backup, so if
+/// one is damaged on disk, no *other* v3 backup can supply it either — they'd
+/// all resolve to the same damaged file. The sources that actually can are
+/// ones that carry the database as a complete, inline copy rather than
+/// through the chunk store: the live vault database (if currently
+/// unlocked) and any v1/v2 archive for this profile. Each candidate is
+/// re-chunked with the same content-defined chunker used at backup time,
+/// and any piece matching a broken hash is written back via `ChunkStore::put`.
+pub fn backup_repair(state: &Arc<AppState>, backup_path: String) -> Result<BackupRepairReport> {
+    let report = backup_verify(state, backup_path.clone())?;
+    let mut broken: Vec<String> = report
+        .chunks
+        .iter()
+        .chain(report.attachments.iter().flat_map(|a| a.chunks.iter()))
+        .filter(|entry| entry.status != ChunkStatus::Ok)
+        .map(|entry| entry.chunk_hash.clone())
+        .collect();
+
+    if broken.is_empty() {
+        return Ok(BackupRepairReport {
+            repaired: Vec::new(),
+            still_broken: Vec::new(),
+        });
+    }
+
+    let bytes = fs::read(&backup_path).map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+    let (metadata, _) = decode_archive(&bytes)?;
+    let storage_paths = state.get_storage_paths()?;
+    let vault_key = resolve_vault_key_for_archive(state, &storage_paths, &metadata)?;
+    let store = ChunkStore::new(chunks_dir(&storage_paths, &metadata.profile_id)?);
+
+    let mut repaired = Vec::new();
+    for candidate in repair_candidates(&storage_paths, &metadata.profile_id, vault_key.as_ref()) {
+        if broken.is_empty() {
+            break;
+        }
+        for piece in chunking::chunk(&candidate) {
+            let hash = crate::data::backup::chunk_store::chunk_hash(piece);
+            if let Some(pos) = broken.iter().position(|h| h == &hash) {
+                store.put(&metadata.profile_id, vault_key.as_ref(), piece)?;
+                repaired.push(broken.remove(pos));
+            }
+        }
+    }
+
+    Ok(BackupRepairReport {
+        repaired,
+        still_broken: broken,
+    })
+}
+
+/// Every full, independent copy of bytes we've previously chunked for this
+/// profile that we can still read whole: the live vault file (best-effort —
+/// absent or unreadable if the profile isn't currently unlocked), every
+/// file still sitting in `attachments/` (re-chunking an attachment that
+/// hasn't moved regenerates the same chunks a damaged backup lost), and
+/// every v1/v2 archive on disk, which embed the whole database instead of
+/// referencing the chunk store.
+fn repair_candidates(
+    storage_paths: &crate::data::storage_paths::StoragePaths,
+    profile_id: &str,
+    vault_key: Option<&[u8; 32]>,
+) -> Vec<Vec<u8>> {
+    let mut candidates = Vec::new();
+
+    if let Ok(live_path) = vault_db_path(storage_paths, profile_id) {
+        if let Ok(live_bytes) = fs::read(live_path) {
+            candidates.push(live_bytes);
+        }
+    }
+
+    if let Ok(dir) = attachments_dir(storage_paths, profile_id) {
+        if let Ok(read_dir) = fs::read_dir(&dir) {
+            for entry in read_dir.flatten() {
+                if let Ok(bytes) = fs::read(entry.path()) {
+                    candidates.push(bytes);
+                }
+            }
+        }
+    }
+
+    if let Ok(dir) = backups_dir(storage_paths, profile_id) {
+        if let Ok(read_dir) = fs::read_dir(&dir) {
+            for entry in read_dir.flatten() {
+                let Ok(bytes) = fs::read(entry.path()) else { continue };
+                let Ok((metadata, payload)) = decode_archive(&bytes) else { continue };
+                if metadata.profile_id != profile_id || !metadata.chunk_hashes.is_empty() {
+                    continue;
+                }
+                if let Ok(db_bytes) = decode_payload_verified(&metadata, &payload, &dir, vault_key) {
+                    candidates.push(db_bytes);
+                }
+            }
+        }
+    }
+
+    candidates
+}
+
+fn prune_old_backups(sp: &crate::data::storage_paths::StoragePaths, profile_id: &str) -> Result<()> {
+    let settings = settings_service::get_settings(sp, profile_id)?;
+    let dir = backups_dir(sp, profile_id)?;
+    let cutoff = Utc::now() - chrono::Duration::days(settings.backup_retention_days);
+
+    let Ok(read_dir) = fs::read_dir(&dir) else { return Ok(()) };
+    for entry in read_dir.flatten() {
+        let Ok(bytes) = fs::read(entry.path()) else { continue };
+        let Ok((metadata, _)) = decode_archive(&bytes) else { continue };
+        let Ok(created_at) = chrono::DateTime::parse_from_rfc3339(&metadata.created_at) else { continue };
+        if created_at < cutoff {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    Ok(())
+}
+
+fn frequency_to_duration(frequency: BackupFrequency) -> chrono::Duration {
+    match frequency {
+        BackupFrequency::Daily => chrono::Duration::days(1),
+        BackupFrequency::Weekly => chrono::Duration::days(7),
+        BackupFrequency::Monthly => chrono::Duration::days(30),
+    }
+}
+
+/// Called periodically by the app; creates a backup only if backups are
+/// enabled and the configured frequency has elapsed since the most recent
+/// one. Returns the new backup's path, or `None` if nothing was due.
+pub fn backup_create_if_due_auto(state: &Arc<AppState>) -> Result<Option<String>> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let storage_paths = state.get_storage_paths()?;
+    let settings = settings_service::get_settings(&storage_paths, &profile_id)?;
+
+    if !settings.backups_enabled {
+        return Ok(None);
+    }
+
+    let existing = backup_list(state)?;
+    let due = match existing.first() {
+        None => true,
+        Some(latest) => match chrono::DateTime::parse_from_rfc3339(&latest.created_at) {
+            Ok(last_created) => {
+                Utc::now() - last_created.with_timezone(&Utc) >= frequency_to_duration(settings.backup_frequency)
+            }
+            Err(_) => true,
+        },
+    };
+
+    if !due {
+        return Ok(None);
+    }
+
+    backup_create(state, None, true).map(Some)
+}
+
+/// Decrypts a backup archive into a uniquely named temp vault DB file so the
+/// caller can inspect/import it before committing to overwrite anything.
+pub fn decrypt_backup_to_temp(state: &Arc<AppState>, backup_path: String) -> Result<String> {
+    let bytes = fs::read(&backup_path).map_err(|_| ErrorCodeString::new("BACKUP_READ_FAILED"))?;
+    let (metadata, encrypted_payload) = decode_archive(&bytes)?;
+    let storage_paths = state.get_storage_paths()?;
+    let vault_key = resolve_vault_key_for_archive(state, &storage_paths, &metadata)?;
+    let db_bytes = decode_payload_verified(
+        &metadata,
+        &encrypted_payload,
+        &chunks_dir(&storage_paths, &metadata.profile_id)?,
+        vault_key.as_ref(),
+    )?;
+
+    let temp_id = Uuid::new_v4().to_string();
+    let temp_dir = storage_paths.app_dir().join("tmp").join("backup-restore");
+    fs::create_dir_all(&temp_dir).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+    let temp_path = temp_dir.join(format!("{temp_id}.db"));
+    write_atomic(&temp_path, &db_bytes).map_err(|_| ErrorCodeString::new("BACKUP_WRITE_FAILED"))?;
+
+    Ok(temp_id)
 }
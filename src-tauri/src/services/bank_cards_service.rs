@@ -32,8 +32,8 @@ pub fn list_bank_cards_summary(state: &Arc<AppState>) -> Result<Vec<BankCardSumm
     repo_impl::list_bank_cards_summary(
         state,
         &profile_id,
-        &settings.default_sort_field,
-        &settings.default_sort_direction,
+        settings.default_sort_field,
+        settings.default_sort_direction,
     )
 }
 
@@ -1,34 +1,31 @@
 use chrono::Utc;
 use std::fs;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::app_state::AppState;
+use crate::data::crypto::totp;
 use crate::data::profiles::paths::attachment_file_path;
 use crate::data::sqlite::repo_impl;
 use crate::error::{ErrorCodeString, Result};
+use crate::services::oplog_service::{self, VaultOperation};
 use crate::services::security_service;
 use crate::services::settings_service::get_settings;
 use crate::types::{
-    CreateDataCardInput, DataCard, DataCardSummary, MoveDataCardInput, SetDataCardFavoriteInput,
-    UpdateDataCardInput,
+    CreateDataCardInput, CustomFieldType, DataCard, DataCardSummary, MoveDataCardInput,
+    SetDataCardFavoriteInput, TotpCodeResult, UpdateDataCardInput,
 };
 
 fn require_logged_in(state: &Arc<AppState>) -> Result<String> {
-    let active_profile = state
-        .active_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-    let logged_in_profile = state
-        .logged_in_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-
-    match (active_profile, logged_in_profile) {
-        (Some(active), Some(logged)) if active == logged => Ok(active),
-        _ => Err(ErrorCodeString::new("VAULT_LOCKED")),
-    }
+    Ok(security_service::require_unlocked_active_profile(state)?.profile_id)
+}
+
+/// The vault key backing the current session, if the active profile has a
+/// password at all — mirrors `attachments_service`'s own vault-key lookup,
+/// used here so `update_datacard` can encrypt the password-history row it
+/// writes via `data::crypto::encrypted_value::EncryptedValue`.
+fn vault_key(state: &Arc<AppState>) -> Result<Option<[u8; 32]>> {
+    Ok(security_service::require_unlocked_active_profile(state)?.vault_key)
 }
 
 fn normalize_tags(tags: Vec<String>) -> Vec<String> {
@@ -53,8 +50,8 @@ pub fn list_datacards(state: &Arc<AppState>) -> Result<Vec<DataCard>> {
         state,
         &profile_id,
         false,
-        &settings.default_sort_field,
-        &settings.default_sort_direction,
+        settings.default_sort_field,
+        settings.default_sort_direction,
     )
 }
 
@@ -65,8 +62,8 @@ pub fn list_datacards_summary(state: &Arc<AppState>) -> Result<Vec<DataCardSumma
     repo_impl::list_datacards_summary(
         state,
         &profile_id,
-        &settings.default_sort_field,
-        &settings.default_sort_direction,
+        settings.default_sort_field,
+        settings.default_sort_direction,
     )
 }
 
@@ -95,6 +92,11 @@ pub fn create_datacard(input: CreateDataCardInput, state: &Arc<AppState>) -> Res
         });
 
     let created = repo_impl::create_datacard(state, &profile_id, &sanitized)?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::DataCardUpserted { id: created.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(created)
 }
@@ -118,7 +120,20 @@ pub fn update_datacard(input: UpdateDataCardInput, state: &Arc<AppState>) -> Res
             }
         });
 
-    let updated = repo_impl::update_datacard(state, &profile_id, &sanitized)?;
+    let storage_paths = state.get_storage_paths()?;
+    let settings = get_settings(&storage_paths, &profile_id)?;
+    let updated = repo_impl::update_datacard(
+        state,
+        &profile_id,
+        &sanitized,
+        vault_key(state)?,
+        settings.password_history_retention_count,
+    )?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::DataCardUpserted { id: sanitized.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(updated)
 }
@@ -126,6 +141,11 @@ pub fn update_datacard(input: UpdateDataCardInput, state: &Arc<AppState>) -> Res
 pub fn move_datacard_to_folder(input: MoveDataCardInput, state: &Arc<AppState>) -> Result<bool> {
     let profile_id = require_logged_in(state)?;
     let moved = repo_impl::move_datacard(state, &profile_id, &input.id, &input.folder_id)?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::DataCardUpserted { id: input.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(moved)
 }
@@ -138,6 +158,7 @@ pub fn delete_datacard(id: String, state: &Arc<AppState>) -> Result<bool> {
         let now = Utc::now().to_rfc3339();
         repo_impl::soft_delete_datacard(state, &profile_id, &id, &now)?;
         repo_impl::soft_delete_attachments_by_datacard(state, &profile_id, &id, &now)?;
+        oplog_service::record(state, &profile_id, &VaultOperation::DataCardDeleted { id: id.clone() })?;
         security_service::persist_active_vault(state)?;
         Ok(true)
     } else {
@@ -159,6 +180,7 @@ pub fn restore_datacard(id: String, state: &Arc<AppState>) -> Result<bool> {
     let profile_id = require_logged_in(state)?;
     repo_impl::restore_datacard(state, &profile_id, &id)?;
     repo_impl::restore_attachments_by_datacard(state, &profile_id, &id)?;
+    oplog_service::record(state, &profile_id, &VaultOperation::DataCardUpserted { id: id.clone() })?;
     security_service::persist_active_vault(state)?;
     Ok(true)
 }
@@ -176,17 +198,26 @@ fn purge_datacard_with_attachments(
     let attachments = repo_impl::list_all_attachments_by_datacard(state, profile_id, id)?;
     let storage_paths = state.get_storage_paths()?;
     for attachment in attachments {
-        let file_path = attachment_file_path(&storage_paths, profile_id, &attachment.id)?;
-        let _ = fs::remove_file(file_path);
         if let Err(err) = repo_impl::purge_attachment(state, profile_id, &attachment.id) {
             if err.code == "ATTACHMENT_NOT_FOUND" {
                 continue;
             }
             return Err(err);
         }
+
+        // The blob is content-addressed and may be shared with an
+        // attachment on another datacard; only remove it once this was the
+        // last row pointing at that hash.
+        let remaining =
+            repo_impl::count_attachments_by_content_hash(state, profile_id, &attachment.content_hash)?;
+        if remaining == 0 {
+            let file_path = attachment_file_path(&storage_paths, profile_id, &attachment.content_hash)?;
+            let _ = fs::remove_file(file_path);
+        }
     }
 
     let purged = repo_impl::purge_datacard(state, profile_id, id)?;
+    oplog_service::record(state, profile_id, &VaultOperation::DataCardDeleted { id: id.to_string() })?;
     security_service::persist_active_vault(state)?;
     Ok(purged)
 }
@@ -197,6 +228,41 @@ pub fn set_datacard_favorite(
 ) -> Result<bool> {
     let profile_id = require_logged_in(state)?;
     let updated = repo_impl::set_datacard_favorite(state, &profile_id, &input)?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::DataCardUpserted { id: input.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(updated)
 }
+
+/// Computes the live TOTP code for one of a datacard's `Totp`-typed custom
+/// fields, identified by `field_key`. Unlike `DataCard::totp_uri` (the
+/// single per-card secret used by autofill), a card can carry several TOTP
+/// custom fields side by side, so the caller has to say which one.
+pub fn get_custom_field_totp_code(
+    datacard_id: String,
+    field_key: String,
+    state: &Arc<AppState>,
+) -> Result<TotpCodeResult> {
+    let profile_id = require_logged_in(state)?;
+    let card = repo_impl::get_datacard(state, &profile_id, &datacard_id)?;
+
+    let field = card
+        .custom_fields
+        .iter()
+        .find(|f| f.key == field_key && f.field_type == CustomFieldType::Totp)
+        .ok_or_else(|| ErrorCodeString::new("TOTP_FIELD_NOT_FOUND"))?;
+
+    let params = totp::parse_secret_or_uri(&field.value)?;
+    let unix_seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let code = totp::generate(&params, unix_seconds)?;
+    Ok(TotpCodeResult {
+        code: code.code,
+        seconds_remaining: code.seconds_remaining,
+    })
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::sync::Arc;
 
@@ -7,26 +8,16 @@ use crate::app_state::AppState;
 use crate::data::profiles::paths::attachment_file_path;
 use crate::data::sqlite::repo_impl;
 use crate::error::{ErrorCodeString, Result};
+use crate::services::oplog_service::{self, VaultOperation};
 use crate::services::security_service;
 use crate::services::settings_service::get_settings;
-use crate::types::{CreateFolderInput, Folder, MoveFolderInput, RenameFolderInput};
+use crate::types::{
+    CreateFolderInput, Folder, FolderBatchItemResult, MoveFolderInput, MoveFoldersInput,
+    RenameFolderInput,
+};
 
 fn require_logged_in(state: &Arc<AppState>) -> Result<String> {
-    let active_profile = state
-        .active_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-    let logged_in_profile = state
-        .logged_in_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-
-    match (active_profile, logged_in_profile) {
-        (Some(active), Some(logged)) if active == logged => Ok(active),
-        _ => Err(ErrorCodeString::new("VAULT_LOCKED")),
-    }
+    Ok(security_service::require_unlocked_active_profile(state)?.profile_id)
 }
 
 pub fn list_folders(state: &Arc<AppState>) -> Result<Vec<Folder>> {
@@ -41,6 +32,11 @@ pub fn create_folder(input: CreateFolderInput, state: &Arc<AppState>) -> Result<
         return Err(ErrorCodeString::new("FOLDER_NAME_REQUIRED"));
     }
     let folder = repo_impl::create_folder(state, &profile_id, name, &input.parent_id)?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::FolderUpserted { id: folder.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(folder)
 }
@@ -52,6 +48,11 @@ pub fn rename_folder(input: RenameFolderInput, state: &Arc<AppState>) -> Result<
         return Err(ErrorCodeString::new("FOLDER_NAME_REQUIRED"));
     }
     let renamed = repo_impl::rename_folder(state, &profile_id, &input.id, name)?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::FolderUpserted { id: input.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(renamed)
 }
@@ -59,6 +60,11 @@ pub fn rename_folder(input: RenameFolderInput, state: &Arc<AppState>) -> Result<
 pub fn move_folder(input: MoveFolderInput, state: &Arc<AppState>) -> Result<bool> {
     let profile_id = require_logged_in(state)?;
     let moved = repo_impl::move_folder(state, &profile_id, &input.id, &input.parent_id)?;
+    oplog_service::record(
+        state,
+        &profile_id,
+        &VaultOperation::FolderUpserted { id: input.id.clone() },
+    )?;
     security_service::persist_active_vault(state)?;
     Ok(moved)
 }
@@ -72,6 +78,7 @@ pub fn delete_folder_only(id: String, state: &Arc<AppState>) -> Result<bool> {
 
     repo_impl::move_datacards_to_root(state, &profile_id, &id)?;
     let deleted = repo_impl::purge_folder(state, &profile_id, &id)?;
+    oplog_service::record(state, &profile_id, &VaultOperation::FolderDeleted { id: id.clone() })?;
     security_service::persist_active_vault(state)?;
     Ok(deleted)
 }
@@ -83,7 +90,8 @@ pub fn delete_folder_and_cards(id: String, state: &Arc<AppState>) -> Result<bool
         return Err(ErrorCodeString::new("FOLDER_IS_SYSTEM"));
     }
 
-    let settings = get_settings(&state.storage_paths, &profile_id)?;
+    let storage_paths = state.get_storage_paths()?;
+    let settings = get_settings(&storage_paths, &profile_id)?;
 
     if settings.soft_delete_enabled {
         let now = Utc::now().to_rfc3339();
@@ -101,20 +109,219 @@ pub fn delete_folder_and_cards(id: String, state: &Arc<AppState>) -> Result<bool
                 repo_impl::list_all_attachments_by_datacard(state, &profile_id, &datacard_id)?;
 
             for attachment in attachments {
-                let file_path =
-                    attachment_file_path(&state.storage_paths, &profile_id, &attachment.id);
-                let _ = fs::remove_file(file_path);
                 if let Err(err) = repo_impl::purge_attachment(state, &profile_id, &attachment.id) {
                     if err.code != "ATTACHMENT_NOT_FOUND" {
                         return Err(err);
                     }
                 }
+
+                let remaining = repo_impl::count_attachments_by_content_hash(
+                    state,
+                    &profile_id,
+                    &attachment.content_hash,
+                )?;
+                if remaining == 0 {
+                    let file_path =
+                        attachment_file_path(&storage_paths, &profile_id, &attachment.content_hash)?;
+                    let _ = fs::remove_file(file_path);
+                }
             }
         }
         repo_impl::purge_datacards_in_folder(state, &profile_id, &id)?;
     }
 
     let deleted = repo_impl::purge_folder(state, &profile_id, &id)?;
+    oplog_service::record(state, &profile_id, &VaultOperation::FolderDeleted { id: id.clone() })?;
     security_service::persist_active_vault(state)?;
     Ok(deleted)
 }
+
+/// Moves a folder (and, if soft delete is on, its cards) to the trash.
+/// Mirrors `datacards_service::delete_datacard`'s soft/hard split, except
+/// here the folder row itself is only ever soft-deleted — purging it
+/// outright is `purge_folder`'s job, since a purged folder can't be
+/// `restore_folder`d back.
+pub fn delete_folder(id: String, state: &Arc<AppState>) -> Result<bool> {
+    let profile_id = require_logged_in(state)?;
+    let folder = repo_impl::get_folder(state, &profile_id, &id)?;
+    if folder.is_system {
+        return Err(ErrorCodeString::new("FOLDER_IS_SYSTEM"));
+    }
+
+    let storage_paths = state.get_storage_paths()?;
+    let settings = get_settings(&storage_paths, &profile_id)?;
+
+    if settings.soft_delete_enabled {
+        repo_impl::soft_delete_folder(state, &profile_id, &id)?;
+        repo_impl::soft_delete_datacards_in_folder(state, &profile_id, &id)?;
+    } else {
+        return purge_folder_with_cards(state, &profile_id, &id);
+    }
+
+    oplog_service::record(state, &profile_id, &VaultOperation::FolderDeleted { id: id.clone() })?;
+    security_service::persist_active_vault(state)?;
+    Ok(true)
+}
+
+pub fn list_deleted_folders(state: &Arc<AppState>) -> Result<Vec<Folder>> {
+    let profile_id = require_logged_in(state)?;
+    repo_impl::list_deleted_folders(state, &profile_id)
+}
+
+pub fn restore_folder(id: String, state: &Arc<AppState>) -> Result<bool> {
+    let profile_id = require_logged_in(state)?;
+    repo_impl::restore_folder(state, &profile_id, &id)?;
+    repo_impl::restore_datacards_in_folder(state, &profile_id, &id)?;
+    oplog_service::record(state, &profile_id, &VaultOperation::FolderUpserted { id: id.clone() })?;
+    security_service::persist_active_vault(state)?;
+    Ok(true)
+}
+
+/// Permanently removes a folder and everything still in it (datacards and
+/// their attachments, whether or not they were already trashed).
+pub fn purge_folder(id: String, state: &Arc<AppState>) -> Result<bool> {
+    let profile_id = require_logged_in(state)?;
+    purge_folder_with_cards(state, &profile_id, &id)
+}
+
+fn purge_folder_with_cards(state: &Arc<AppState>, profile_id: &str, id: &str) -> Result<bool> {
+    let storage_paths = state.get_storage_paths()?;
+    let datacard_ids = repo_impl::list_datacard_ids_in_folder(state, profile_id, id, true)?;
+    for datacard_id in datacard_ids {
+        let attachments = repo_impl::list_all_attachments_by_datacard(state, profile_id, &datacard_id)?;
+        for attachment in attachments {
+            if let Err(err) = repo_impl::purge_attachment(state, profile_id, &attachment.id) {
+                if err.code != "ATTACHMENT_NOT_FOUND" {
+                    return Err(err);
+                }
+            }
+
+            let remaining =
+                repo_impl::count_attachments_by_content_hash(state, profile_id, &attachment.content_hash)?;
+            if remaining == 0 {
+                let file_path = attachment_file_path(&storage_paths, profile_id, &attachment.content_hash)?;
+                let _ = fs::remove_file(file_path);
+            }
+        }
+    }
+    repo_impl::purge_datacards_in_folder(state, profile_id, id)?;
+
+    let purged = repo_impl::purge_folder(state, profile_id, id)?;
+    oplog_service::record(state, profile_id, &VaultOperation::FolderDeleted { id: id.to_string() })?;
+    security_service::persist_active_vault(state)?;
+    Ok(purged)
+}
+
+/// Runs `op` over every id in `ids`, turning each outcome into a
+/// `FolderBatchItemResult` instead of failing the whole call on the first
+/// error. Used by every `*_folders` batch entry point below except
+/// `move_folders`, which validates all ids up front instead.
+fn run_batch<F>(ids: Vec<String>, op: F) -> Vec<FolderBatchItemResult>
+where
+    F: Fn(&str) -> Result<bool>,
+{
+    ids.into_iter()
+        .map(|id| match op(&id) {
+            Ok(_) => FolderBatchItemResult {
+                id,
+                success: true,
+                error_code: None,
+            },
+            Err(err) => FolderBatchItemResult {
+                id,
+                success: false,
+                error_code: Some(err.code),
+            },
+        })
+        .collect()
+}
+
+/// Finds every id in `ids` that moving into `new_parent_id` would make a
+/// descendant of itself — i.e. every id that appears on `new_parent_id`'s
+/// ancestor chain in the pre-move tree, or that equals `new_parent_id`
+/// itself. Used to keep `move_folders` all-or-nothing: if this returns
+/// anything, nothing in the batch is moved.
+fn folders_that_would_cycle(
+    ids: &[String],
+    new_parent_id: &Option<String>,
+    folders: &[Folder],
+) -> Vec<String> {
+    let parent_of: HashMap<&str, Option<&str>> = folders
+        .iter()
+        .map(|f| (f.id.as_str(), f.parent_id.as_deref()))
+        .collect();
+
+    let mut ancestors = Vec::new();
+    let mut current = new_parent_id.as_deref();
+    while let Some(ancestor_id) = current {
+        ancestors.push(ancestor_id.to_string());
+        current = parent_of.get(ancestor_id).copied().flatten();
+    }
+
+    ids.iter()
+        .filter(|id| Some(id.as_str()) == new_parent_id.as_deref() || ancestors.contains(id))
+        .cloned()
+        .collect()
+}
+
+/// Moves every id in `ids` to `parent_id` as a single all-or-nothing
+/// operation: if moving any id there would create a cycle (moving a
+/// folder into its own descendant), nothing in the batch is moved.
+pub fn move_folders(
+    input: MoveFoldersInput,
+    state: &Arc<AppState>,
+) -> Result<Vec<FolderBatchItemResult>> {
+    let profile_id = require_logged_in(state)?;
+    let folders = repo_impl::list_folders(state, &profile_id)?;
+    let cycles = folders_that_would_cycle(&input.ids, &input.parent_id, &folders);
+    if !cycles.is_empty() {
+        return Ok(input
+            .ids
+            .into_iter()
+            .map(|id| {
+                let is_cycle = cycles.contains(&id);
+                FolderBatchItemResult {
+                    id,
+                    success: false,
+                    error_code: Some(
+                        if is_cycle {
+                            "FOLDER_MOVE_CYCLE"
+                        } else {
+                            "FOLDER_MOVE_BATCH_ABORTED"
+                        }
+                        .to_string(),
+                    ),
+                }
+            })
+            .collect());
+    }
+
+    let results = run_batch(input.ids, |id| {
+        let moved = repo_impl::move_folder(state, &profile_id, id, &input.parent_id)?;
+        oplog_service::record(state, &profile_id, &VaultOperation::FolderUpserted { id: id.to_string() })?;
+        Ok(moved)
+    });
+    security_service::persist_active_vault(state)?;
+    Ok(results)
+}
+
+pub fn delete_folders(ids: Vec<String>, state: &Arc<AppState>) -> Result<Vec<FolderBatchItemResult>> {
+    require_logged_in(state)?;
+    let results = run_batch(ids, |id| delete_folder(id.to_string(), state));
+    Ok(results)
+}
+
+pub fn restore_folders(
+    ids: Vec<String>,
+    state: &Arc<AppState>,
+) -> Result<Vec<FolderBatchItemResult>> {
+    require_logged_in(state)?;
+    let results = run_batch(ids, |id| restore_folder(id.to_string(), state));
+    Ok(results)
+}
+
+pub fn purge_folders(ids: Vec<String>, state: &Arc<AppState>) -> Result<Vec<FolderBatchItemResult>> {
+    require_logged_in(state)?;
+    let results = run_batch(ids, |id| purge_folder(id.to_string(), state));
+    Ok(results)
+}
@@ -0,0 +1,324 @@
+//! Bulk-imports a directory tree from the filesystem: every subdirectory
+//! becomes a folder (nested the same way on disk), and every regular file
+//! becomes a datacard with that file attached. Meant for migrating a pile
+//! of existing documents/secrets into the vault in one pass rather than
+//! dragging files in one at a time.
+//!
+//! Runs as one long `repo_impl` transaction-by-convention rather than going
+//! through `folders_service`/`datacards_service`/`attachments_service`: each
+//! of those persists the whole (possibly large) vault to disk after every
+//! single mutation, which would make an import of a few hundred files do a
+//! few hundred full vault serializations. Here we write every row directly
+//! and call `security_service::persist_active_vault` exactly once, after
+//! the whole tree has been walked.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::data::crypto::cipher;
+use crate::data::profiles::paths::attachment_file_path;
+use crate::data::sqlite::repo_impl;
+use crate::error::{ErrorCodeString, Result};
+use crate::services::oplog_service::{self, VaultOperation};
+use crate::services::security_service;
+use crate::types::{AttachmentMeta, CreateDataCardInput};
+
+/// `attachments_service` has no size ceiling of its own any more — its
+/// storage path now streams in `FRAME_SIZE` chunks regardless of total
+/// size — but bulk import still reads each entry whole before handing it
+/// off, so this cap stays here to bound that in-memory read.
+const MAX_IMPORT_FILE_SIZE_BYTES: u64 = 50 * 1024 * 1024;
+
+fn require_logged_in(state: &Arc<AppState>) -> Result<String> {
+    Ok(security_service::require_unlocked_active_profile(state)?.profile_id)
+}
+
+fn content_hash(plaintext: &[u8]) -> String {
+    Sha256::digest(plaintext)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Same magic-byte table as `attachments_service::sniff_mime_type`,
+/// duplicated rather than shared — see `normalize_tags` in
+/// `datacards_service`/`bank_cards_service` for the precedent of keeping
+/// small per-file helpers local instead of a shared utils module.
+fn sniff_mime_type(bytes: &[u8], file_name: &str) -> String {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"RIFF", "image/webp"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+
+    for (magic, mime) in SIGNATURES {
+        if bytes.starts_with(magic) {
+            return mime.to_string();
+        }
+    }
+
+    if bytes.iter().take(512).all(|b| *b != 0) {
+        if let Some(ext) = Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+        {
+            match ext.as_str() {
+                "txt" | "md" | "csv" | "log" => return "text/plain".to_string(),
+                "json" => return "application/json".to_string(),
+                "xml" => return "application/xml".to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    "application/octet-stream".to_string()
+}
+
+fn source_mtime_rfc3339(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(chrono::DateTime::<Utc>::from(modified).to_rfc3339())
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImportItemResult {
+    pub path: String,
+    pub success: bool,
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImportDirectoryReport {
+    pub folders_created: u32,
+    pub datacards_created: u32,
+    /// One entry per path the import couldn't bring in, so a bad file or
+    /// two doesn't sink the rest of the tree; successes aren't listed here,
+    /// only `folders_created`/`datacards_created` are incremented for them.
+    pub skipped: Vec<ImportItemResult>,
+}
+
+fn title_from_file_name(file_name: &str) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file_name)
+        .trim();
+    if stem.is_empty() {
+        file_name.to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+fn write_attachment_blob(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    hash: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let storage_paths = state.get_storage_paths()?;
+    let file_path = attachment_file_path(&storage_paths, profile_id, hash)?;
+    if file_path.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))?;
+    }
+
+    let vault_key = security_service::require_unlocked_active_profile(state)?.vault_key;
+
+    if let Some(key) = vault_key {
+        let encrypted = cipher::encrypt_attachment_blob(profile_id, hash, &key, bytes)?;
+        fs::write(&file_path, &encrypted).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))
+    } else {
+        fs::write(&file_path, bytes).map_err(|_| ErrorCodeString::new("ATTACHMENT_WRITE_FAILED"))
+    }
+}
+
+/// Imports a single file as a new datacard (titled after the file's stem)
+/// with the file attached, reusing the same content-addressed storage and
+/// dedup-on-write behavior as `attachments_service::add_attachment_from_path`.
+fn import_file(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    folder_id: Option<String>,
+    path: &Path,
+) -> Result<()> {
+    let metadata = fs::metadata(path).map_err(|_| ErrorCodeString::new("IMPORT_ENTRY_UNREADABLE"))?;
+    if metadata.len() > MAX_IMPORT_FILE_SIZE_BYTES {
+        return Err(ErrorCodeString::new("IMPORT_FILE_TOO_LARGE"));
+    }
+    let bytes = fs::read(path).map_err(|_| ErrorCodeString::new("IMPORT_FILE_READ_FAILED"))?;
+
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("untitled")
+        .to_string();
+
+    let datacard = repo_impl::create_datacard(
+        state,
+        profile_id,
+        &CreateDataCardInput {
+            title: title_from_file_name(&file_name),
+            url: None,
+            email: None,
+            username: None,
+            mobile_phone: None,
+            note: None,
+            tags: Vec::new(),
+            password: None,
+            bank_card: None,
+            custom_fields: Vec::new(),
+            folder_id,
+            uri_match: Default::default(),
+            totp_uri: None,
+        },
+    )?;
+    oplog_service::record(
+        state,
+        profile_id,
+        &VaultOperation::DataCardUpserted { id: datacard.id.clone() },
+    )?;
+
+    let hash = content_hash(&bytes);
+    write_attachment_blob(state, profile_id, &hash, &bytes)?;
+
+    let now = Utc::now().to_rfc3339();
+    let meta = AttachmentMeta {
+        id: Uuid::new_v4().to_string(),
+        datacard_id: datacard.id,
+        file_name: file_name.clone(),
+        mime_type: Some(sniff_mime_type(&bytes, &file_name)),
+        byte_size: bytes.len() as i64,
+        content_hash: hash,
+        source_mtime: source_mtime_rfc3339(path),
+        created_at: now.clone(),
+        updated_at: now,
+        deleted_at: None,
+    };
+    repo_impl::insert_attachment(state, profile_id, &meta)
+}
+
+/// Walks `source_root` breadth-first, recreating each subdirectory as a
+/// folder under `target_folder_id` and importing each regular file as a
+/// datacard. Unreadable entries, oversized files, and non-regular files
+/// (symlinks, sockets, …) are recorded in `ImportDirectoryReport::skipped`
+/// instead of aborting the rest of the walk.
+pub fn import_directory(
+    source_root: String,
+    target_folder_id: Option<String>,
+    state: &Arc<AppState>,
+) -> Result<ImportDirectoryReport> {
+    let profile_id = require_logged_in(state)?;
+    let root = PathBuf::from(&source_root);
+    let root_metadata =
+        fs::metadata(&root).map_err(|_| ErrorCodeString::new("IMPORT_SOURCE_NOT_FOUND"))?;
+    if !root_metadata.is_dir() {
+        return Err(ErrorCodeString::new("IMPORT_SOURCE_NOT_A_DIRECTORY"));
+    }
+
+    let mut report = ImportDirectoryReport {
+        folders_created: 0,
+        datacards_created: 0,
+        skipped: Vec::new(),
+    };
+
+    let mut folder_ids: HashMap<PathBuf, Option<String>> = HashMap::new();
+    folder_ids.insert(root.clone(), target_folder_id);
+
+    let mut dirs_to_visit = vec![root];
+    while let Some(dir) = dirs_to_visit.pop() {
+        let parent_folder_id = folder_ids.get(&dir).cloned().flatten();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                report.skipped.push(ImportItemResult {
+                    path: dir.to_string_lossy().to_string(),
+                    success: false,
+                    error_code: Some("IMPORT_DIR_READ_FAILED".to_string()),
+                });
+                continue;
+            }
+        };
+
+        // `read_dir` makes no ordering guarantee; sort so the folders a
+        // user ends up with match what they'd see in a file browser.
+        let mut children: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+        children.sort();
+
+        for child in children {
+            let metadata = match fs::symlink_metadata(&child) {
+                Ok(metadata) => metadata,
+                Err(_) => {
+                    report.skipped.push(ImportItemResult {
+                        path: child.to_string_lossy().to_string(),
+                        success: false,
+                        error_code: Some("IMPORT_ENTRY_UNREADABLE".to_string()),
+                    });
+                    continue;
+                }
+            };
+
+            if metadata.is_dir() {
+                let name = child
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("Untitled")
+                    .to_string();
+                match repo_impl::create_folder(state, &profile_id, &name, &parent_folder_id) {
+                    Ok(folder) => {
+                        oplog_service::record(
+                            state,
+                            &profile_id,
+                            &VaultOperation::FolderUpserted { id: folder.id.clone() },
+                        )?;
+                        report.folders_created += 1;
+                        folder_ids.insert(child.clone(), Some(folder.id));
+                        dirs_to_visit.push(child);
+                    }
+                    Err(err) => report.skipped.push(ImportItemResult {
+                        path: child.to_string_lossy().to_string(),
+                        success: false,
+                        error_code: Some(err.code),
+                    }),
+                }
+                continue;
+            }
+
+            if !metadata.is_file() {
+                report.skipped.push(ImportItemResult {
+                    path: child.to_string_lossy().to_string(),
+                    success: false,
+                    error_code: Some("IMPORT_ENTRY_NOT_A_FILE".to_string()),
+                });
+                continue;
+            }
+
+            match import_file(state, &profile_id, parent_folder_id.clone(), &child) {
+                Ok(()) => report.datacards_created += 1,
+                Err(err) => report.skipped.push(ImportItemResult {
+                    path: child.to_string_lossy().to_string(),
+                    success: false,
+                    error_code: Some(err.code),
+                }),
+            }
+        }
+    }
+
+    security_service::persist_active_vault(state)?;
+    Ok(report)
+}
@@ -0,0 +1,147 @@
+//! Records vault mutations to the append-only `oplog` table so another
+//! device signed into the same profile can replay just what changed since
+//! it last synced, instead of re-pulling the whole vault.
+//!
+//! Call `record` after a mutation commits successfully. Every few hundred
+//! entries `maybe_checkpoint` folds the log back down so it doesn't grow
+//! forever for long-lived profiles — but only up to the slowest device's
+//! acknowledged cursor (`acknowledge_synced`), so a device that's behind
+//! never has entries it still needs compacted out from under it.
+//!
+//! Each entry's payload is sealed under the active session's vault key via
+//! `cipher::encrypt_oplog_entry` — `cipher::encrypt_placeholder` only still
+//! runs for a passwordless profile, which has no key to seal it under.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::data::crypto::cipher;
+use crate::data::sqlite::backend::{self, VaultConnection};
+use crate::data::sqlite::oplog;
+use crate::error::{ErrorCodeString, Result};
+
+const CHECKPOINT_EVERY_N_OPS: i64 = 500;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum VaultOperation {
+    DataCardUpserted { id: String },
+    DataCardDeleted { id: String },
+    FolderUpserted { id: String },
+    FolderDeleted { id: String },
+}
+
+impl VaultOperation {
+    fn op_type(&self) -> &'static str {
+        match self {
+            VaultOperation::DataCardUpserted { .. } => "datacard_upserted",
+            VaultOperation::DataCardDeleted { .. } => "datacard_deleted",
+            VaultOperation::FolderUpserted { .. } => "folder_upserted",
+            VaultOperation::FolderDeleted { .. } => "folder_deleted",
+        }
+    }
+}
+
+/// Same connection `repo_impl`'s queries use for this profile — see
+/// `sqlite::backend::open_vault_connection`. The oplog table lives in the
+/// same vault file the rest of the schema does, so it needs the same
+/// pooled-file-vs-session-connection dispatch repo_impl already gets.
+fn open<'a>(state: &'a Arc<AppState>, profile_id: &str) -> Result<VaultConnection<'a>> {
+    backend::open_vault_connection(state, profile_id)
+}
+
+pub fn record(state: &Arc<AppState>, profile_id: &str, op: &VaultOperation) -> Result<()> {
+    let conn = open(state, profile_id)?;
+    // Read off the connection itself rather than calling
+    // `state.vault_key_for` separately: that would be a second, independent
+    // `vault_session` lock, and a lock/unlock racing between the two could
+    // leave this reading a different answer than `conn` actually came from.
+    let vault_key = conn.vault_key();
+    let json = serde_json::to_vec(op).map_err(|_| ErrorCodeString::new("OPLOG_SERIALIZE_FAILED"))?;
+    let op_type = op.op_type();
+    let created_at = Utc::now().to_rfc3339();
+    let encrypted = match vault_key {
+        Some(key) => cipher::encrypt_oplog_entry(profile_id, op_type, &created_at, &key, &json)?,
+        None => cipher::encrypt_placeholder(&json),
+    };
+    let seq = oplog::append(&conn, op_type, &encrypted, &created_at)?;
+
+    maybe_checkpoint(&conn, seq)?;
+    Ok(())
+}
+
+/// Checkpoints up to the slowest device's cursor (or `latest_seq` if no
+/// device has registered a cursor yet), so compaction never drops entries a
+/// known device hasn't replayed — it would otherwise have to fall back to a
+/// full re-sync the next time it connects.
+fn maybe_checkpoint(conn: &rusqlite::Connection, latest_seq: i64) -> Result<()> {
+    let last = oplog::last_checkpoint_seq(conn)?;
+    if latest_seq - last < CHECKPOINT_EVERY_N_OPS {
+        return Ok(());
+    }
+    let safe_seq = oplog::min_device_cursor(conn)?.unwrap_or(latest_seq);
+    if safe_seq > last {
+        oplog::checkpoint(conn, safe_seq, &Utc::now().to_rfc3339())?;
+    }
+    Ok(())
+}
+
+/// Records that `device_id` has fully replayed everything up to `seq`, so
+/// future checkpoints won't compact away entries it still needs.
+pub fn acknowledge_synced(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    device_id: &str,
+    seq: i64,
+) -> Result<()> {
+    let conn = open(state, profile_id)?;
+    oplog::set_device_cursor(&conn, device_id, seq, &Utc::now().to_rfc3339())
+}
+
+/// The sequence a given device last acknowledged, or `None` if it has never
+/// synced this vault and should start from the most recent checkpoint.
+pub fn cursor_for_device(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    device_id: &str,
+) -> Result<Option<i64>> {
+    let conn = open(state, profile_id)?;
+    oplog::device_cursor(&conn, device_id)
+}
+
+/// Decrypts and deserializes every operation recorded after `since_seq`, in
+/// order. Returns the new high-water mark alongside the operations so the
+/// caller can remember where to resume from next time.
+pub fn changes_since(
+    state: &Arc<AppState>,
+    profile_id: &str,
+    since_seq: i64,
+) -> Result<(i64, Vec<VaultOperation>)> {
+    let conn = open(state, profile_id)?;
+    // Same reason as `record`: read the key off this connection, not a
+    // separate `vault_key_for` lock that could race a lock/unlock in between.
+    let vault_key = conn.vault_key();
+    let entries = oplog::list_since(&conn, since_seq)?;
+    let latest = entries.last().map(|e| e.seq).unwrap_or(since_seq);
+
+    let mut ops = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let decrypted = match vault_key {
+            Some(key) => cipher::decrypt_oplog_entry(
+                profile_id,
+                &entry.op_type,
+                &entry.created_at,
+                &key,
+                &entry.payload,
+            )?,
+            None => cipher::decrypt_placeholder(&entry.payload),
+        };
+        let op: VaultOperation = serde_json::from_slice(&decrypted)
+            .map_err(|_| ErrorCodeString::new("OPLOG_DESERIALIZE_FAILED"))?;
+        ops.push(op);
+    }
+
+    Ok((latest, ops))
+}
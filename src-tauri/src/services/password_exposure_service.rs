@@ -0,0 +1,130 @@
+//! Local reuse detection plus optional HaveIBeenPwned breach lookups for a
+//! datacard's current password, without ever handing a usable password (or
+//! a hash of one reusable outside this process) to SQLite or the network.
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use reqwest::blocking::Client;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::data::sqlite::repo_impl;
+use crate::error::{ErrorCodeString, Result};
+use crate::services::security_service;
+use crate::types::{PasswordExposureReport, SortDirection, SortField};
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// HMAC-SHA256 of `password` under a key generated fresh for this one call
+/// (see `check_password_exposure`) — every password compared in that same
+/// reuse scan is hashed with the identical key, which is all that's needed
+/// for equal passwords to produce equal digests; the key itself never
+/// touches the database and is discarded the moment the scan returns, so
+/// there's no persistent per-profile secret to manage and no digest that
+/// could be replayed or correlated against a later scan.
+fn hmac_password(key: &[u8; 32], password: &str) -> Result<String> {
+    let mut mac = HmacSha256::new_from_slice(key).map_err(|_| ErrorCodeString::new("HMAC_KEY_INVALID"))?;
+    mac.update(password.as_bytes());
+    Ok(to_hex(&mac.finalize().into_bytes()))
+}
+
+/// Checks the datacard's current password for local reuse across every
+/// other datacard in the profile, and — only if `check_breach` is true —
+/// against HaveIBeenPwned via its k-anonymity range API: only the first 5
+/// hex characters of the password's SHA-1 digest ever leave the machine.
+pub fn check_password_exposure(
+    state: &Arc<AppState>,
+    datacard_id: &str,
+    check_breach: bool,
+) -> Result<PasswordExposureReport> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let target = repo_impl::get_datacard(state, &profile_id, datacard_id)?;
+    let password = target.password.clone().unwrap_or_default();
+
+    if password.is_empty() {
+        return Ok(PasswordExposureReport {
+            reused_datacard_ids: Vec::new(),
+            breach_count: None,
+        });
+    }
+
+    let mut hmac_key = [0u8; 32];
+    OsRng.fill_bytes(&mut hmac_key);
+    let target_digest = hmac_password(&hmac_key, &password)?;
+
+    let all_cards = repo_impl::list_datacards(
+        state,
+        &profile_id,
+        false,
+        SortField::default(),
+        SortDirection::default(),
+    )?;
+
+    let mut reused_datacard_ids = Vec::new();
+    for card in all_cards {
+        if card.id == target.id {
+            continue;
+        }
+        let Some(other_password) = card.password.as_deref() else {
+            continue;
+        };
+        if other_password.is_empty() {
+            continue;
+        }
+        if hmac_password(&hmac_key, other_password)? == target_digest {
+            reused_datacard_ids.push(card.id);
+        }
+    }
+
+    let breach_count = if check_breach {
+        Some(breach_hit_count(&password)?)
+    } else {
+        None
+    };
+
+    Ok(PasswordExposureReport {
+        reused_datacard_ids,
+        breach_count,
+    })
+}
+
+/// How many times HaveIBeenPwned has seen `password` in a breach, via the
+/// k-anonymity range API (https://haveibeenpwned.com/API/v3#PwnedPasswords):
+/// only `prefix` (the digest's first 5 hex chars) is sent, and the full
+/// suffix list that comes back is scanned locally for a match — the full
+/// SHA-1 digest never leaves this function, let alone the machine.
+fn breach_hit_count(password: &str) -> Result<u64> {
+    let digest = to_hex(&Sha1::digest(password.as_bytes())).to_uppercase();
+    let (prefix, suffix) = digest.split_at(5);
+
+    let client = Client::new();
+    let response = client
+        .get(format!("https://api.pwnedpasswords.com/range/{prefix}"))
+        .header("Add-Padding", "true")
+        .send()
+        .map_err(|_| ErrorCodeString::new("BREACH_CHECK_REQUEST_FAILED"))?;
+    if !response.status().is_success() {
+        return Err(ErrorCodeString::new("BREACH_CHECK_REQUEST_FAILED"));
+    }
+    let body = response
+        .text()
+        .map_err(|_| ErrorCodeString::new("BREACH_CHECK_REQUEST_FAILED"))?;
+
+    for line in body.lines() {
+        let Some((line_suffix, count)) = line.trim().split_once(':') else {
+            continue;
+        };
+        if line_suffix.eq_ignore_ascii_case(suffix) {
+            return count.trim().parse().map_err(|_| ErrorCodeString::new("BREACH_CHECK_REQUEST_FAILED"));
+        }
+    }
+
+    Ok(0)
+}
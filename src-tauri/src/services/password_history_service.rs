@@ -7,8 +7,8 @@ use crate::services::security_service;
 use crate::types::PasswordHistoryRow;
 
 pub fn list_history(state: &Arc<AppState>, datacard_id: &str) -> Result<Vec<PasswordHistoryRow>> {
-    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
-    repo_impl::list_password_history(state, &profile_id, datacard_id)
+    let session = security_service::require_unlocked_active_profile(state)?;
+    repo_impl::list_password_history(state, &session.profile_id, datacard_id, session.vault_key)
 }
 
 pub fn clear_history(state: &Arc<AppState>, datacard_id: &str) -> Result<()> {
@@ -1,14 +1,25 @@
-use crate::data::crypto::kdf::{derive_master_key, generate_kdf_salt};
-use crate::data::crypto::key_check;
-use crate::data::profiles::paths::{ensure_profile_dirs, kdf_salt_path};
+use std::fs;
+
+use uuid::Uuid;
+
+use crate::data::crypto::cipher;
+use crate::data::crypto::kdf::{calibrate_params, derive_master_key_versioned, read_params_file, write_params_file};
+use crate::data::crypto::{key_check, master_key, secret_store};
+use crate::data::fs::atomic_write::write_atomic;
+use crate::data::crypto::totp;
+use crate::data::profiles::paths::{
+    ensure_profile_dirs, kdf_salt_path, key_check_path, keychain_secret_path, profile_dir,
+    totp_secret_path,
+};
 use crate::data::profiles::registry;
 use crate::data::settings::config;
 use crate::data::sqlite::init::{init_database_passwordless, init_database_protected_encrypted};
+use crate::data::storage::vault_blob::{VaultBlobKind, VaultBlobStorage};
+use crate::data::storage::vault_chunk_store::{VaultChunkStore, VaultManifest};
 use crate::data::storage_paths::StoragePaths;
 use crate::error::{ErrorCodeString, Result};
 use crate::services::settings_service::get_settings;
 use crate::types::{ProfileMeta, ProfilesList};
-use std::fs;
 use zeroize::Zeroizing;
 
 pub fn list_profiles(sp: &StoragePaths) -> Result<ProfilesList> {
@@ -18,6 +29,7 @@ pub fn list_profiles(sp: &StoragePaths) -> Result<ProfilesList> {
 
 pub fn create_profile(
     sp: &StoragePaths,
+    blob_storage: &dyn VaultBlobStorage,
     name: &str,
     password: Option<String>,
 ) -> Result<ProfileMeta> {
@@ -32,13 +44,19 @@ pub fn create_profile(
     if is_passwordless {
         init_database_passwordless(sp, &profile.id)?;
     } else {
-        let salt = generate_kdf_salt();
-        fs::write(kdf_salt_path(sp, &profile.id)?, &salt)
-            .map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+        let params = calibrate_params();
+        write_params_file(&kdf_salt_path(sp, &profile.id)?, &params)?;
         let pwd = password.unwrap_or_default();
-        let key = Zeroizing::new(derive_master_key(&pwd, &salt)?);
-        key_check::create_key_check_file(sp, &profile.id, &key)?;
-        init_database_protected_encrypted(sp, &profile.id, &key)?;
+        let wrapping_key = Zeroizing::new(derive_master_key_versioned(&pwd, &params)?);
+        key_check::create_key_check_file(sp, &profile.id, &wrapping_key)?;
+
+        // The vault itself is encrypted under a random master key, which is
+        // in turn wrapped by the password-derived key. That indirection is
+        // what lets `change_master_password` re-wrap the master key on a
+        // password change without re-encrypting the whole vault.
+        let vault_key = master_key::generate_master_key();
+        master_key::write_master_key_wrapped_with_password(blob_storage, &profile.id, &wrapping_key, &vault_key)?;
+        init_database_protected_encrypted(sp, &profile.id, &vault_key)?;
     }
 
     let _ = get_settings(sp, &profile.id)?;
@@ -65,3 +83,257 @@ pub fn set_active_profile(sp: &StoragePaths, id: &str) -> Result<bool> {
     config::save_settings(sp, &settings)?;
     Ok(true)
 }
+
+/// Opts a profile into keychain-backed unlock: verifies `password` the same
+/// way a normal login would, then protects it under
+/// `data::crypto::secret_store` (OS keychain/Credential Manager/Secret
+/// Service) and marks `ProfileRecord::keychain_backed`. From then on,
+/// `security_service::unlock_from_keychain` can unlock the profile without
+/// the caller ever typing the password in again.
+pub fn store_profile_secret(sp: &StoragePaths, id: &str, password: &str) -> Result<()> {
+    let record = registry::get_profile(sp, id)?.ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    if !record.has_password {
+        return Err(ErrorCodeString::new("PROFILE_HAS_NO_PASSWORD"));
+    }
+    if !registry::verify_profile_password(sp, id, password)? {
+        return Err(ErrorCodeString::new("INVALID_PASSWORD"));
+    }
+
+    let protected = secret_store::protect(password.as_bytes(), Some(id.as_bytes()))?;
+    write_atomic(&keychain_secret_path(sp, id)?, &protected)
+        .map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+    registry::set_keychain_backed(sp, id, true)
+}
+
+/// Reads a profile's keychain-stored password back, re-validating it
+/// against `key_check` before handing it back — if the master password
+/// changed out from under the stored secret (e.g. `change_master_password`
+/// ran on another device and this one's keychain copy never got updated),
+/// callers should treat this the same as not having a stored secret at all
+/// rather than silently unlocking with a stale password.
+pub fn load_profile_secret(sp: &StoragePaths, id: &str) -> Result<String> {
+    let path = keychain_secret_path(sp, id)?;
+    if !path.exists() {
+        return Err(ErrorCodeString::new("KEYCHAIN_SECRET_MISSING"));
+    }
+
+    let protected = fs::read(&path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
+    let plaintext = secret_store::unprotect(&protected, Some(id.as_bytes()))?;
+    let password = String::from_utf8(plaintext).map_err(|_| ErrorCodeString::new("KEYCHAIN_SECRET_CORRUPTED"))?;
+
+    if !registry::verify_profile_password(sp, id, &password)? {
+        return Err(ErrorCodeString::new("KEYCHAIN_SECRET_STALE"));
+    }
+    Ok(password)
+}
+
+/// Opts a profile back out of keychain-backed unlock, removing the stored
+/// secret from disk. Idempotent: clearing a profile that was never
+/// keychain-backed just clears the registry flag.
+pub fn clear_profile_secret(sp: &StoragePaths, id: &str) -> Result<()> {
+    let path = keychain_secret_path(sp, id)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+    }
+    registry::set_keychain_backed(sp, id, false)
+}
+
+/// Enrolls a profile in TOTP-based second-factor unlock: generates a fresh
+/// secret, stores it OS-bound (same mechanism as `store_profile_secret`'s
+/// keychain-backed password, via `secret_store`) and returns the
+/// provisioning URI for QR display. Requires the current password, the
+/// same precondition `store_profile_secret` has, so enrollment can't be
+/// triggered by anyone who hasn't already unlocked the profile once.
+pub fn enroll_totp(sp: &StoragePaths, id: &str, password: &str) -> Result<String> {
+    let record = registry::get_profile(sp, id)?.ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    if !record.has_password {
+        return Err(ErrorCodeString::new("PROFILE_HAS_NO_PASSWORD"));
+    }
+    if !registry::verify_profile_password(sp, id, password)? {
+        return Err(ErrorCodeString::new("INVALID_PASSWORD"));
+    }
+
+    let secret = totp::generate_secret();
+    let protected = secret_store::protect(secret.as_bytes(), Some(id.as_bytes()))?;
+    write_atomic(&totp_secret_path(sp, id)?, &protected)
+        .map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+    registry::set_has_totp(sp, id, true)?;
+
+    Ok(totp::build_otpauth_uri("Password Manager", &record.name, &secret))
+}
+
+/// Opts a profile back out of TOTP second-factor unlock, removing the
+/// stored secret from disk. Idempotent, mirroring `clear_profile_secret`.
+pub fn disable_totp(sp: &StoragePaths, id: &str) -> Result<()> {
+    let path = totp_secret_path(sp, id)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_WRITE"))?;
+    }
+    registry::set_has_totp(sp, id, false)
+}
+
+/// Checks a login attempt's 6-digit token against the profile's enrolled
+/// TOTP secret, tolerating one step of clock skew either way. Callers
+/// should only reach this once `ProfileMeta.has_totp` is known to be true;
+/// a profile with nothing enrolled has no secret file to check against.
+pub fn verify_totp_token(sp: &StoragePaths, id: &str, token: &str) -> Result<bool> {
+    let path = totp_secret_path(sp, id)?;
+    if !path.exists() {
+        return Err(ErrorCodeString::new("TOTP_NOT_ENROLLED"));
+    }
+    let protected = fs::read(&path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
+    let secret_bytes = secret_store::unprotect(&protected, Some(id.as_bytes()))?;
+    let secret = String::from_utf8(secret_bytes).map_err(|_| ErrorCodeString::new("TOTP_SECRET_CORRUPTED"))?;
+
+    let params = totp::parse_secret_or_uri(&secret)?;
+    let unix_seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    totp::verify_with_skew(&params, token, unix_seconds)
+}
+
+/// Rotates a profile's master password with full re-encryption: unlike
+/// `security_service::change_master_password`, which only re-wraps the
+/// existing envelope master key and leaves the vault content alone, this
+/// decrypts the whole vault under a brand-new random master key and
+/// re-encrypts everything with it, so the old password's key material
+/// can't decrypt anything left on disk afterward.
+///
+/// Crash safety: every expensive and fallible step — decrypting the
+/// current vault, generating the new key, re-chunking and re-encrypting
+/// the plaintext — happens before any file the profile already depends on
+/// is touched. The new chunks are staged in a fresh sibling directory
+/// (see `VaultChunkStore::new_in_dir`) rather than `vault_chunks` itself,
+/// so a failure at any point up to here leaves the profile byte-for-byte
+/// as it was. Only once the new chunks, manifest, master key and KDF
+/// material are all ready does the commit phase run: the same
+/// `write_atomic`-backed calls `change_master_password` already relies on
+/// for the KDF salt, key-check file and wrapped master key, finishing
+/// with an atomic directory rename that swaps the staged chunks in for
+/// `vault_chunks` in one step. The commit phase also snapshots the salt,
+/// key-check file and wrapped master key it's about to overwrite, so a
+/// failure partway through restores the old envelope rather than leaving
+/// the profile unlockable by neither password.
+pub fn rotate_master_password(
+    sp: &StoragePaths,
+    blob_storage: &dyn VaultBlobStorage,
+    id: &str,
+    old_password: &str,
+    new_password: &str,
+) -> Result<bool> {
+    if new_password.is_empty() {
+        return Err(ErrorCodeString::new("PASSWORD_REQUIRED"));
+    }
+
+    let record = registry::get_profile(sp, id)?.ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    if !record.has_password {
+        return Err(ErrorCodeString::new("PROFILE_HAS_NO_PASSWORD"));
+    }
+
+    let salt_path = kdf_salt_path(sp, id)?;
+    let params = read_params_file(&salt_path)?;
+    let old_wrapping_key = Zeroizing::new(derive_master_key_versioned(old_password, &params)?);
+    if !key_check::verify_key_check_file(sp, id, &old_wrapping_key)? {
+        return Err(ErrorCodeString::new("INVALID_PASSWORD"));
+    }
+
+    let old_vault_key = Zeroizing::new(if blob_storage.exists(id, VaultBlobKind::MasterKey)? {
+        master_key::read_master_key_wrapped_with_password(blob_storage, id, &old_wrapping_key)?
+    } else {
+        *old_wrapping_key
+    });
+
+    if !blob_storage.exists(id, VaultBlobKind::VaultDb)? {
+        return Err(ErrorCodeString::new("VAULT_CORRUPTED"));
+    }
+    let encrypted = blob_storage.read_blob(id, VaultBlobKind::VaultDb)?;
+    let unwrapped = cipher::decrypt_vault_blob(id, &old_vault_key, &encrypted)
+        .map_err(|_| ErrorCodeString::new("VAULT_DECRYPT_FAILED"))?;
+
+    let old_chunk_store = VaultChunkStore::new(sp, id)?;
+    let plaintext = match serde_json::from_slice::<VaultManifest>(&unwrapped) {
+        Ok(manifest) => old_chunk_store.get_chunks(id, &old_vault_key, &manifest.chunk_hashes)?,
+        Err(_) => unwrapped,
+    };
+
+    // --- Staging: nothing below this point touches a file the profile
+    // already depends on until the commit phase further down.
+    let new_vault_key = master_key::generate_master_key();
+    let chunks_dir = profile_dir(sp, id)?.join("vault_chunks");
+    let staging_dir = profile_dir(sp, id)?.join(format!("vault_chunks.rotate.{}", Uuid::new_v4()));
+    let staging_store = VaultChunkStore::new_in_dir(staging_dir.clone());
+
+    let stage_result = staging_store
+        .put_chunks(id, &new_vault_key, &plaintext)
+        .and_then(|chunk_hashes| {
+            let manifest = VaultManifest { chunk_hashes };
+            let manifest_bytes = serde_json::to_vec(&manifest)
+                .map_err(|_| ErrorCodeString::new("VAULT_MANIFEST_SERIALIZE_FAILED"))?;
+            cipher::encrypt_vault_blob(id, &new_vault_key, &manifest_bytes)
+        });
+    let new_encrypted_manifest = match stage_result {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            let _ = fs::remove_dir_all(&staging_dir);
+            return Err(err);
+        }
+    };
+
+    let new_params = calibrate_params();
+    let new_wrapping_key = Zeroizing::new(derive_master_key_versioned(new_password, &new_params)?);
+
+    // --- Commit: every write from here is already atomic on its own
+    // (`write_atomic` under the hood), but the four of them together are
+    // not — a crash between, say, the new salt landing and the new
+    // key-check file landing would leave the profile unlockable by either
+    // password. Snapshot what each write is about to replace so a failure
+    // partway through can restore the old envelope instead of bricking it.
+    let key_check_path = key_check_path(sp, id)?;
+    let old_salt_bytes = fs::read(&salt_path).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_READ"))?;
+    let old_key_check_bytes =
+        fs::read(&key_check_path).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_READ"))?;
+    // Pre-envelope (pre-chunk3-1) profiles have no master-key blob at all
+    // yet — see the same `exists()` check above — so there's nothing to
+    // snapshot or restore for them here.
+    let old_master_key_bytes = if blob_storage.exists(id, VaultBlobKind::MasterKey)? {
+        Some(blob_storage.read_blob(id, VaultBlobKind::MasterKey)?)
+    } else {
+        None
+    };
+
+    let commit: Result<()> = (|| {
+        write_params_file(&salt_path, &new_params)?;
+        key_check::create_key_check_file(sp, id, &new_wrapping_key)?;
+        master_key::write_master_key_wrapped_with_password(blob_storage, id, &new_wrapping_key, &new_vault_key)?;
+        blob_storage.write_blob(id, VaultBlobKind::VaultDb, &new_encrypted_manifest)?;
+        Ok(())
+    })();
+
+    if let Err(err) = commit {
+        let _ = write_atomic(&salt_path, &old_salt_bytes);
+        let _ = write_atomic(&key_check_path, &old_key_check_bytes);
+        match &old_master_key_bytes {
+            Some(old_master_key_bytes) => {
+                let _ = blob_storage.write_blob(id, VaultBlobKind::MasterKey, old_master_key_bytes);
+            }
+            None => {
+                let _ = blob_storage.delete(id, VaultBlobKind::MasterKey);
+            }
+        }
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(err);
+    }
+
+    if chunks_dir.exists() {
+        let trash_dir = profile_dir(sp, id)?.join(format!("vault_chunks.old.{}", Uuid::new_v4()));
+        fs::rename(&chunks_dir, &trash_dir).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_WRITE"))?;
+        fs::rename(&staging_dir, &chunks_dir).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_WRITE"))?;
+        let _ = fs::remove_dir_all(&trash_dir);
+    } else {
+        fs::rename(&staging_dir, &chunks_dir).map_err(|_| ErrorCodeString::new("VAULT_STORAGE_WRITE"))?;
+    }
+
+    registry::set_has_password(sp, id, true)?;
+    Ok(true)
+}
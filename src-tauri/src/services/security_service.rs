@@ -7,12 +7,16 @@ use rusqlite::DatabaseName;
 use zeroize::{Zeroize, Zeroizing};
 
 use crate::app_state::{AppState, VaultSession};
-use crate::data::crypto::{cipher, kdf, key_check};
-use crate::data::profiles::paths::{kdf_salt_path, vault_db_path};
+use crate::data::crypto::kdf::KdfParams;
+use crate::data::crypto::{cipher, kdf, key_check, master_key};
+use crate::data::profiles::paths::kdf_salt_path;
 use crate::data::profiles::registry;
 use crate::data::sqlite::init::init_database_passwordless;
 use crate::data::sqlite::migrations;
 use crate::data::sqlite::pool::clear_pool;
+use crate::data::sqlite::repo_impl;
+use crate::data::storage::vault_blob::{VaultBlobKind, VaultBlobStorage};
+use crate::data::storage::vault_chunk_store::{VaultChunkStore, VaultManifest};
 use crate::error::{ErrorCodeString, Result};
 use crate::services::attachments_service;
 
@@ -35,32 +39,121 @@ fn owned_data_from_bytes(mut bytes: Vec<u8>) -> Result<OwnedData> {
     Ok(owned)
 }
 
+/// Transparently upgrades a profile's KDF params to the current
+/// calibration, on the already-verified password from a successful unlock.
+/// The new key_check file takes effect immediately; if the profile already
+/// has an envelope master key, it's re-wrapped under the new wrapping key
+/// in the same pass, so a KDF rehash never touches the vault blob itself.
+/// Best-effort: failures here don't block the unlock that's already
+/// succeeded.
+fn rehash_kdf_params(
+    storage_paths: &crate::data::storage_paths::StoragePaths,
+    blob_storage: &dyn VaultBlobStorage,
+    profile_id: &str,
+    password: &str,
+    salt_path: &std::path::Path,
+    old_wrapping_key: &[u8; 32],
+) -> Option<[u8; 32]> {
+    let new_params: KdfParams = kdf::calibrate_params();
+    let new_wrapping_key = kdf::derive_master_key_versioned(password, &new_params).ok()?;
+    kdf::write_params_file(salt_path, &new_params).ok()?;
+    key_check::create_key_check_file(storage_paths, profile_id, &new_wrapping_key).ok()?;
+
+    if let Ok(vault_key) =
+        master_key::read_master_key_wrapped_with_password(blob_storage, profile_id, old_wrapping_key)
+    {
+        let _ = master_key::write_master_key_wrapped_with_password(
+            blob_storage,
+            profile_id,
+            &new_wrapping_key,
+            &vault_key,
+        );
+    }
+
+    Some(new_wrapping_key)
+}
+
+/// A profile created before the envelope scheme existed has no
+/// `vault_key.bin`: its vault is still encrypted directly under the
+/// password-derived key. On a successful unlock, generate a real envelope
+/// master key, wrap it under the current wrapping key, and hand it back so
+/// the caller installs it as the live session key — the vault itself picks
+/// up the new key on its next ordinary persist, exactly like a KDF rehash.
+/// Best-effort: if this fails, the profile just stays on the legacy layout
+/// and gets another chance next login.
+fn migrate_legacy_vault_key_to_envelope(
+    blob_storage: &dyn VaultBlobStorage,
+    profile_id: &str,
+    wrapping_key: &[u8; 32],
+) -> Option<[u8; 32]> {
+    let vault_key = master_key::generate_master_key();
+    master_key::write_master_key_wrapped_with_password(blob_storage, profile_id, wrapping_key, &vault_key).ok()?;
+    Some(vault_key)
+}
+
 fn open_protected_vault_session(
     profile_id: &str,
     password: &str,
     storage_paths: &crate::data::storage_paths::StoragePaths,
     state: &Arc<AppState>,
 ) -> Result<()> {
+    let blob_storage = state.get_vault_blob_storage()?;
+
     let salt_path = kdf_salt_path(storage_paths, profile_id)?;
     if !salt_path.exists() {
         return Err(ErrorCodeString::new("KDF_SALT_MISSING"));
     }
-    let salt =
-        std::fs::read(&salt_path).map_err(|_| ErrorCodeString::new("PROFILE_STORAGE_READ"))?;
-    let key = Zeroizing::new(kdf::derive_master_key(password, &salt)?);
+    let params = kdf::read_params_file(&salt_path)?;
+    let mut wrapping_key = Zeroizing::new(kdf::derive_master_key_versioned(password, &params)?);
 
-    if !key_check::verify_key_check_file(storage_paths, profile_id, &key)? {
+    if !key_check::verify_key_check_file(storage_paths, profile_id, &wrapping_key)? {
         return Err(ErrorCodeString::new("INVALID_PASSWORD"));
     }
 
-    let vault_path = vault_db_path(storage_paths, profile_id)?;
-    if !vault_path.exists() {
+    if kdf::needs_rehash(&params) {
+        if let Some(new_wrapping_key) = rehash_kdf_params(
+            storage_paths,
+            blob_storage.as_ref(),
+            profile_id,
+            password,
+            &salt_path,
+            &wrapping_key,
+        ) {
+            wrapping_key = Zeroizing::new(new_wrapping_key);
+        }
+    }
+
+    // Legacy profiles (predating the envelope scheme) have no master-key
+    // blob: the password-derived key *is* the vault key until this login
+    // migrates it.
+    let is_legacy_vault_key = !blob_storage.exists(profile_id, VaultBlobKind::MasterKey)?;
+    let mut vault_key = Zeroizing::new(if is_legacy_vault_key {
+        *wrapping_key
+    } else {
+        master_key::read_master_key_wrapped_with_password(blob_storage.as_ref(), profile_id, &wrapping_key)?
+    });
+
+    if !blob_storage.exists(profile_id, VaultBlobKind::VaultDb)? {
         return Err(ErrorCodeString::new("VAULT_CORRUPTED"));
     }
-    let encrypted = cipher::read_encrypted_file(&vault_path)?;
-    let decrypted = cipher::decrypt_vault_blob(profile_id, &key, &encrypted)
+    let encrypted = blob_storage.read_blob(profile_id, VaultBlobKind::VaultDb)?;
+    let unwrapped = cipher::decrypt_vault_blob(profile_id, &vault_key, &encrypted)
         .map_err(|_| ErrorCodeString::new("VAULT_DECRYPT_FAILED"))?;
 
+    // Current profiles store a small manifest (ordered chunk hashes) here
+    // rather than the serialized database itself; a profile last persisted
+    // before chunked persistence existed still has the raw database bytes,
+    // which don't parse as a manifest. Either way the vault picks up the
+    // chunked layout on its next ordinary persist, exactly like a KDF
+    // rehash or an envelope migration.
+    let decrypted = match serde_json::from_slice::<VaultManifest>(&unwrapped) {
+        Ok(manifest) => {
+            let chunk_store = VaultChunkStore::new(storage_paths, profile_id)?;
+            chunk_store.get_chunks(profile_id, &vault_key, &manifest.chunk_hashes)?
+        }
+        Err(_) => unwrapped,
+    };
+
     let mut conn = rusqlite::Connection::open_in_memory()
         .map_err(|_| ErrorCodeString::new("DB_OPEN_FAILED"))?;
     let owned = owned_data_from_bytes(decrypted)?;
@@ -71,6 +164,14 @@ fn open_protected_vault_session(
     migrations::validate_core_schema(&conn)
         .map_err(|_| ErrorCodeString::new("VAULT_CORRUPTED"))?;
 
+    if is_legacy_vault_key {
+        if let Some(new_vault_key) =
+            migrate_legacy_vault_key_to_envelope(blob_storage.as_ref(), profile_id, &wrapping_key)
+        {
+            vault_key = Zeroizing::new(new_vault_key);
+        }
+    }
+
     {
         let mut session = state
             .vault_session
@@ -80,20 +181,34 @@ fn open_protected_vault_session(
         *session = Some(VaultSession {
             profile_id: profile_id.to_string(),
             conn,
-            key,
+            key: vault_key,
         });
     }
 
     Ok(())
 }
 
-pub fn login_vault(id: &str, password: Option<String>, state: &Arc<AppState>) -> Result<bool> {
+pub fn login_vault(
+    id: &str,
+    password: Option<String>,
+    totp_token: Option<String>,
+    state: &Arc<AppState>,
+) -> Result<bool> {
     let storage_paths = state.get_storage_paths()?;
     let profile = registry::get_profile(&storage_paths, id)?
         .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
     let pwd = password.unwrap_or_default();
     let is_passwordless = !profile.has_password;
 
+    if profile.has_totp {
+        let token = totp_token
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| ErrorCodeString::new("TOTP_TOKEN_REQUIRED"))?;
+        if !crate::services::profiles_service::verify_totp_token(&storage_paths, id, &token)? {
+            return Err(ErrorCodeString::new("TOTP_TOKEN_INVALID"));
+        }
+    }
+
     if is_passwordless {
         init_database_passwordless(&storage_paths, id)?;
     } else {
@@ -106,6 +221,82 @@ pub fn login_vault(id: &str, password: Option<String>, state: &Arc<AppState>) ->
     Ok(true)
 }
 
+/// Unlocks a profile using its OS-keychain-stored password instead of one
+/// typed in by the caller — what the browser extension uses so it can
+/// offer autofill without ever prompting for the master password itself.
+/// `profiles_service::load_profile_secret` re-validates the stored password
+/// against `key_check` before this ever reaches `login_vault`, so a stale
+/// or tampered keychain entry fails the same way a wrong password would.
+/// A profile with TOTP enrolled can't be unlocked this way at all — there's
+/// no prompt to collect a token from — and `login_vault` rejects it with
+/// `TOTP_TOKEN_REQUIRED` exactly as if the caller had omitted it.
+pub fn unlock_from_keychain(id: &str, state: &Arc<AppState>) -> Result<bool> {
+    let storage_paths = state.get_storage_paths()?;
+    let password = crate::services::profiles_service::load_profile_secret(&storage_paths, id)?;
+    login_vault(id, Some(password), None, state)
+}
+
+/// Changes the active profile's password in place, without re-encrypting
+/// the vault: verifies `current_password` the same way login does, then
+/// re-wraps the existing envelope master key under a freshly calibrated
+/// KDF derivation of `new_password`. A legacy profile (no envelope yet) is
+/// migrated to the envelope scheme as a side effect, since at that point
+/// we already have the plaintext master key (the old wrapping key) in
+/// hand and are about to write a fresh wrapped copy anyway.
+pub fn change_master_password(
+    current_password: &str,
+    new_password: &str,
+    state: &Arc<AppState>,
+) -> Result<bool> {
+    if new_password.is_empty() {
+        return Err(ErrorCodeString::new("PASSWORD_REQUIRED"));
+    }
+
+    let storage_paths = state.get_storage_paths()?;
+    let profile_id = state
+        .active_profile
+        .lock()
+        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
+        .clone()
+        .ok_or_else(|| ErrorCodeString::new("VAULT_LOCKED"))?;
+
+    let profile = registry::get_profile(&storage_paths, &profile_id)?
+        .ok_or_else(|| ErrorCodeString::new("PROFILE_NOT_FOUND"))?;
+    if !profile.has_password {
+        return Err(ErrorCodeString::new("PROFILE_HAS_NO_PASSWORD"));
+    }
+
+    let blob_storage = state.get_vault_blob_storage()?;
+
+    let salt_path = kdf_salt_path(&storage_paths, &profile_id)?;
+    let params = kdf::read_params_file(&salt_path)?;
+    let old_wrapping_key = Zeroizing::new(kdf::derive_master_key_versioned(current_password, &params)?);
+
+    if !key_check::verify_key_check_file(&storage_paths, &profile_id, &old_wrapping_key)? {
+        return Err(ErrorCodeString::new("INVALID_PASSWORD"));
+    }
+
+    let vault_key = Zeroizing::new(if blob_storage.exists(&profile_id, VaultBlobKind::MasterKey)? {
+        master_key::read_master_key_wrapped_with_password(blob_storage.as_ref(), &profile_id, &old_wrapping_key)?
+    } else {
+        *old_wrapping_key
+    });
+
+    let new_params = kdf::calibrate_params();
+    let new_wrapping_key = Zeroizing::new(kdf::derive_master_key_versioned(new_password, &new_params)?);
+
+    kdf::write_params_file(&salt_path, &new_params)?;
+    key_check::create_key_check_file(&storage_paths, &profile_id, &new_wrapping_key)?;
+    master_key::write_master_key_wrapped_with_password(
+        blob_storage.as_ref(),
+        &profile_id,
+        &new_wrapping_key,
+        &vault_key,
+    )?;
+
+    Ok(true)
+}
+
 pub fn persist_active_vault(state: &Arc<AppState>) -> Result<Option<String>> {
     let _flight_guard = state
         .vault_persist_guard
@@ -134,9 +325,24 @@ pub fn persist_active_vault(state: &Arc<AppState>) -> Result<Option<String>> {
     };
 
     if let Some((profile_id, key_material, bytes)) = maybe_bytes_and_meta {
+        let blob_storage = state.get_vault_blob_storage()?;
         let storage_paths = state.get_storage_paths()?;
-        let encrypted = cipher::encrypt_vault_blob(&profile_id, &key_material, &bytes)?;
-        cipher::write_encrypted_file(&vault_db_path(&storage_paths, &profile_id)?, &encrypted)?;
+        let chunk_store = VaultChunkStore::new(&storage_paths, &profile_id)?;
+
+        let chunk_hashes = chunk_store.put_chunks(&profile_id, &key_material, &bytes)?;
+        let manifest = VaultManifest {
+            chunk_hashes: chunk_hashes.clone(),
+        };
+        let manifest_bytes = serde_json::to_vec(&manifest)
+            .map_err(|_| ErrorCodeString::new("VAULT_MANIFEST_SERIALIZE_FAILED"))?;
+        let encrypted_manifest = cipher::encrypt_vault_blob(&profile_id, &key_material, &manifest_bytes)?;
+        blob_storage.write_blob(&profile_id, VaultBlobKind::VaultDb, &encrypted_manifest)?;
+
+        // Only GC once the manifest pointing at `chunk_hashes` is durably
+        // written, so a crash mid-GC can never leave a referenced chunk
+        // missing.
+        chunk_store.gc(&chunk_hashes.into_iter().collect())?;
+
         return Ok(Some(profile_id));
     }
 
@@ -244,3 +450,10 @@ pub fn auto_lock_cleanup(state: &Arc<AppState>) -> Result<bool> {
 pub fn health_check() -> Result<bool> {
     Ok(true)
 }
+
+/// Diagnostic: the active profile's on-disk schema version — see
+/// `data::sqlite::repo_impl::db_schema_version`.
+pub fn db_schema_version(state: &Arc<AppState>) -> Result<i32> {
+    let profile_id = require_unlocked_active_profile(state)?.profile_id;
+    repo_impl::db_schema_version(state, &profile_id)
+}
@@ -6,24 +6,11 @@ use crate::data::fs::atomic_write::write_atomic;
 use crate::data::profiles::paths::user_settings_path;
 use crate::data::storage_paths::StoragePaths;
 use crate::error::{ErrorCodeString, Result};
+use crate::services::security_service;
 use crate::types::UserSettings;
 
 fn require_logged_in(state: &Arc<AppState>) -> Result<String> {
-    let active_profile = state
-        .active_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-    let logged_in_profile = state
-        .logged_in_profile
-        .lock()
-        .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?
-        .clone();
-
-    match (active_profile, logged_in_profile) {
-        (Some(active), Some(logged)) if active == logged => Ok(active),
-        _ => Err(ErrorCodeString::new("VAULT_LOCKED")),
-    }
+    Ok(security_service::require_unlocked_active_profile(state)?.profile_id)
 }
 
 fn validate_settings(settings: &UserSettings) -> Result<()> {
@@ -34,6 +21,7 @@ fn validate_settings(settings: &UserSettings) -> Result<()> {
         in_range(settings.clipboard_clear_timeout_seconds, 1, 600),
         in_range(settings.auto_lock_timeout, 30, 86_400),
         in_range(settings.trash_retention_days, 1, 3_650),
+        in_range(settings.password_history_retention_count, 1, 1_000),
         in_range(settings.backup_retention_days, 1, 3_650),
     ]
     .into_iter()
@@ -44,13 +32,7 @@ fn validate_settings(settings: &UserSettings) -> Result<()> {
         true
     };
 
-    let valid_frequency =
-        ["daily", "weekly", "monthly"].contains(&settings.backup_frequency.as_str());
-    let valid_sort_field =
-        ["created_at", "updated_at", "title"].contains(&settings.default_sort_field.as_str());
-    let valid_sort_direction = ["ASC", "DESC"].contains(&settings.default_sort_direction.as_str());
-
-    if valid_values && valid_auto_backup_interval && valid_frequency && valid_sort_field && valid_sort_direction {
+    if valid_values && valid_auto_backup_interval {
         Ok(())
     } else {
         Err(ErrorCodeString::new("SETTINGS_VALIDATION_FAILED"))
@@ -0,0 +1,142 @@
+//! Cross-profile item sharing: lets one profile hand a single `DataCard` to
+//! another profile (in the same workspace, or a different one entirely)
+//! without exposing its vault. See `data::crypto::sharing` for the
+//! underlying x25519 + AES-GCM primitives.
+
+use std::fs;
+use std::sync::Arc;
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::app_state::AppState;
+use crate::data::crypto::sharing;
+use crate::data::fs::atomic_write::write_atomic;
+use crate::data::profiles::paths::share_identity_path;
+use crate::data::sqlite::repo_impl;
+use crate::error::{ErrorCodeString, Result};
+use crate::services::security_service;
+use crate::types::{CreateDataCardInput, DataCard};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredIdentity {
+    secret_key_b64: String,
+}
+
+/// A shared item in transit. `sender_public_key_b64` lets the recipient
+/// derive the same shared key the sender used; `envelope_b64` is
+/// `nonce || ciphertext` from `sharing::encrypt_envelope`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedDataCardEnvelope {
+    pub sender_public_key_b64: String,
+    pub datacard_id: String,
+    pub envelope_b64: String,
+}
+
+fn load_or_create_identity(state: &Arc<AppState>, profile_id: &str) -> Result<StaticSecret> {
+    let storage_paths = state.get_storage_paths()?;
+    let path = share_identity_path(&storage_paths, profile_id)?;
+
+    if path.exists() {
+        let content = fs::read_to_string(&path).map_err(|_| ErrorCodeString::new("SHARE_IDENTITY_READ_FAILED"))?;
+        let stored: StoredIdentity =
+            serde_json::from_str(&content).map_err(|_| ErrorCodeString::new("SHARE_IDENTITY_CORRUPTED"))?;
+        let secret_bytes = general_purpose::STANDARD
+            .decode(&stored.secret_key_b64)
+            .map_err(|_| ErrorCodeString::new("SHARE_IDENTITY_CORRUPTED"))?;
+        let secret_array: [u8; 32] = secret_bytes
+            .try_into()
+            .map_err(|_| ErrorCodeString::new("SHARE_IDENTITY_CORRUPTED"))?;
+        return Ok(StaticSecret::from(secret_array));
+    }
+
+    let secret = sharing::generate_identity();
+    let stored = StoredIdentity {
+        secret_key_b64: general_purpose::STANDARD.encode(secret.to_bytes()),
+    };
+    let serialized =
+        serde_json::to_string_pretty(&stored).map_err(|_| ErrorCodeString::new("SHARE_IDENTITY_WRITE_FAILED"))?;
+    write_atomic(&path, serialized.as_bytes()).map_err(|_| ErrorCodeString::new("SHARE_IDENTITY_WRITE_FAILED"))?;
+    Ok(secret)
+}
+
+/// Returns this profile's base64-encoded x25519 public key, generating and
+/// persisting its identity keypair on first use.
+pub fn get_public_key(state: &Arc<AppState>) -> Result<String> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let secret = load_or_create_identity(state, &profile_id)?;
+    let public = sharing::public_key_of(&secret);
+    Ok(general_purpose::STANDARD.encode(public.as_bytes()))
+}
+
+fn decode_public_key(b64: &str) -> Result<PublicKey> {
+    let bytes = general_purpose::STANDARD
+        .decode(b64)
+        .map_err(|_| ErrorCodeString::new("SHARE_PUBLIC_KEY_INVALID"))?;
+    let array: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| ErrorCodeString::new("SHARE_PUBLIC_KEY_INVALID"))?;
+    Ok(PublicKey::from(array))
+}
+
+pub fn share_datacard(
+    state: &Arc<AppState>,
+    datacard_id: String,
+    recipient_public_key_b64: String,
+) -> Result<SharedDataCardEnvelope> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let card = repo_impl::get_datacard(state, &profile_id, &datacard_id)?;
+
+    let our_secret = load_or_create_identity(state, &profile_id)?;
+    let recipient_public = decode_public_key(&recipient_public_key_b64)?;
+    let shared_key = sharing::derive_shared_key(&our_secret, &recipient_public);
+
+    let plaintext =
+        serde_json::to_vec(&card).map_err(|_| ErrorCodeString::new("SHARE_SERIALIZE_FAILED"))?;
+    let envelope = sharing::encrypt_envelope(&shared_key, card.id.as_bytes(), &plaintext)?;
+
+    let our_public = sharing::public_key_of(&our_secret);
+    Ok(SharedDataCardEnvelope {
+        sender_public_key_b64: general_purpose::STANDARD.encode(our_public.as_bytes()),
+        datacard_id: card.id,
+        envelope_b64: general_purpose::STANDARD.encode(envelope),
+    })
+}
+
+/// Decrypts an incoming `SharedDataCardEnvelope` and imports it as a new
+/// item in the recipient's active vault.
+pub fn accept_shared_datacard(
+    state: &Arc<AppState>,
+    shared: SharedDataCardEnvelope,
+) -> Result<DataCard> {
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let our_secret = load_or_create_identity(state, &profile_id)?;
+    let sender_public = decode_public_key(&shared.sender_public_key_b64)?;
+    let shared_key = sharing::derive_shared_key(&our_secret, &sender_public);
+
+    let envelope = general_purpose::STANDARD
+        .decode(&shared.envelope_b64)
+        .map_err(|_| ErrorCodeString::new("SHARE_ENVELOPE_CORRUPTED"))?;
+    let plaintext = sharing::decrypt_envelope(&shared_key, shared.datacard_id.as_bytes(), &envelope)?;
+    let card: DataCard =
+        serde_json::from_slice(&plaintext).map_err(|_| ErrorCodeString::new("SHARE_ENVELOPE_CORRUPTED"))?;
+
+    let input = CreateDataCardInput {
+        title: card.title,
+        url: card.url,
+        email: card.email,
+        username: card.username,
+        mobile_phone: card.mobile_phone,
+        note: card.note,
+        tags: card.tags,
+        password: card.password,
+        bank_card: card.bank_card,
+        custom_fields: card.custom_fields,
+        folder_id: None,
+        uri_match: card.uri_match,
+        totp_uri: card.totp_uri,
+    };
+    repo_impl::create_datacard(state, &profile_id, &input)
+}
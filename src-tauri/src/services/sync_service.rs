@@ -0,0 +1,136 @@
+//! Opt-in remote sync of the active vault's encrypted blob through whatever
+//! `VaultSyncTransport` is installed on `AppState` (see `data::sync`). The
+//! blob pushed/pulled here is the same already-encrypted file
+//! `security_service` reads and writes, so sync never has access to
+//! plaintext and never needs its own key material.
+//!
+//! This file used to also gateway onto two *other* cross-device mechanisms
+//! explored for the same problem — `data::sync::vault_log`'s HLC-clocked
+//! merge log and `data::sqlite::crdt`'s cr-sqlite replica — alongside
+//! `services::oplog_service`'s per-device ack cursors, all three behind
+//! `CROSS_DEVICE_SYNC_WIRED`. That's now resolved down to one path:
+//! `vault_log` is the merge log a real cross-device sync feature will
+//! finish wiring (it already has everything that needs — real AEAD, HLC
+//! ordering, checkpoint+compaction — see `record_vault_operation`/
+//! `load_vault_log_checkpoint` below, both still gated until a mutation
+//! path and a peer-merge replay actually call them). `crdt`'s cr-sqlite
+//! replica has been dropped from this service and from the command surface
+//! (see `commands::sync`) — it stays in the tree only as the reference
+//! column-level-merge prototype its own module doc already describes,
+//! with no remaining caller. `oplog` keeps doing exactly what it already
+//! does well (local compaction, per-device cursors for incremental pulls)
+//! and isn't part of the peer-merge question at all.
+
+use std::fs;
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::app_state::AppState;
+use crate::data::profiles::paths::vault_db_path;
+use crate::data::sync::vault_log;
+use crate::error::{ErrorCodeString, Result};
+use crate::services::security_service;
+
+/// Pushes the active profile's current encrypted vault file to the
+/// configured transport. Returns `false` (not an error) if sync isn't
+/// configured, so callers can treat it as a no-op.
+pub fn push_active_vault(state: &Arc<AppState>) -> Result<bool> {
+    let transport = {
+        let slot = state
+            .sync_transport
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        match slot.as_ref() {
+            Some(transport) => transport.clone(),
+            None => return Ok(false),
+        }
+    };
+
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    security_service::persist_active_vault(state)?;
+
+    let storage_paths = state.get_storage_paths()?;
+    let encrypted = fs::read(vault_db_path(&storage_paths, &profile_id)?)
+        .map_err(|_| ErrorCodeString::new("SYNC_READ_FAILED"))?;
+
+    transport.push(&profile_id, &encrypted)?;
+    Ok(true)
+}
+
+/// Pulls the remote copy of the active profile's vault down and overwrites
+/// the local encrypted file with it. Returns `false` if sync isn't
+/// configured or the remote has nothing for this profile yet.
+pub fn pull_active_vault(state: &Arc<AppState>) -> Result<bool> {
+    let transport = {
+        let slot = state
+            .sync_transport
+            .lock()
+            .map_err(|_| ErrorCodeString::new("STATE_UNAVAILABLE"))?;
+        match slot.as_ref() {
+            Some(transport) => transport.clone(),
+            None => return Ok(false),
+        }
+    };
+
+    let profile_id = security_service::require_unlocked_active_profile(state)?.profile_id;
+    let Some(encrypted) = transport.pull(&profile_id)? else {
+        return Ok(false);
+    };
+
+    let storage_paths = state.get_storage_paths()?;
+    crate::data::sqlite::pool::clear_pool(&profile_id);
+    crate::data::fs::atomic_write::write_atomic(&vault_db_path(&storage_paths, &profile_id)?, &encrypted)
+        .map_err(|_| ErrorCodeString::new("SYNC_WRITE_FAILED"))?;
+
+    Ok(true)
+}
+
+/// `vault_log::append_operation` isn't wired into a real mutation path yet:
+/// nothing in `datacards_service`/`folders_service`/`attachments_service`
+/// calls `record_vault_operation` on write, and nothing replays a peer's
+/// log back into the materialized vault on login. Both entry points below
+/// check this flag first and fail clearly rather than quietly handing back
+/// a log no other device's edits will ever reach — flip it once that
+/// wiring lands.
+const CROSS_DEVICE_SYNC_WIRED: bool = false;
+
+/// Appends `op` to the active profile's CRDT-style sync log under a clock
+/// minted for `device_id` — see `data::sync::vault_log`. Thin service-layer
+/// entry point over `vault_log::append_operation`; not yet called from any
+/// mutation path, see that function's doc comment for where it plugs in.
+pub fn record_vault_operation(
+    state: &Arc<AppState>,
+    device_id: &str,
+    op: &vault_log::SyncOperation,
+) -> Result<vault_log::Hlc> {
+    if !CROSS_DEVICE_SYNC_WIRED {
+        return Err(ErrorCodeString::new("SYNC_NOT_YET_IMPLEMENTED"));
+    }
+    let session = security_service::require_unlocked_active_profile(state)?;
+    let key = session
+        .vault_key
+        .ok_or_else(|| ErrorCodeString::new("VAULT_KEY_UNAVAILABLE"))?;
+    vault_log::append_operation(
+        state,
+        &session.profile_id,
+        device_id,
+        &key,
+        Utc::now().timestamp_millis(),
+        op,
+    )
+}
+
+/// Loads the active profile's sync log from its newest checkpoint forward
+/// — see `vault_log::load_since_checkpoint`. `Ok(None)` means the log has
+/// no checkpoint yet (not enough operations recorded so far).
+pub fn load_vault_log_checkpoint(state: &Arc<AppState>) -> Result<Option<vault_log::ReplayState>> {
+    if !CROSS_DEVICE_SYNC_WIRED {
+        return Err(ErrorCodeString::new("SYNC_NOT_YET_IMPLEMENTED"));
+    }
+    let session = security_service::require_unlocked_active_profile(state)?;
+    let key = session
+        .vault_key
+        .ok_or_else(|| ErrorCodeString::new("VAULT_KEY_UNAVAILABLE"))?;
+    vault_log::load_since_checkpoint(state, &session.profile_id, &key)
+}
@@ -1,25 +1,42 @@
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct ProfileMeta {
     pub id: String,
     pub name: String,
     pub has_password: bool,
+    pub keychain_backed: bool,
+    /// Whether a TOTP secret is enrolled for this profile, requiring a
+    /// 6-digit token alongside the password to unlock it. See
+    /// `profiles_service::enroll_totp`.
+    pub has_totp: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct AttachmentMeta {
     pub id: String,
     pub datacard_id: String,
     pub file_name: String,
     pub mime_type: Option<String>,
     pub byte_size: i64,
+    /// SHA-256 digest (hex) of the plaintext, and the key the encrypted
+    /// blob is stored under on disk. Shared by every attachment row whose
+    /// content is identical, so the blob itself is only stored once; see
+    /// `data::sqlite::repo_impl::count_attachments_by_content_hash`.
+    pub content_hash: String,
+    /// Source file's last-modified time, if the filesystem reported one.
+    /// `save_attachment_to_path` restores this onto the extracted copy.
+    pub source_mtime: Option<String>,
     pub created_at: String,
     pub updated_at: String,
     pub deleted_at: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct AttachmentPreviewPayload {
     pub attachment_id: String,
     pub file_name: String,
@@ -28,12 +45,71 @@ pub struct AttachmentPreviewPayload {
     pub base64_data: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Serializes a `Vec<u8>` as a base64 string on the wire — the same
+/// approach `data::crypto::kdf`'s `salt_b64` module uses for a raw KDF
+/// salt — so `AttachmentRangePayload::data` never has to round-trip as a
+/// JSON array of numbers, only as plain bytes in memory on this side.
+mod as_base64 {
+    use base64::engine::general_purpose;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+        general_purpose::STANDARD.encode(bytes).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(d)?;
+        general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Same as `as_base64`, for a field that's sometimes absent entirely
+/// (`ChangeRow::val` is `None` for a column cr-sqlite recorded as deleted
+/// rather than set to some value) rather than present-but-empty.
+mod as_base64_opt {
+    use base64::engine::general_purpose;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, s: S) -> std::result::Result<S::Ok, S::Error> {
+        bytes.as_ref().map(|b| general_purpose::STANDARD.encode(b)).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<Vec<u8>>, D::Error> {
+        let encoded: Option<String> = Option::deserialize(d)?;
+        encoded
+            .map(|value| general_purpose::STANDARD.decode(value).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// One byte range of an attachment's decrypted content, for paging through
+/// large attachments instead of fetching the whole file as one
+/// `AttachmentPreviewPayload::base64_data` string. `offset` and the actual
+/// length of `data` may be smaller than requested, if the range ran past
+/// `total_byte_size`.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct AttachmentRangePayload {
+    pub attachment_id: String,
+    pub offset: i64,
+    #[serde(with = "as_base64")]
+    #[ts(type = "string")]
+    pub data: Vec<u8>,
+    pub total_byte_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct ProfilesList {
     pub profiles: Vec<ProfileMeta>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct Folder {
     pub id: String,
     pub name: String,
@@ -44,7 +120,8 @@ pub struct Folder {
     pub deleted_at: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct BankCard {
     pub holder: String,
     pub number: String,
@@ -53,7 +130,8 @@ pub struct BankCard {
     pub note: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../bindings/")]
 #[serde(rename_all = "lowercase")]
 pub enum CustomFieldType {
     Text,
@@ -61,9 +139,14 @@ pub enum CustomFieldType {
     Url,
     Number,
     Date,
+    /// Value is a TOTP seed: either a bare Base32 secret or a full
+    /// `otpauth://totp/...` URI. See `data::crypto::totp::parse_secret_or_uri`
+    /// and the `get_custom_field_totp_code` command.
+    Totp,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct CustomField {
     pub key: String,
     pub value: String,
@@ -71,7 +154,47 @@ pub struct CustomField {
     pub field_type: CustomFieldType,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A freshly computed TOTP code plus how many seconds it remains valid
+/// for, returned by `get_custom_field_totp_code`.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct TotpCodeResult {
+    pub code: String,
+    pub seconds_remaining: u64,
+}
+
+/// How a datacard's `url` is matched against a page the browser extension
+/// asks about, modeled on Bitwarden's URI match types. `origin_matches_url`
+/// dispatches on this instead of always applying the base-domain/subdomain
+/// heuristic.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../bindings/")]
+#[serde(rename_all = "snake_case")]
+pub enum UriMatchMode {
+    /// Same base domain or a subdomain of it, scheme and port must match
+    /// (the only behavior before match modes existed).
+    Domain,
+    /// Exact host and port, no subdomain expansion.
+    Host,
+    /// The requested URL, as a plain string, starts with the stored `url`.
+    StartsWith,
+    /// The requested URL equals the stored `url` after normalization.
+    Exact,
+    /// The stored `url` is compiled as a regular expression and matched
+    /// against the full requested URL.
+    RegularExpression,
+    /// Never offered for autofill, regardless of the requested URL.
+    Never,
+}
+
+impl Default for UriMatchMode {
+    fn default() -> Self {
+        UriMatchMode::Domain
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct DataCard {
     pub id: String,
     pub folder_id: Option<String>,
@@ -84,6 +207,8 @@ pub struct DataCard {
     pub note: Option<String>,
     pub is_favorite: bool,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub uri_match: UriMatchMode,
 
     pub created_at: String,
     pub updated_at: String,
@@ -92,9 +217,26 @@ pub struct DataCard {
     pub password: Option<String>,
     pub bank_card: Option<BankCard>,
     pub custom_fields: Vec<CustomField>,
+    /// An `otpauth://totp/...` URI, if this card has a TOTP secret enrolled.
+    /// Never included in `DataCardSummary` — same as `password`, it's only
+    /// handed out when a caller fetches the full card.
+    pub totp_uri: Option<String>,
+}
+
+/// Outcome of `attachments_service::purge_expired`: how many soft-deleted
+/// attachment rows past the profile's `trash_retention_days` were
+/// hard-deleted, and how many of their content-addressed blob files were
+/// actually freed on disk (a blob shared with a still-live attachment isn't
+/// removed, so this can be lower than `rows_purged`).
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct AttachmentPurgeReport {
+    pub rows_purged: usize,
+    pub files_removed: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct PasswordHistoryRow {
     pub id: String,
     pub datacard_id: String,
@@ -102,7 +244,63 @@ pub struct PasswordHistoryRow {
     pub created_at: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Result of `password_exposure_service::check_password_exposure` for one
+/// datacard: which other datacards (by id) reuse the same password, and —
+/// only when the caller opted into the network check — how many times
+/// HaveIBeenPwned has seen that password in a breach. `breach_count` is
+/// `None` when the breach check wasn't requested, `Some(0)` when it was
+/// requested and came back clean.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct PasswordExposureReport {
+    pub reused_datacard_ids: Vec<String>,
+    pub breach_count: Option<u64>,
+}
+
+/// One row of `audit_log`, captured by a SQLite trigger rather than by the
+/// code path that performed the edit/delete — see
+/// `data::sqlite::migrations::migrate_v7_to_v8_audit_log`. `old_value_json`
+/// is the full prior row serialized with SQLite's `json_object()`, still
+/// JSON-encoded here; callers that need it structured parse it themselves,
+/// same as `DataCard::bank_card_json` is handled one layer up.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct AuditLogEntry {
+    pub id: String,
+    pub table_name: String,
+    pub row_id: String,
+    pub action: String,
+    pub old_value_json: String,
+    pub changed_at: String,
+}
+
+/// One column-level change from cr-sqlite's `crsql_changes` virtual table
+/// — see `data::sqlite::crdt`. `pk`/`val`/`site_id` are raw bytes cr-sqlite
+/// hands back (a serialized primary key, the column's new value, and a
+/// site's 16-byte identity respectively), so they go over the wire as
+/// base64 the same way `AttachmentRangePayload::data` does.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct ChangeRow {
+    pub table: String,
+    #[serde(with = "as_base64")]
+    #[ts(type = "string")]
+    pub pk: Vec<u8>,
+    pub cid: String,
+    #[serde(with = "as_base64_opt", default)]
+    #[ts(type = "string | null")]
+    pub val: Option<Vec<u8>>,
+    pub col_version: i64,
+    pub db_version: i64,
+    #[serde(with = "as_base64")]
+    #[ts(type = "string")]
+    pub site_id: Vec<u8>,
+    pub cl: i64,
+    pub seq: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct DataCardSummary {
     pub id: String,
     pub folder_id: Option<String>,
@@ -111,31 +309,55 @@ pub struct DataCardSummary {
     pub email: Option<String>,
     pub username: Option<String>,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub uri_match: UriMatchMode,
     pub created_at: String,
     pub updated_at: String,
     pub deleted_at: Option<String>,
     pub is_favorite: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct CreateFolderInput {
     pub name: String,
     pub parent_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct RenameFolderInput {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct MoveFolderInput {
     pub id: String,
     pub parent_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct MoveFoldersInput {
+    pub ids: Vec<String>,
+    pub parent_id: Option<String>,
+}
+
+/// Outcome of one id within a batch folder operation (`*_folders` in
+/// `folders_service`). Batches report per-id success rather than a single
+/// bool so the UI can show which of a multi-select actually failed.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
+pub struct FolderBatchItemResult {
+    pub id: String,
+    pub success: bool,
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct CreateDataCardInput {
     pub title: String,
     pub url: Option<String>,
@@ -148,9 +370,14 @@ pub struct CreateDataCardInput {
     pub bank_card: Option<BankCard>,
     pub custom_fields: Vec<CustomField>,
     pub folder_id: Option<String>,
+    #[serde(default)]
+    pub uri_match: UriMatchMode,
+    #[serde(default)]
+    pub totp_uri: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct UpdateDataCardInput {
     pub id: String,
     pub title: String,
@@ -164,21 +391,77 @@ pub struct UpdateDataCardInput {
     pub bank_card: Option<BankCard>,
     pub custom_fields: Vec<CustomField>,
     pub folder_id: Option<String>,
+    #[serde(default)]
+    pub uri_match: UriMatchMode,
+    #[serde(default)]
+    pub totp_uri: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct MoveDataCardInput {
     pub id: String,
     pub folder_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct SetDataCardFavoriteInput {
     pub id: String,
     pub is_favorite: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Column the vault's default list view is sorted by. Drives
+/// `data::sqlite::repo_impl::order_clause`, which matches on this
+/// exhaustively instead of string-comparing a raw column name.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../bindings/")]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Title,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Default for SortField {
+    fn default() -> Self {
+        SortField::UpdatedAt
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../bindings/")]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl Default for SortDirection {
+    fn default() -> Self {
+        SortDirection::Desc
+    }
+}
+
+/// How often `backup_service::backup_create_if_due_auto` checks whether a
+/// new automatic backup is due.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../bindings/")]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Default for BackupFrequency {
+    fn default() -> Self {
+        BackupFrequency::Weekly
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../bindings/")]
 pub struct UserSettings {
     pub auto_hide_secret_timeout_seconds: i64,
     pub auto_lock_enabled: bool,
@@ -190,15 +473,20 @@ pub struct UserSettings {
     pub soft_delete_enabled: bool,
     pub trash_retention_days: i64,
 
+    /// How many rows of `datacard_password_history` are kept per datacard;
+    /// `insert_password_history` prunes older rows past this count every
+    /// time a card's password changes.
+    pub password_history_retention_count: i64,
+
     pub backups_enabled: bool,
-    pub backup_frequency: String,
+    pub backup_frequency: BackupFrequency,
     pub backup_retention_days: i64,
 
     pub default_export_dir: Option<String>,
     pub last_export_dir: Option<String>,
 
-    pub default_sort_field: String,
-    pub default_sort_direction: String,
+    pub default_sort_field: SortField,
+    pub default_sort_direction: SortDirection,
 
     pub mask_password_by_default: bool,
 }
@@ -213,13 +501,14 @@ impl Default for UserSettings {
             clipboard_clear_timeout_seconds: 30,
             soft_delete_enabled: true,
             trash_retention_days: 30,
+            password_history_retention_count: 10,
             backups_enabled: false,
-            backup_frequency: "weekly".to_string(),
+            backup_frequency: BackupFrequency::default(),
             backup_retention_days: 30,
             default_export_dir: None,
             last_export_dir: None,
-            default_sort_field: "updated_at".to_string(),
-            default_sort_direction: "DESC".to_string(),
+            default_sort_field: SortField::default(),
+            default_sort_direction: SortDirection::default(),
             mask_password_by_default: true,
         }
     }